@@ -4,6 +4,7 @@ use std::collections::HashMap;
 
 /// Struct the describes the branch and bound tree nodes
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QuboBBNode {
     pub lower_bound: f64,
     pub solution: Array1<f64>,
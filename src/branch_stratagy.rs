@@ -11,6 +11,9 @@ pub enum BranchStrategy {
     BestApproximation,
 }
 
+/// The branching strategy selected in the solver options, serialized as the name of the variant
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BranchStrategySelection {
     FirstNotFixed,
     MostViolated,
@@ -41,7 +44,11 @@ impl BranchStrategy {
     }
 }
 
-/// #Panics if the node does not have an unfixed variable
+/// Branches on the first variable that is not fixed
+///
+/// # Panics
+///
+/// Will panic if the node does not have an unfixed variable.
 pub fn first_not_fixed(solver: &BBSolver, node: &QuboBBNode) -> usize {
     // scan through the variables and find the first one that is not fixed
     for i in 0..solver.qubo.num_x() {
@@ -70,6 +77,11 @@ pub fn most_violated(solver: &BBSolver, node: &QuboBBNode) -> usize {
     index_most_violated
 }
 
+/// Branches on a random variable that is not fixed, seeded by the solver seed and the number of visited nodes
+///
+/// # Panics
+///
+/// Will panic if the random index does not fit in a usize, which can only happen on a 32-bit target.
 pub fn random(solver: &BBSolver, node: &QuboBBNode) -> usize {
     // generate a prng
     let mut prng = PRNG {
@@ -14,6 +14,9 @@ pub trait SubProblemSolver {
     fn solve_lower_bound(&self, bbsolver: &BBSolver, node: &QuboBBNode) -> SubProblemResult;
 }
 
+/// The subproblem solver selected in the solver options, serialized as the name of the variant
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SubProblemSelection {
     Clarabel,
 }
@@ -31,6 +34,7 @@ pub fn get_sub_problem_solver(
 pub struct ClarabelSubProblemSolver {
     q: CscMatrix,
     c: Array1<f64>,
+    offset: f64,
 }
 
 impl SubProblemSolver for ClarabelSubProblemSolver {
@@ -39,6 +43,7 @@ impl SubProblemSolver for ClarabelSubProblemSolver {
         Self {
             q: q_new,
             c: qubo.c.clone(),
+            offset: qubo.offset,
         }
     }
 
@@ -93,7 +98,11 @@ impl SubProblemSolver for ClarabelSubProblemSolver {
         // solve the optimization problem
         solver.solve();
 
-        (solver.solution.obj_val, Array1::from(solver.solution.x))
+        // the QP solver does not know about the constant term, so we add it back in
+        (
+            solver.solution.obj_val + self.offset,
+            Array1::from(solver.solution.x),
+        )
     }
 }
 
@@ -9,9 +9,11 @@ use crate::branch_subproblem::{
 };
 use crate::branchbound_utils::{check_integer_feasibility, get_current_time};
 use crate::branchboundlogger::SolverOutputLogger;
+use crate::constraint_reduction::ImplicationGraph;
 use crate::early_termination::beck_proof;
 use crate::lower_bound::li_lower_bound;
-use crate::preprocess::preprocess_qubo;
+use crate::persistence::compute_roof_duality;
+use crate::preprocess::{preprocess_with_implications, propagate_fixed_variables};
 use crate::solver_options::SolverOptions;
 use std::collections::BinaryHeap;
 
@@ -30,6 +32,23 @@ pub struct BBSolver {
     pub options: SolverOptions,
     pub early_stop: bool,
     pub solver_logger: SolverOutputLogger,
+    /// The pairwise rules found by the preprocessor, used to fix variables in each node
+    pub implications: ImplicationGraph,
+}
+
+/// The result of a B&B solve, the best solution found along with the search statistics
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BBSolverResult {
+    /// The best solution found
+    pub solution: Array1<usize>,
+    /// The objective value of the best solution
+    pub objective: f64,
+    /// The wall time of the solve, in seconds
+    pub time: f64,
+    pub nodes_visited: usize,
+    pub nodes_processed: usize,
+    pub nodes_solved: usize,
 }
 
 pub enum Event {
@@ -61,6 +80,9 @@ impl BBSolver {
         // create auxiliary variables
         let num_x = qubo.num_x();
 
+        // the all zeros starting solution has an objective value equal to the constant offset
+        let zero_solution_value = qubo.offset;
+
         let subproblem_solver = get_sub_problem_solver(&qubo, &options.sub_problem_solver);
         let branch_strategy = BranchStrategy::get_branch_strategy(&options.branch_strategy);
         let start_time = get_current_time();
@@ -69,7 +91,7 @@ impl BBSolver {
         Self {
             qubo,
             best_solution: Array1::zeros(num_x),
-            best_solution_value: 0.0,
+            best_solution_value: zero_solution_value,
             nodes: BinaryHeap::new(),
             nodes_processed: 0,
             nodes_visited: 0,
@@ -80,6 +102,7 @@ impl BBSolver {
             options,
             early_stop: false,
             solver_logger: SolverOutputLogger { output_level },
+            implications: ImplicationGraph::new(num_x),
         }
     }
 
@@ -92,12 +115,23 @@ impl BBSolver {
     /// The main solve function of the B&B algorithm
     pub fn solve(&mut self) -> (Array1<usize>, f64) {
         // preprocess the problem
-        let fixed_variables = preprocess_qubo(&self.qubo, &self.options.fixed_variables);
+        let mut probing_options = self.options.probing.clone();
+        probing_options.incumbent = Some(self.best_solution_value);
+
+        let (fixed_variables, implications) = preprocess_with_implications(
+            &self.qubo,
+            &self.options.fixed_variables,
+            &probing_options,
+        );
         self.options.fixed_variables = fixed_variables.clone();
+        self.implications = implications;
+
+        // the roof dual is a lower bound at the root node
+        let roof_dual = compute_roof_duality(&self.qubo, &fixed_variables).lower_bound;
 
         // create the root node
         let root_node = QuboBBNode {
-            lower_bound: f64::NEG_INFINITY,
+            lower_bound: roof_dual,
             solution: Array1::zeros(self.qubo.num_x()),
             fixed_variables,
         };
@@ -112,8 +146,8 @@ impl BBSolver {
         // display the header
         self.solver_logger.output_header(self);
 
-        // if the best solution is negative, then we output the warm start information
-        if self.best_solution_value < 0.0 {
+        // if the best solution is better than the all zeros solution, then we output the warm start information
+        if self.best_solution_value < self.qubo.offset {
             self.solver_logger.output_warm_start_info(self);
         }
 
@@ -143,6 +177,18 @@ impl BBSolver {
         (self.best_solution.clone(), self.best_solution_value)
     }
 
+    /// Collects the best solution found and the search statistics of the solver
+    pub fn result(&self) -> BBSolverResult {
+        BBSolverResult {
+            solution: self.best_solution.clone(),
+            objective: self.best_solution_value,
+            time: get_current_time() - self.time_start,
+            nodes_visited: self.nodes_visited,
+            nodes_processed: self.nodes_processed,
+            nodes_solved: self.nodes_solved,
+        }
+    }
+
     /// Checks if we can prune the node, based on the lower bound and best solution, returns an action
     pub fn can_prune_action(&self, node: &QuboBBNode) -> (PruneAction, Event) {
         // if our parent solution is above our current feasible soltion then prune
@@ -195,8 +241,18 @@ impl BBSolver {
         // create a mutable copy of the node
         let mut node = node.clone();
 
-        // pass to the presolver to see if there are any variables we can fix
-        node.fixed_variables = preprocess_qubo(&self.qubo, &node.fixed_variables);
+        // pass to the presolver to see if there are any variables we can fix, if the fixed variables contradict the
+        // rules of the preprocessor, then there is no optimal solution in this node, and we can prune it
+        let Some(fixed_variables) =
+            propagate_fixed_variables(&self.qubo, &node.fixed_variables, &self.implications)
+        else {
+            return ProcessNodeState {
+                prune_action: PruneAction::Prune,
+                event: Some(Event::Nill),
+                logging: NodeLoggingAction::Processed,
+            };
+        };
+        node.fixed_variables = fixed_variables;
 
         // calculate the lower bound via the li lower bound formula
         let li_bound = li_lower_bound(&self.qubo, &node.fixed_variables);
@@ -403,10 +459,10 @@ mod tests {
     use crate::preprocess::preprocess_qubo;
     use crate::qubo::Qubo;
     use crate::solver_options::SolverOptions;
-    use crate::tests::{make_solver_qubo, make_test_prng};
+    use crate::tests::{brute_force_min, make_solver_qubo, make_test_prng};
     use crate::{branchbound, local_search};
     use ndarray::Array1;
-    use sprs::CsMat;
+    use sprs::{CsMat, TriMat};
     use std::collections::HashMap;
 
     pub fn get_default_solver_options() -> SolverOptions {
@@ -484,4 +540,96 @@ mod tests {
             assert_eq!(val, solution[index]);
         }
     }
+
+    #[test]
+    fn branch_bound_with_probing_finds_optimum() {
+        let mut prng = make_test_prng();
+        let mut num_implications = 0;
+
+        for _ in 0..10 {
+            // independent sets on disjoint triangles with weak couplings between them, where x_i = 1 forces the rest
+            // of its triangle to 0, so probing finds x_i + x_j <= 1 implications that every node propagates
+            let num_x = 15;
+            let mut q = TriMat::new((num_x, num_x));
+            for t in 0..num_x / 3 {
+                for (i, j) in [(0, 1), (1, 2), (0, 2)] {
+                    q.add_triplet(3 * t + i, 3 * t + j, 6.0);
+                    q.add_triplet(3 * t + j, 3 * t + i, 6.0);
+                }
+            }
+            for i in 0..num_x {
+                for j in (i / 3 + 1) * 3..num_x {
+                    let weight = prng.gen_f64() - 0.5;
+                    q.add_triplet(i, j, weight);
+                    q.add_triplet(j, i, weight);
+                }
+            }
+            let c = Array1::from_shape_fn(num_x, |_| -1.0 - 0.5 * prng.gen_f64());
+            let p = Qubo::new_with_c(q.to_csr(), c);
+
+            // probing is on by default
+            let mut options = get_default_solver_options();
+            options.verbose = 0;
+
+            let mut solver = branchbound::BBSolver::new(p.convex_symmetric_form(), options);
+            let (solution, value) = solver.solve();
+
+            assert!((value - brute_force_min(&p)).abs() < 1E-8);
+            assert!((p.eval_usize(&solution) - value).abs() < 1E-8);
+            num_implications += solver.implications.len();
+        }
+
+        assert!(num_implications > 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn solver_options_serde() {
+        let mut options = get_default_solver_options();
+        options.set_branch_strategy(Some("WorstApproximation".to_string()));
+        options.fixed_variables = HashMap::from([(0, 1), (3, 0)]);
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["branch_strategy"], "WorstApproximation");
+        assert_eq!(json["sub_problem_solver"], "Clarabel");
+
+        let options_json: SolverOptions = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            options_json.branch_strategy,
+            BranchStrategySelection::WorstApproximation
+        ));
+        assert_eq!(options_json.fixed_variables, options.fixed_variables);
+        assert_eq!(options_json.max_time, options.max_time);
+
+        // missing fields fall back to the defaults
+        let partial: SolverOptions =
+            serde_json::from_str(r#"{"branch_strategy": "Random", "threads": 4}"#).unwrap();
+        assert!(matches!(
+            partial.branch_strategy,
+            BranchStrategySelection::Random
+        ));
+        assert_eq!(partial.threads, 4);
+        assert_eq!(partial.max_time, SolverOptions::new().max_time);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn solver_result_serde() {
+        let eye = CsMat::eye(3);
+        let c = Array1::from_vec(vec![-1.1, -2.0, -3.0]);
+        let p = Qubo::new_with_c(eye, c);
+
+        let mut solver = branchbound::BBSolver::new(p, get_default_solver_options());
+        solver.warm_start(Array1::from_vec(vec![1, 1, 0]));
+        solver.solve();
+
+        let result = solver.result();
+        let json = serde_json::to_string(&result).unwrap();
+        let result_json: branchbound::BBSolverResult = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(result_json.solution, result.solution);
+        assert_eq!(result_json.objective, result.objective);
+        assert_eq!(result_json.nodes_visited, result.nodes_visited);
+        assert_eq!(result_json.nodes_processed, result.nodes_processed);
+    }
 }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 /// Enum for the type of constraint that is being used in the Constraint struct
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ConstraintType {
     AtLeastOne,
     ExactlyOne,
@@ -11,10 +12,11 @@ pub enum ConstraintType {
 }
 
 /// The Constraint struct, that is for storing constraint information from the preprocessor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Constraint {
     pub(crate) x_i: usize,
     pub(crate) x_j: usize,
-    constr_type: ConstraintType,
+    pub(crate) constr_type: ConstraintType,
 }
 
 impl Constraint {
@@ -28,6 +30,9 @@ impl Constraint {
     }
 
     /// Checks if the persistent variables are consistent with the constraint
+    ///
+    /// # Panics
+    /// This function should not panic as the unwraps are only reached when both variables are fixed
     pub fn check(&self, persistent: &HashMap<usize, usize>) -> bool {
         // can only be computed if both variables are fixed
         if self.how_many_fixed(persistent) != 2 {
@@ -51,6 +56,9 @@ impl Constraint {
 
     /// Given a set of persistent variables, computes of an inference can be made and if so
     /// returns the index and value of the fixed variable
+    ///
+    /// # Panics
+    /// This function should not panic as the standard form always exists when exactly one variable is fixed
     pub fn make_inference(&self, persistent: &HashMap<usize, usize>) -> Option<(usize, usize)> {
         // count how many fixed variables we have
         let num_fixed = self.how_many_fixed(persistent);
@@ -103,7 +111,7 @@ impl Constraint {
         if Self::is_fixed(persistent, self.x_i) {
             count += 1;
         }
-        if Self::is_fixed(persistent, self.x_i) {
+        if Self::is_fixed(persistent, self.x_j) {
             count += 1;
         }
         count
@@ -113,6 +121,11 @@ impl Constraint {
         persistent.contains_key(&index)
     }
 
+    /// Given that exactly one of the variables is fixed, returns the index of the fixed variable, the index of the
+    /// free variable and the value of the fixed variable
+    ///
+    /// # Panics
+    /// This function should not panic as x_j is fixed whenever x_i is not
     pub fn get_standard_form(
         &self,
         persistent: &HashMap<usize, usize>,
@@ -131,15 +144,14 @@ impl Constraint {
     /// Given a constraint of the type x_i + x_j <= 1, computes if we can make an inference on
     /// either x_i or x_j, given some fixed variables. If so, returns the index and value of the
     /// fixed value, otherwise returns None
-    #[allow(clippy::unnecessary_wraps)]
     pub const fn no_more_then_one_inference(
         free_var: usize,
         fixed_value: usize,
     ) -> Option<(usize, usize)> {
-        // if the fixed value is 1, then the free variable must be 0
+        // if the fixed value is 1, then the free variable must be 0, and otherwise the free variable can take any value
         match fixed_value == 1 {
             true => Some((free_var, 0)),
-            false => Some((free_var, 1)),
+            false => None,
         }
     }
 
@@ -1 +1,642 @@
+//! This module contains the implication graph propagator for the pairwise constraints of the preprocessor
+//!
+//! The rules of glover2018 in `variable_reduction` generate constraints between pairs of variables, x_i <= x_h and
+//! x_i + x_h >= 1, that every optimal solution satisfies. The implication graph collects these constraints, indexed by
+//! variable, and propagates a set of fixed variables through them until no more variables can be fixed. If the fixed
+//! variables violate a constraint, then there is no optimal solution with these variables fixed.
+//!
+//! The pairwise constraints are also a 2-SAT instance. Each variable has two literals, x_i and !x_i, and each
+//! constraint is one or two clauses (a or b), which are the implications !a -> b and !b -> a. Literals in the same
+//! strongly connected component of this implication digraph are equal in every assignment that satisfies the
+//! constraints, which gives the variable equalities x_i = x_j and complements x_i = 1 - x_j, and a literal that is
+//! implied by its own negation is forced.
 
+use crate::constraint::{Constraint, ConstraintType};
+use crate::qubo::Qubo;
+use crate::variable_reduction::{generate_rule_11, generate_rule_21};
+use std::collections::{HashMap, VecDeque};
+
+/// A variable that is equal to, or the complement of, a representative variable in every assignment that satisfies the
+/// constraints, so it can be merged into the representative
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VariableMerge {
+    /// The variable that is kept
+    pub representative: usize,
+    /// The variable that is merged into the representative
+    pub variable: usize,
+    /// If true, then variable = 1 - representative, otherwise variable = representative
+    pub complement: bool,
+}
+
+/// The result of the 2-SAT analysis of the pairwise constraints
+#[derive(Clone, Debug, Default)]
+pub struct TwoSatAnalysis {
+    /// The given fixed variables, along with every variable that is forced by the constraints
+    pub fixed_variables: HashMap<usize, usize>,
+    /// The free variables that can be merged into another free variable, at most one merge per variable
+    pub merges: Vec<VariableMerge>,
+}
+
+/// A set of pairwise constraints, with the constraints each variable is in
+#[derive(Clone, Debug, Default)]
+pub struct ImplicationGraph {
+    constraints: Vec<Constraint>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl ImplicationGraph {
+    /// Creates an implication graph over num_x variables without any constraints
+    pub fn new(num_x: usize) -> Self {
+        Self {
+            constraints: Vec::new(),
+            adjacency: vec![Vec::new(); num_x],
+        }
+    }
+
+    /// Creates an implication graph from a list of constraints
+    pub fn from_constraints(num_x: usize, constraints: Vec<Constraint>) -> Self {
+        let mut graph = Self::new(num_x);
+        for constraint in constraints {
+            graph.add_constraint(constraint);
+        }
+        graph
+    }
+
+    /// Generates the implication graph of the rules 1.1 and 2.1 of glover2018 for all of the free variables
+    ///
+    /// Like persistence, the rules are found from the gradient bounds of `grad_bounds`, so the QUBO should be symmetric.
+    ///
+    /// Example:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    /// use hercules::constraint_reduction::ImplicationGraph;
+    /// use smolprng::{PRNG, JsfLarge};
+    /// use std::collections::HashMap;
+    ///
+    /// // generate a random QUBO
+    /// let mut prng = PRNG {
+    ///   generator: JsfLarge::default(),
+    /// };
+    /// let p = Qubo::make_random_qubo(50, &mut prng, 0.1).make_symmetric();
+    ///
+    /// // generate the rules, and see what fixing x_0 = 1 implies
+    /// let graph = ImplicationGraph::generate(&p, &HashMap::new());
+    /// let fixed = graph.propagate(&HashMap::from([(0, 1)]));
+    /// ```
+    pub fn generate(qubo: &Qubo, fixed_variables: &HashMap<usize, usize>) -> Self {
+        let mut graph = Self::new(qubo.num_x());
+
+        for i in 0..qubo.num_x() {
+            if fixed_variables.contains_key(&i) {
+                continue;
+            }
+
+            for constraint in generate_rule_11(qubo, fixed_variables, i) {
+                graph.add_constraint(constraint);
+            }
+            for constraint in generate_rule_21(qubo, fixed_variables, i) {
+                graph.add_constraint(constraint);
+            }
+        }
+
+        graph
+    }
+
+    /// Adds a constraint to the graph, growing the graph if the constraint is on a new variable
+    pub fn add_constraint(&mut self, constraint: Constraint) {
+        let k = self.constraints.len();
+        let max_index = constraint.x_i.max(constraint.x_j);
+
+        if max_index >= self.adjacency.len() {
+            self.adjacency.resize(max_index + 1, Vec::new());
+        }
+
+        self.adjacency[constraint.x_i].push(k);
+        if constraint.x_j != constraint.x_i {
+            self.adjacency[constraint.x_j].push(k);
+        }
+        self.constraints.push(constraint);
+    }
+
+    /// Returns the constraints in the graph
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// Returns the number of constraints in the graph
+    pub const fn len(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Returns true if the graph has no constraints
+    pub const fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    /// Propagates the fixed variables through the constraints until no more variables can be fixed, and returns the
+    /// expanded set of fixed variables
+    ///
+    /// Returns None if the fixed variables contradict a constraint, either directly or after propagation.
+    pub fn propagate(
+        &self,
+        fixed_variables: &HashMap<usize, usize>,
+    ) -> Option<HashMap<usize, usize>> {
+        let mut fixed = fixed_variables.clone();
+
+        // every fixed variable needs to be checked against its constraints once
+        let mut queue: VecDeque<usize> = fixed.keys().copied().collect();
+
+        while let Some(i) = queue.pop_front() {
+            let Some(incident) = self.adjacency.get(i) else {
+                continue;
+            };
+
+            for &k in incident {
+                let constraint = &self.constraints[k];
+
+                if !constraint.check(&fixed) {
+                    return None;
+                }
+
+                if let Some((j, value)) = constraint.make_inference(&fixed) {
+                    fixed.insert(j, value);
+                    queue.push_back(j);
+                }
+            }
+        }
+
+        Some(fixed)
+    }
+
+    /// Runs the 2-SAT analysis of the constraints with the given variables fixed, finding the forced variables and the
+    /// variables that can be merged
+    ///
+    /// Returns None if no assignment satisfies both the constraints and the fixed variables.
+    ///
+    /// Example:
+    /// ```rust
+    /// use hercules::constraint::{Constraint, ConstraintType};
+    /// use hercules::constraint_reduction::ImplicationGraph;
+    /// use std::collections::HashMap;
+    ///
+    /// // x_0 <= x_1 and x_1 <= x_0, so x_0 = x_1, and x_0 + x_2 = 1, so x_2 = 1 - x_0
+    /// let graph = ImplicationGraph::from_constraints(
+    ///     3,
+    ///     vec![
+    ///         Constraint::new(0, 1, ConstraintType::LessThan),
+    ///         Constraint::new(1, 0, ConstraintType::LessThan),
+    ///         Constraint::new(0, 2, ConstraintType::ExactlyOne),
+    ///     ],
+    /// );
+    ///
+    /// let analysis = graph.two_sat_analysis(&HashMap::new()).unwrap();
+    /// assert_eq!(analysis.merges.len(), 2);
+    ///
+    /// // but x_0 = 1 and x_1 = 0 can not both hold
+    /// assert!(graph.two_sat_analysis(&HashMap::from([(0, 1), (1, 0)])).is_none());
+    /// ```
+    pub fn two_sat_analysis(
+        &self,
+        fixed_variables: &HashMap<usize, usize>,
+    ) -> Option<TwoSatAnalysis> {
+        let num_x = self
+            .adjacency
+            .len()
+            .max(fixed_variables.keys().map(|&i| i + 1).max().unwrap_or(0));
+
+        // build the implication digraph over the literals, with a unit clause for each fixed variable
+        let mut implications = vec![Vec::new(); 2 * num_x];
+        let clauses = self.constraints.iter().flat_map(clauses).chain(
+            fixed_variables
+                .iter()
+                .map(|(&i, &value)| (literal(i, value), literal(i, value))),
+        );
+
+        for (a, b) in clauses {
+            implications[a ^ 1].push(b);
+            if a != b {
+                implications[b ^ 1].push(a);
+            }
+        }
+
+        let component = strongly_connected_components(&implications);
+
+        let mut analysis = TwoSatAnalysis {
+            fixed_variables: fixed_variables.clone(),
+            merges: Vec::new(),
+        };
+
+        let mut visited = vec![false; 2 * num_x];
+
+        for i in 0..num_x {
+            let (positive, negative) = (literal(i, 1), literal(i, 0));
+
+            // a variable that is equal to its own complement can not be assigned
+            if component[positive] == component[negative] {
+                return None;
+            }
+
+            // edges only go to components with a lower index, so only one of the literals can imply the other
+            let value = match component[negative] > component[positive] {
+                true => 1,
+                false => 0,
+            };
+            let (from, to) = (literal(i, 1 - value), literal(i, value));
+            if reaches(&implications, &component, from, to, &mut visited) {
+                analysis.fixed_variables.insert(i, value);
+            }
+        }
+
+        // group the literals of the free variables by component
+        let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (lit, &c) in component.iter().enumerate() {
+            if !analysis.fixed_variables.contains_key(&(lit / 2)) {
+                members.entry(c).or_default().push(lit);
+            }
+        }
+
+        // every component has a mirror component of the negated literals, the one where the lowest variable is positive
+        // gives the merges into that variable
+        for lits in members.values() {
+            let representative = lits.iter().min().copied().unwrap_or(0);
+            if representative & 1 == 1 {
+                continue;
+            }
+
+            for &lit in lits.iter().filter(|&&lit| lit != representative) {
+                analysis.merges.push(VariableMerge {
+                    representative: representative / 2,
+                    variable: lit / 2,
+                    complement: lit & 1 == 1,
+                });
+            }
+        }
+
+        analysis.merges.sort_by_key(|merge| merge.variable);
+
+        Some(analysis)
+    }
+}
+
+/// The literal that is true when x_i = value, 2i for x_i = 1 and 2i + 1 for x_i = 0, so that negation is lit ^ 1
+const fn literal(i: usize, value: usize) -> usize {
+    2 * i + (1 - value)
+}
+
+/// The clauses (a or b) of a constraint, as pairs of literals
+fn clauses(constraint: &Constraint) -> Vec<(usize, usize)> {
+    let (i, j) = (constraint.x_i, constraint.x_j);
+
+    match constraint.constr_type {
+        ConstraintType::AtLeastOne => vec![(literal(i, 1), literal(j, 1))],
+        ConstraintType::NoMoreThanOne => vec![(literal(i, 0), literal(j, 0))],
+        ConstraintType::ExactlyOne => vec![
+            (literal(i, 1), literal(j, 1)),
+            (literal(i, 0), literal(j, 0)),
+        ],
+        ConstraintType::LessThan => vec![(literal(i, 0), literal(j, 1))],
+        ConstraintType::GreaterThan => vec![(literal(i, 1), literal(j, 0))],
+        ConstraintType::Equal => vec![
+            (literal(i, 0), literal(j, 1)),
+            (literal(i, 1), literal(j, 0)),
+        ],
+    }
+}
+
+/// Finds the strongly connected components of a digraph with Tarjan's algorithm, without recursion so that long chains
+/// of implications can not overflow the stack
+///
+/// The components are numbered in reverse topological order, so every edge between components goes from a higher
+/// component to a lower one.
+pub(crate) fn strongly_connected_components(adjacency: &[Vec<usize>]) -> Vec<usize> {
+    let n = adjacency.len();
+    let mut index = vec![usize::MAX; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut component = vec![usize::MAX; n];
+    let mut next_index = 0;
+    let mut num_components = 0;
+
+    for root in 0..n {
+        if index[root] != usize::MAX {
+            continue;
+        }
+
+        // the call stack holds each node being visited, with the position of the next edge to follow
+        let mut call_stack = vec![(root, 0)];
+        index[root] = next_index;
+        lowlink[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&(v, edge)) = call_stack.last() {
+            if edge < adjacency[v].len() {
+                let w = adjacency[v][edge];
+                if let Some(top) = call_stack.last_mut() {
+                    top.1 += 1;
+                }
+
+                if index[w] == usize::MAX {
+                    index[w] = next_index;
+                    lowlink[w] = next_index;
+                    next_index += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    call_stack.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[v] = lowlink[v].min(index[w]);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some(&(u, _)) = call_stack.last() {
+                lowlink[u] = lowlink[u].min(lowlink[v]);
+            }
+
+            // v is the root of a component, which is everything above it on the stack
+            if lowlink[v] == index[v] {
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component[w] = num_components;
+                    if w == v {
+                        break;
+                    }
+                }
+                num_components += 1;
+            }
+        }
+    }
+
+    component
+}
+
+/// Checks if there is a path from source to target, only searching the components that can still reach the target
+///
+/// The visited flags are reset before returning, so that they can be reused without reallocating for every search.
+fn reaches(
+    adjacency: &[Vec<usize>],
+    component: &[usize],
+    source: usize,
+    target: usize,
+    visited: &mut [bool],
+) -> bool {
+    let mut touched = vec![source];
+    let mut queue = VecDeque::from([source]);
+    visited[source] = true;
+    let mut found = false;
+
+    while let Some(v) = queue.pop_front() {
+        if v == target {
+            found = true;
+            break;
+        }
+
+        for &w in &adjacency[v] {
+            if !visited[w] && component[w] >= component[target] {
+                visited[w] = true;
+                touched.push(w);
+                queue.push_back(w);
+            }
+        }
+    }
+
+    for v in touched {
+        visited[v] = false;
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraint::{Constraint, ConstraintType};
+    use crate::constraint_reduction::{ImplicationGraph, VariableMerge};
+    use crate::qubo::Qubo;
+    use crate::tests::make_test_prng;
+    use ndarray::Array1;
+    use std::collections::HashMap;
+
+    #[test]
+    fn propagate_chain() {
+        // x_0 <= x_1 <= x_2, and x_2 + x_3 >= 1
+        let graph = ImplicationGraph::from_constraints(
+            4,
+            vec![
+                Constraint::new(0, 1, ConstraintType::LessThan),
+                Constraint::new(1, 2, ConstraintType::LessThan),
+                Constraint::new(2, 3, ConstraintType::AtLeastOne),
+            ],
+        );
+
+        let fixed = graph.propagate(&HashMap::from([(0, 1)])).unwrap();
+        assert_eq!(fixed, HashMap::from([(0, 1), (1, 1), (2, 1)]));
+
+        let fixed = graph.propagate(&HashMap::from([(2, 0)])).unwrap();
+        assert_eq!(fixed, HashMap::from([(0, 0), (1, 0), (2, 0), (3, 1)]));
+
+        // nothing follows from x_3 = 1
+        let fixed = graph.propagate(&HashMap::from([(3, 1)])).unwrap();
+        assert_eq!(fixed, HashMap::from([(3, 1)]));
+    }
+
+    #[test]
+    fn propagate_contradiction() {
+        // x_0 <= x_1 and x_0 + x_1 <= 1, so x_0 = 1 is infeasible
+        let graph = ImplicationGraph::from_constraints(
+            2,
+            vec![
+                Constraint::new(0, 1, ConstraintType::LessThan),
+                Constraint::new(0, 1, ConstraintType::NoMoreThanOne),
+            ],
+        );
+
+        assert!(graph.propagate(&HashMap::from([(0, 1)])).is_none());
+        assert!(graph.propagate(&HashMap::from([(0, 1), (1, 0)])).is_none());
+
+        // x_0 = 0 says nothing about x_1
+        let fixed = graph.propagate(&HashMap::from([(0, 0)])).unwrap();
+        assert_eq!(fixed, HashMap::from([(0, 0)]));
+    }
+
+    #[test]
+    fn generated_rules_hold_at_the_optimum() {
+        let mut prng = make_test_prng();
+        let mut num_constraints = 0;
+
+        for _ in 0..20 {
+            // the bounds of the preprocessor assume a symmetric Hessian, as in the B&B solver
+            let p = Qubo::make_random_qubo(10, &mut prng, 0.5).make_symmetric();
+
+            // find every optimal solution by brute force
+            let objectives: Vec<_> = (0..1usize << p.num_x())
+                .map(|mask| {
+                    let x = Array1::from_shape_fn(p.num_x(), |i| (mask >> i) & 1);
+                    let obj = p.eval_usize(&x);
+                    (x, obj)
+                })
+                .collect();
+            let best = objectives
+                .iter()
+                .map(|(_, obj)| *obj)
+                .fold(f64::INFINITY, f64::min);
+
+            let graph = ImplicationGraph::generate(&p, &HashMap::new());
+            num_constraints += graph.len();
+
+            for (x, obj) in &objectives {
+                if (obj - best).abs() > 1E-10 {
+                    continue;
+                }
+
+                let fixed: HashMap<_, _> = x.iter().copied().enumerate().collect();
+                assert!(graph.constraints().iter().all(|c| c.check(&fixed)));
+
+                // propagating a part of an optimal solution only fixes variables to their optimal values
+                let partial: HashMap<_, _> = fixed
+                    .iter()
+                    .filter(|(&i, _)| i % 3 == 0)
+                    .map(|(&i, &v)| (i, v))
+                    .collect();
+                let propagated = graph.propagate(&partial).unwrap();
+                assert!(propagated.iter().all(|(&i, &v)| x[i] == v));
+            }
+        }
+
+        // the test is not vacuous
+        assert!(num_constraints > 0);
+    }
+
+    #[test]
+    fn two_sat_merges() {
+        // x_0 = x_1 from two inequalities, x_2 = 1 - x_1 and x_3 = x_4
+        let graph = ImplicationGraph::from_constraints(
+            5,
+            vec![
+                Constraint::new(0, 1, ConstraintType::LessThan),
+                Constraint::new(0, 1, ConstraintType::GreaterThan),
+                Constraint::new(1, 2, ConstraintType::AtLeastOne),
+                Constraint::new(1, 2, ConstraintType::NoMoreThanOne),
+                Constraint::new(4, 3, ConstraintType::Equal),
+            ],
+        );
+
+        let analysis = graph.two_sat_analysis(&HashMap::new()).unwrap();
+
+        assert!(analysis.fixed_variables.is_empty());
+        assert_eq!(
+            analysis.merges,
+            vec![
+                VariableMerge {
+                    representative: 0,
+                    variable: 1,
+                    complement: false
+                },
+                VariableMerge {
+                    representative: 0,
+                    variable: 2,
+                    complement: true
+                },
+                VariableMerge {
+                    representative: 3,
+                    variable: 4,
+                    complement: false
+                },
+            ]
+        );
+
+        // fixing a variable fixes everything merged with it instead
+        let analysis = graph.two_sat_analysis(&HashMap::from([(2, 1)])).unwrap();
+        assert_eq!(
+            analysis.fixed_variables,
+            HashMap::from([(0, 0), (1, 0), (2, 1)])
+        );
+        assert_eq!(analysis.merges.len(), 1);
+    }
+
+    #[test]
+    fn two_sat_forced_and_infeasible() {
+        // x_0 <= x_1 and x_0 + x_1 <= 1, so x_0 = 0
+        let graph = ImplicationGraph::from_constraints(
+            2,
+            vec![
+                Constraint::new(0, 1, ConstraintType::LessThan),
+                Constraint::new(0, 1, ConstraintType::NoMoreThanOne),
+            ],
+        );
+
+        let analysis = graph.two_sat_analysis(&HashMap::new()).unwrap();
+        assert_eq!(analysis.fixed_variables, HashMap::from([(0, 0)]));
+        assert!(analysis.merges.is_empty());
+
+        assert!(graph.two_sat_analysis(&HashMap::from([(0, 1)])).is_none());
+
+        // every assignment of x_0 and x_1 violates one of these
+        let graph = ImplicationGraph::from_constraints(
+            2,
+            vec![
+                Constraint::new(0, 1, ConstraintType::Equal),
+                Constraint::new(0, 1, ConstraintType::ExactlyOne),
+            ],
+        );
+        assert!(graph.two_sat_analysis(&HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn two_sat_matches_brute_force() {
+        let mut prng = make_test_prng();
+        let types = [
+            ConstraintType::AtLeastOne,
+            ConstraintType::ExactlyOne,
+            ConstraintType::NoMoreThanOne,
+            ConstraintType::GreaterThan,
+            ConstraintType::LessThan,
+            ConstraintType::Equal,
+        ];
+        let num_x = 7;
+
+        for _ in 0..200 {
+            let constraints: Vec<_> = (0..6)
+                .map(|_| {
+                    let i = prng.gen_u64() as usize % num_x;
+                    let j = (i + 1 + prng.gen_u64() as usize % (num_x - 1)) % num_x;
+                    Constraint::new(i, j, types[prng.gen_u64() as usize % types.len()])
+                })
+                .collect();
+            let graph = ImplicationGraph::from_constraints(num_x, constraints);
+
+            // every assignment that satisfies all of the constraints
+            let satisfying: Vec<HashMap<_, _>> = (0..1usize << num_x)
+                .map(|mask| (0..num_x).map(|i| (i, (mask >> i) & 1)).collect())
+                .filter(|x| graph.constraints().iter().all(|c| c.check(x)))
+                .collect();
+
+            let Some(analysis) = graph.two_sat_analysis(&HashMap::new()) else {
+                assert!(satisfying.is_empty());
+                continue;
+            };
+            assert!(!satisfying.is_empty());
+
+            // the fixings and merges hold in every satisfying assignment
+            for x in &satisfying {
+                assert!(analysis.fixed_variables.iter().all(|(i, v)| x[i] == *v));
+                assert!(analysis.merges.iter().all(|m| {
+                    let expected = match m.complement {
+                        true => 1 - x[&m.representative],
+                        false => x[&m.representative],
+                    };
+                    x[&m.variable] == expected
+                }));
+            }
+
+            // and every variable that takes a single value is found
+            for i in 0..num_x {
+                if satisfying.iter().all(|x| x[&i] == satisfying[0][&i]) {
+                    assert_eq!(analysis.fixed_variables[&i], satisfying[0][&i]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! This module contains the elite pool used by the population based heuristics
+//!
+//! The pool keeps a fixed number of distinct solutions, and balances their quality against their diversity with the
+//! quality-and-distance score of Lü, Glover and Hao (2010). When a new solution is offered to a full pool, every solution
+//! is scored by its objective and by its distance to the closest other solution, and the worst scoring one is dropped.
+//! This keeps a cluster of near identical good solutions from taking over the pool.
+
+use crate::utils::calculate_hamming_distance;
+use ndarray::Array1;
+
+/// The weight of the objective in the quality-and-distance score, the rest of the weight is on the distance
+const QUALITY_WEIGHT: f64 = 0.6;
+
+/// A pool of distinct binary solutions, with their objectives
+#[derive(Clone)]
+pub struct ElitePool {
+    capacity: usize,
+    solutions: Vec<Array1<usize>>,
+    objectives: Vec<f64>,
+}
+
+impl ElitePool {
+    /// Creates an empty pool that holds up to capacity solutions
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            solutions: Vec::new(),
+            objectives: Vec::new(),
+        }
+    }
+
+    /// Returns the largest number of solutions the pool holds
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of solutions in the pool
+    pub const fn len(&self) -> usize {
+        self.solutions.len()
+    }
+
+    /// Returns true if there are no solutions in the pool
+    pub const fn is_empty(&self) -> bool {
+        self.solutions.is_empty()
+    }
+
+    /// Returns true if the pool holds as many solutions as its capacity
+    pub const fn is_full(&self) -> bool {
+        self.solutions.len() >= self.capacity
+    }
+
+    /// Returns the solutions in the pool
+    pub fn solutions(&self) -> &[Array1<usize>] {
+        &self.solutions
+    }
+
+    /// Returns the objectives of the solutions in the pool, in the same order as the solutions
+    pub fn objectives(&self) -> &[f64] {
+        &self.objectives
+    }
+
+    /// Returns the solution with the lowest objective, along with its objective
+    pub fn best(&self) -> Option<(&Array1<usize>, f64)> {
+        self.objectives
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(k, &objective)| (&self.solutions[k], objective))
+    }
+
+    /// Returns true if the solution is already in the pool
+    pub fn contains(&self, x: &Array1<usize>) -> bool {
+        self.solutions.iter().any(|solution| solution == x)
+    }
+
+    /// Offers a solution to the pool, and returns true if it was added
+    ///
+    /// A duplicate of a solution in the pool is never added. If the pool is full, the solution with the worst
+    /// quality-and-distance score out of the pool and the new solution is dropped, which can be the new solution.
+    ///
+    /// Example:
+    /// ```rust
+    /// use hercules::elite_pool::ElitePool;
+    /// use ndarray::Array1;
+    ///
+    /// let mut pool = ElitePool::new(2);
+    ///
+    /// assert!(pool.insert(Array1::from_vec(vec![0, 0, 0]), 1.0));
+    /// assert!(pool.insert(Array1::from_vec(vec![1, 1, 1]), 2.0));
+    ///
+    /// // duplicates are not added
+    /// assert!(!pool.insert(Array1::from_vec(vec![0, 0, 0]), 1.0));
+    ///
+    /// // a better solution replaces the worst one
+    /// assert!(pool.insert(Array1::from_vec(vec![1, 0, 1]), 0.0));
+    /// assert_eq!(pool.best().unwrap().1, 0.0);
+    /// ```
+    pub fn insert(&mut self, x: Array1<usize>, objective: f64) -> bool {
+        if self.capacity == 0 || self.contains(&x) {
+            return false;
+        }
+
+        self.solutions.push(x);
+        self.objectives.push(objective);
+
+        if self.solutions.len() <= self.capacity {
+            return true;
+        }
+
+        // drop the worst scoring solution, the new solution is the last one
+        let worst = self.worst_score_index();
+        self.solutions.swap_remove(worst);
+        self.objectives.swap_remove(worst);
+
+        worst != self.capacity
+    }
+
+    /// Finds the solution with the lowest quality-and-distance score
+    fn worst_score_index(&self) -> usize {
+        let num_solutions = self.solutions.len();
+
+        // the distance of each solution to the closest other solution in the pool
+        let distances: Vec<_> = (0..num_solutions)
+            .map(|k| {
+                (0..num_solutions)
+                    .filter(|&l| l != k)
+                    .map(|l| calculate_hamming_distance(&self.solutions[k], &self.solutions[l]))
+                    .min()
+                    .unwrap_or(0) as f64
+            })
+            .collect();
+
+        let (obj_min, obj_max) = min_max(&self.objectives);
+        let (dist_min, dist_max) = min_max(&distances);
+
+        // a low objective and a large distance both give a high score, both terms are scaled to [0, 1)
+        let score = |k: usize| {
+            let quality = (obj_max - self.objectives[k]) / (obj_max - obj_min + 1.0);
+            let diversity = (distances[k] - dist_min) / (dist_max - dist_min + 1.0);
+            QUALITY_WEIGHT * quality + (1.0 - QUALITY_WEIGHT) * diversity
+        };
+
+        (0..num_solutions)
+            .min_by(|&a, &b| score(a).total_cmp(&score(b)))
+            .unwrap_or(0)
+    }
+}
+
+/// The smallest and largest values of a slice
+fn min_max(values: &[f64]) -> (f64, f64) {
+    values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::elite_pool::ElitePool;
+    use ndarray::Array1;
+
+    #[test]
+    fn pool_fills_without_duplicates() {
+        let mut pool = ElitePool::new(3);
+
+        assert!(pool.insert(Array1::from_vec(vec![0, 0, 0, 0]), 3.0));
+        assert!(!pool.insert(Array1::from_vec(vec![0, 0, 0, 0]), 3.0));
+        assert!(pool.insert(Array1::from_vec(vec![1, 0, 0, 0]), 2.0));
+        assert!(pool.insert(Array1::from_vec(vec![1, 1, 1, 1]), 5.0));
+
+        assert_eq!(pool.len(), 3);
+        assert!(pool.is_full());
+        assert_eq!(pool.best().unwrap().0, &Array1::from_vec(vec![1, 0, 0, 0]));
+    }
+
+    #[test]
+    fn pool_keeps_diverse_solutions() {
+        let mut pool = ElitePool::new(3);
+
+        pool.insert(Array1::from_vec(vec![0, 0, 0, 0, 0, 0]), 0.0);
+        pool.insert(Array1::from_vec(vec![1, 0, 0, 0, 0, 0]), 0.1);
+        pool.insert(Array1::from_vec(vec![1, 1, 1, 1, 1, 1]), 0.5);
+
+        // a slightly better solution next to the cluster replaces a member of the cluster, not the distant solution
+        assert!(pool.insert(Array1::from_vec(vec![0, 1, 0, 0, 0, 0]), 0.05));
+        assert!(pool.contains(&Array1::from_vec(vec![1, 1, 1, 1, 1, 1])));
+
+        // a much worse solution is rejected
+        assert!(!pool.insert(Array1::from_vec(vec![0, 0, 0, 1, 1, 0]), 10.0));
+        assert_eq!(pool.len(), 3);
+    }
+}
@@ -0,0 +1,286 @@
+//! This module contains the incremental one flip evaluator that the local search heuristics are built on
+//!
+//! Evaluating the objective of a QUBO from scratch costs O(|Q|), so scanning the one flip neighborhood of a point by
+//! evaluating every neighbor costs O(n|Q|). A [`DeltaEvaluator`] precomputes the symmetric part of the Hessian once per
+//! problem, and a [`FlipState`] keeps the objective of a point along with the gradient of the objective at that point.
+//! The change in the objective from flipping x_i only depends on q_ii and the i-th entry of the gradient, and flipping
+//! x_k only changes the gradient entries of the variables coupled to x_k, so a flip is applied in O(degree) time.
+
+use crate::qubo::Qubo;
+use ndarray::Array1;
+use sprs::CsMat;
+
+/// The per problem data needed to evaluate and apply bit flips, shared by every [`FlipState`] of the problem
+pub struct DeltaEvaluator {
+    /// The symmetric part of the Hessian, 0.5 (Q + Q^T), in CSR order
+    q_sym: CsMat<f64>,
+    /// The diagonal of the Hessian
+    q_diag: Array1<f64>,
+    /// The linear term of the QUBO problem
+    c: Array1<f64>,
+    /// The constant term of the QUBO problem
+    offset: f64,
+}
+
+/// A binary point of a QUBO, with its objective and the gradient of the objective kept up to date as variables are flipped
+#[derive(Clone)]
+pub struct FlipState<'a> {
+    evaluator: &'a DeltaEvaluator,
+    x: Array1<usize>,
+    objective: f64,
+    /// The gradient of the objective at x, 0.5 (Q + Q^T) x + c
+    grad: Array1<f64>,
+}
+
+impl DeltaEvaluator {
+    /// Precomputes the symmetric part of the Hessian of the QUBO, this is O(|Q|) and only needs to be done once per problem
+    ///
+    /// Example of finding the best single bit flip of a point:
+    /// ``` rust
+    /// use hercules::qubo::Qubo;
+    /// use hercules::flip_state::DeltaEvaluator;
+    /// use hercules::initial_points;
+    /// use smolprng::{PRNG, JsfLarge};
+    ///
+    /// let mut prng = PRNG {
+    ///    generator: JsfLarge::default(),
+    /// };
+    /// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+    /// let x_0 = initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+    ///
+    /// let evaluator = DeltaEvaluator::new(&p);
+    /// let mut state = evaluator.state(&x_0);
+    ///
+    /// let (i, delta) = state.best_flip().unwrap();
+    /// state.flip(i);
+    ///
+    /// assert!((state.objective() - (p.eval_usize(&x_0) + delta)).abs() < 1E-10);
+    /// ```
+    pub fn new(qubo: &Qubo) -> Self {
+        let q_sym = qubo.make_symmetric().q;
+        let q_diag = qubo.q.diag().to_dense();
+
+        Self {
+            q_sym,
+            q_diag,
+            c: qubo.c.clone(),
+            offset: qubo.offset,
+        }
+    }
+
+    /// Returns the number of variables of the problem
+    pub fn num_x(&self) -> usize {
+        self.c.len()
+    }
+
+    /// Returns the coupling between x_i and x_j in the symmetric part of the Hessian, this is O(log degree)
+    pub fn coupling(&self, i: usize, j: usize) -> f64 {
+        self.q_sym.get(i, j).copied().unwrap_or(0.0)
+    }
+
+    /// Returns the variables coupled to x_i in the symmetric part of the Hessian, along with the couplings, including x_i
+    /// itself if q_ii is not zero
+    pub fn couplings(&self, i: usize) -> Vec<(usize, f64)> {
+        self.q_sym
+            .outer_view(i)
+            .map(|row| row.iter().map(|(j, &value)| (j, value)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Creates the flip state of a binary point, this is O(|Q|)
+    pub fn state(&self, x: &Array1<usize>) -> FlipState<'_> {
+        let x_f = x.mapv(|x_i| x_i as f64);
+        let grad = &self.q_sym * &x_f + &self.c;
+
+        // 0.5 x^T Q x = 0.5 x^T (Q + Q^T)/2 x, and (Q + Q^T)/2 x = grad - c
+        let objective = 0.5 * x_f.dot(&(&grad - &self.c)) + self.c.dot(&x_f) + self.offset;
+
+        FlipState {
+            evaluator: self,
+            x: x.clone(),
+            objective,
+            grad,
+        }
+    }
+}
+
+impl FlipState<'_> {
+    /// Returns the current point
+    pub const fn x(&self) -> &Array1<usize> {
+        &self.x
+    }
+
+    /// Returns the objective of the current point
+    pub const fn objective(&self) -> f64 {
+        self.objective
+    }
+
+    /// Returns the gradient of the objective at the current point
+    pub const fn gradient(&self) -> &Array1<f64> {
+        &self.grad
+    }
+
+    /// Consumes the state and returns the current point
+    pub fn into_x(self) -> Array1<usize> {
+        self.x
+    }
+
+    /// The change in the objective from flipping x_i, this is O(1)
+    pub fn delta(&self, i: usize) -> f64 {
+        let direction = 1.0 - 2.0 * self.x[i] as f64;
+        0.5 * self.evaluator.q_diag[i] + direction * self.grad[i]
+    }
+
+    /// The change in the objective from flipping each of the variables, this is O(n)
+    pub fn deltas(&self) -> Array1<f64> {
+        Array1::from_shape_fn(self.x.len(), |i| self.delta(i))
+    }
+
+    /// Returns the variable whose flip decreases the objective the most, along with the change in the objective, or None
+    /// if there are no variables. Ties are broken by the lowest index.
+    pub fn best_flip(&self) -> Option<(usize, f64)> {
+        self.best_flip_of(0..self.x.len())
+    }
+
+    /// Returns the variable out of the candidates whose flip decreases the objective the most, along with the change in
+    /// the objective, or None if there are no candidates. Ties are broken by the first candidate.
+    pub fn best_flip_of<I: IntoIterator<Item = usize>>(
+        &self,
+        candidates: I,
+    ) -> Option<(usize, f64)> {
+        let mut best: Option<(usize, f64)> = None;
+
+        for i in candidates {
+            let delta = self.delta(i);
+            match best {
+                Some((_, best_delta)) if delta >= best_delta => {}
+                _ => best = Some((i, delta)),
+            }
+        }
+
+        best
+    }
+
+    /// Flips x_i and updates the objective and the gradient, this is O(degree of x_i)
+    pub fn flip(&mut self, i: usize) {
+        self.objective += self.delta(i);
+
+        let direction = 1.0 - 2.0 * self.x[i] as f64;
+        self.x[i] = 1 - self.x[i];
+
+        // only the variables coupled to x_i see a change in their gradient entry
+        if let Some(row) = self.evaluator.q_sym.outer_view(i) {
+            for (j, &value) in row.iter() {
+                self.grad[j] += direction * value;
+            }
+        }
+    }
+
+    /// Flips every variable where the current point differs from the target, and returns the number of flips
+    pub fn move_to(&mut self, target: &Array1<usize>) -> usize {
+        let mut num_flips = 0;
+
+        for i in 0..self.x.len() {
+            if self.x[i] != target[i] {
+                self.flip(i);
+                num_flips += 1;
+            }
+        }
+
+        num_flips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flip_state::DeltaEvaluator;
+    use crate::initial_points::generate_random_binary_point;
+    use crate::qubo::Qubo;
+    use crate::tests::{make_solver_qubo, make_test_prng};
+
+    #[test]
+    fn flip_state_matches_full_evaluation() {
+        let mut p = make_solver_qubo();
+        p.offset = 1.5;
+        let mut prng = make_test_prng();
+
+        let evaluator = DeltaEvaluator::new(&p);
+        let x_0 = generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+        let mut state = evaluator.state(&x_0);
+
+        assert!((state.objective() - p.eval_usize(&x_0)).abs() < 1E-10);
+
+        for _ in 0..200 {
+            let i = (prng.gen_u64() % p.num_x() as u64) as usize;
+
+            let mut x_1 = state.x().clone();
+            x_1[i] = 1 - x_1[i];
+            let expected_delta = p.eval_usize(&x_1) - p.eval_usize(state.x());
+
+            assert!((state.delta(i) - expected_delta).abs() < 1E-10);
+
+            state.flip(i);
+
+            assert_eq!(state.x(), &x_1);
+            assert!((state.objective() - p.eval_usize(&x_1)).abs() < 1E-8);
+        }
+
+        // the gradient should not drift away from the gradient computed from scratch
+        let grad = p.eval_grad_usize(state.x());
+        assert!(state
+            .gradient()
+            .iter()
+            .zip(grad.iter())
+            .all(|(a, b)| (a - b).abs() < 1E-8));
+    }
+
+    #[test]
+    fn flip_state_non_symmetric_csc() {
+        // a non-symmetric hessian stored in CSC order
+        let p = Qubo::new_with_c(
+            sprs::TriMat::from_triplets(
+                (3, 3),
+                vec![0, 0, 1, 2, 2],
+                vec![0, 1, 2, 0, 2],
+                vec![1.0, -2.0, 3.0, 0.5, -1.0],
+            )
+            .to_csc(),
+            ndarray::Array1::from_vec(vec![0.5, -1.0, 0.25]),
+        );
+        let evaluator = DeltaEvaluator::new(&p);
+
+        for k in 0..8usize {
+            let x = ndarray::Array1::from_shape_fn(3, |i| (k >> i) & 1);
+            let state = evaluator.state(&x);
+
+            assert!((state.objective() - p.eval_usize(&x)).abs() < 1E-10);
+
+            let (_, objs) = crate::local_search_utils::one_flip_objective(&p, &x);
+            assert!(state
+                .deltas()
+                .iter()
+                .zip(objs.iter())
+                .all(|(a, b)| (a - b).abs() < 1E-10));
+        }
+    }
+
+    #[test]
+    fn move_to_target() {
+        let p = make_solver_qubo();
+        let mut prng = make_test_prng();
+
+        let evaluator = DeltaEvaluator::new(&p);
+        let x_0 = generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+        let x_1 = generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+        let mut state = evaluator.state(&x_0);
+
+        let num_flips = state.move_to(&x_1);
+
+        assert_eq!(
+            num_flips,
+            x_0.iter().zip(x_1.iter()).filter(|(a, b)| a != b).count()
+        );
+        assert_eq!(state.x(), &x_1);
+        assert!((state.objective() - p.eval_usize(&x_1)).abs() < 1E-8);
+    }
+}
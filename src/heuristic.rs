@@ -0,0 +1,763 @@
+//! This module contains the common interface of the primal heuristics
+//!
+//! Every heuristic implements [`Heuristic`], which runs from a starting point under a [`Budget`] and returns a
+//! [`HeuristicResult`] with the best point found and how the search got there. The budget can limit the wall clock time,
+//! the number of evaluations and the number of steps, and can stop the search early once a target objective is reached.
+//! A step is one iteration of the main loop of the heuristic, such as a flip, a sweep or a perturbation, and an evaluation
+//! is the objective change of a single flip or a full evaluation of the objective.
+//!
+//! A budget can also carry a [`SharedIncumbent`], which lets heuristics that run at the same time share their best point
+//! and stop together, as in the `portfolio` runner.
+//!
+//! The searches in `local_search` all keep their free function form, and are run through a [`BudgetTracker`] so that
+//! both forms share the same loop. The adapters in this module wrap each of them, with the parameters of the search that
+//! are not covered by the budget as fields.
+
+use crate::branchbound_utils::get_current_time;
+use crate::flip_state::DeltaEvaluator;
+use crate::initial_points::generate_random_binary_point;
+use crate::local_search::{self, AnnealingSchedule};
+use crate::qubo::Qubo;
+use ndarray::Array1;
+use rayon::prelude::*;
+use smolprng::{JsfLarge, PRNG};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The wall clock time limit in seconds of a default budget
+pub const DEFAULT_MAX_TIME: f64 = 100.0;
+
+/// The limits of a heuristic run, the run stops as soon as any one of them is hit
+#[derive(Clone, Debug)]
+pub struct Budget {
+    /// The wall clock time limit in seconds
+    pub max_time: f64,
+    /// The limit on the number of evaluations
+    pub max_evaluations: usize,
+    /// The limit on the number of steps
+    pub max_steps: usize,
+    /// The run stops once it finds a point with an objective at most this value
+    pub target_objective: Option<f64>,
+    /// The best point shared with other runs, the run offers its improvements to it and stops when it is stopped
+    pub incumbent: Option<Arc<SharedIncumbent>>,
+}
+
+impl Budget {
+    /// Creates a budget that only limits the wall clock time to `DEFAULT_MAX_TIME` seconds
+    pub const fn new() -> Self {
+        Self {
+            max_time: DEFAULT_MAX_TIME,
+            max_evaluations: usize::MAX,
+            max_steps: usize::MAX,
+            target_objective: None,
+            incumbent: None,
+        }
+    }
+
+    /// Creates a budget without any limits, which is only enough for the heuristics that stop on their own, such as the
+    /// local searches and simulated annealing
+    pub const fn unlimited() -> Self {
+        Self {
+            max_time: f64::INFINITY,
+            max_evaluations: usize::MAX,
+            max_steps: usize::MAX,
+            target_objective: None,
+            incumbent: None,
+        }
+    }
+
+    /// Creates a budget that only limits the wall clock time
+    pub fn with_time(max_time: f64) -> Self {
+        Self {
+            max_time,
+            ..Self::unlimited()
+        }
+    }
+
+    /// Creates a budget that only limits the number of steps
+    pub fn with_steps(max_steps: usize) -> Self {
+        Self {
+            max_steps,
+            ..Self::unlimited()
+        }
+    }
+
+    /// Returns true if at least one of the time, evaluation and step limits is finite, so that every run stops
+    pub const fn is_limited(&self) -> bool {
+        self.max_time.is_finite()
+            || self.max_evaluations < usize::MAX
+            || self.max_steps < usize::MAX
+    }
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The result of a heuristic run
+#[derive(Clone, Debug)]
+pub struct HeuristicResult {
+    /// The best point found
+    pub x: Array1<usize>,
+    /// The objective of the best point
+    pub objective: f64,
+    /// The number of evaluations used
+    pub evaluations: usize,
+    /// The number of steps taken
+    pub steps: usize,
+    /// The wall clock time of the run in seconds
+    pub elapsed: f64,
+    /// The elapsed time and the objective every time the best point improved, starting with the starting point
+    pub trace: Vec<(f64, f64)>,
+}
+
+/// The best point over a set of heuristic runs, which can be shared between threads
+///
+/// If there is a target objective, then the incumbent is stopped as soon as a point reaching it is offered, which stops
+/// every run that shares it.
+#[derive(Debug)]
+pub struct SharedIncumbent {
+    best: Mutex<(Array1<usize>, f64)>,
+    target_objective: Option<f64>,
+    stopped: AtomicBool,
+}
+
+impl SharedIncumbent {
+    /// Creates an incumbent from a starting point and its objective
+    pub fn new(x: Array1<usize>, objective: f64, target_objective: Option<f64>) -> Self {
+        let incumbent = Self {
+            best: Mutex::new((x, objective)),
+            target_objective,
+            stopped: AtomicBool::new(false),
+        };
+        incumbent.check_target(objective);
+        incumbent
+    }
+
+    /// Offers a point to the incumbent, and returns true if it is the new best point
+    pub fn offer(&self, x: &Array1<usize>, objective: f64) -> bool {
+        // a poisoned lock only means another run panicked, the point in it is still valid
+        let mut best = self
+            .best
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        if objective >= best.1 {
+            return false;
+        }
+
+        best.0.clone_from(x);
+        best.1 = objective;
+        drop(best);
+
+        self.check_target(objective);
+
+        true
+    }
+
+    /// Returns a copy of the best point and its objective
+    pub fn best(&self) -> (Array1<usize>, f64) {
+        self.best
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Stops every run that shares the incumbent
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the incumbent has been stopped
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// Stops the incumbent if the objective reaches the target
+    fn check_target(&self, objective: f64) {
+        if self
+            .target_objective
+            .is_some_and(|target| objective <= target)
+        {
+            self.stop();
+        }
+    }
+}
+
+/// A primal heuristic for QUBO, that searches for a good point from a starting point
+///
+/// Example of running two different heuristics with the same budget:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use hercules::heuristic::{Budget, Heuristic, SimpleLocalSearch, TabuSearch};
+/// use smolprng::{PRNG, JsfLarge};
+/// use ndarray::Array1;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(20, &mut prng, 0.5);
+/// let x_0 = Array1::zeros(p.num_x());
+///
+/// // run for at most 1000 steps or 1 second
+/// let mut budget = Budget::with_steps(1000);
+/// budget.max_time = 1.0;
+///
+/// let heuristics: Vec<Box<dyn Heuristic>> = vec![Box::new(SimpleLocalSearch), Box::new(TabuSearch { tenure: 5 })];
+///
+/// for heuristic in &heuristics {
+///     let result = heuristic.run(&p, &x_0, &budget);
+///     println!("{}: {} in {} steps", heuristic.name(), result.objective, result.steps);
+/// }
+/// ```
+pub trait Heuristic: Send + Sync {
+    /// The name of the heuristic, for reporting
+    fn name(&self) -> String;
+
+    /// Runs the heuristic from the starting point until it stops on its own or the budget is used up
+    ///
+    /// # Panics
+    ///
+    /// The heuristics that do not stop on their own, such as tabu search and iterated local search, panic if the budget
+    /// is not limited, see `Budget::is_limited`.
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult;
+}
+
+/// Keeps track of the budget and the best point of a heuristic run
+///
+/// A heuristic counts its steps and evaluations with the tracker, offers it every point that could be the new best, and
+/// checks if the budget is used up before each step.
+pub struct BudgetTracker {
+    budget: Budget,
+    start_time: f64,
+    evaluations: usize,
+    steps: usize,
+    best_x: Array1<usize>,
+    best_objective: f64,
+    trace: Vec<(f64, f64)>,
+}
+
+impl BudgetTracker {
+    /// Starts tracking a run from the starting point, the objective of which counts as an evaluation
+    pub fn new(budget: &Budget, x_0: &Array1<usize>, objective: f64) -> Self {
+        Self {
+            budget: budget.clone(),
+            start_time: get_current_time(),
+            evaluations: 1,
+            steps: 0,
+            best_x: x_0.clone(),
+            best_objective: objective,
+            trace: vec![(0.0, objective)],
+        }
+    }
+
+    /// Returns the time in seconds since the start of the run
+    pub fn elapsed(&self) -> f64 {
+        get_current_time() - self.start_time
+    }
+
+    /// Returns true if any of the limits of the budget has been hit
+    pub fn is_exhausted(&self) -> bool {
+        self.is_out_of_resources()
+            || self
+                .budget
+                .target_objective
+                .is_some_and(|target| self.best_objective <= target)
+            || self
+                .budget
+                .incumbent
+                .as_ref()
+                .is_some_and(|incumbent| incumbent.is_stopped())
+    }
+
+    /// Returns true if the step, evaluation or time limit has been hit, ignoring the target objective
+    pub fn is_out_of_resources(&self) -> bool {
+        self.steps >= self.budget.max_steps
+            || self.evaluations >= self.budget.max_evaluations
+            // only look at the clock if there is a time limit
+            || (self.budget.max_time.is_finite() && self.elapsed() > self.budget.max_time)
+    }
+
+    /// Counts a step
+    pub const fn add_step(&mut self) {
+        self.steps += 1;
+    }
+
+    /// Counts a number of evaluations
+    pub const fn add_evaluations(&mut self, evaluations: usize) {
+        self.evaluations = self.evaluations.saturating_add(evaluations);
+    }
+
+    /// Offers a point to the tracker, and returns true if it is the new best point
+    pub fn observe(&mut self, x: &Array1<usize>, objective: f64) -> bool {
+        if objective >= self.best_objective {
+            return false;
+        }
+
+        self.best_objective = objective;
+        self.best_x.clone_from(x);
+        self.trace.push((self.elapsed(), objective));
+
+        if let Some(incumbent) = &self.budget.incumbent {
+            incumbent.offer(x, objective);
+        }
+
+        true
+    }
+
+    /// Offers a point to the tracker, which also replaces the best point if it ties with it, and returns true if it is
+    /// a strictly better point
+    pub fn observe_no_worse(&mut self, x: &Array1<usize>, objective: f64) -> bool {
+        if objective > self.best_objective {
+            return false;
+        }
+
+        if objective < self.best_objective {
+            return self.observe(x, objective);
+        }
+
+        // a tie is not an improvement, so it is not traced or offered to the incumbent
+        self.best_x.clone_from(x);
+        false
+    }
+
+    /// Returns the number of steps taken so far
+    pub const fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Returns the best point so far
+    pub const fn best_x(&self) -> &Array1<usize> {
+        &self.best_x
+    }
+
+    /// Returns the objective of the best point so far
+    pub const fn best_objective(&self) -> f64 {
+        self.best_objective
+    }
+
+    /// Ends the run and returns the result
+    pub fn finish(self) -> HeuristicResult {
+        HeuristicResult {
+            elapsed: self.elapsed(),
+            x: self.best_x,
+            objective: self.best_objective,
+            evaluations: self.evaluations,
+            steps: self.steps,
+            trace: self.trace,
+        }
+    }
+}
+
+/// Checks that a heuristic that only stops when the budget is used up has a budget that is used up at some point
+fn assert_limited(heuristic: &dyn Heuristic, budget: &Budget) {
+    assert!(
+        budget.is_limited(),
+        "{} does not stop on its own, so it needs a time, evaluation or step limit",
+        heuristic.name()
+    );
+}
+
+/// Creates the prng of a run from the seed of a heuristic, so that every run with the same seed is the same
+fn seeded_prng(seed: u64) -> PRNG<JsfLarge> {
+    PRNG {
+        generator: JsfLarge::from(seed),
+    }
+}
+
+/// Simple local search, see `local_search::simple_local_search`
+pub struct SimpleLocalSearch;
+
+/// Gain criteria search, see `local_search::simple_gain_criteria_search`
+pub struct GainCriteriaSearch;
+
+/// Mixed local and gain criteria search, see `local_search::simple_mixed_search`
+pub struct MixedSearch;
+
+/// Particle swarm search, see `local_search::particle_swarm_search`, with the starting point as the first particle
+pub struct ParticleSwarmSearch {
+    pub num_particles: usize,
+    pub seed: u64,
+}
+
+/// Tabu search, see `local_search::tabu_search`
+pub struct TabuSearch {
+    pub tenure: usize,
+}
+
+/// Simulated annealing, see `local_search::simulated_annealing`, where the schedule is over num_sweeps sweeps
+pub struct SimulatedAnnealing {
+    pub schedule: AnnealingSchedule,
+    pub num_sweeps: usize,
+    pub num_restarts: usize,
+    pub seed: u64,
+}
+
+/// Iterated local search, see `local_search::iterated_local_search`
+pub struct IteratedLocalSearch {
+    pub seed: u64,
+}
+
+/// Variable neighborhood search, see `local_search::variable_neighborhood_search`
+pub struct VariableNeighborhoodSearch {
+    pub k_max: usize,
+    pub seed: u64,
+}
+
+/// Random search, see `local_search::random_search`, where each step is a random point
+pub struct RandomSearch {
+    pub seed: u64,
+}
+
+/// Runs a heuristic from the starting point and from num_starts - 1 random points in parallel, and keeps the best result
+///
+/// Each start gets the full budget. This covers the multi start searches of `local_search`.
+pub struct MultiStart<H: Heuristic> {
+    pub heuristic: H,
+    pub num_starts: usize,
+    pub seed: u64,
+}
+
+impl Heuristic for SimpleLocalSearch {
+    fn name(&self) -> String {
+        "simple local search".to_string()
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        let evaluator = DeltaEvaluator::new(qubo);
+        let mut state = evaluator.state(start);
+        let mut tracker = BudgetTracker::new(budget, start, state.objective());
+
+        local_search::run_simple_local_search(&mut state, &mut tracker);
+
+        tracker.finish()
+    }
+}
+
+impl Heuristic for GainCriteriaSearch {
+    fn name(&self) -> String {
+        "gain criteria search".to_string()
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        let evaluator = DeltaEvaluator::new(qubo);
+        let mut state = evaluator.state(start);
+        let mut tracker = BudgetTracker::new(budget, start, state.objective());
+
+        local_search::run_gain_criteria_search(&mut state, &mut tracker);
+
+        tracker.finish()
+    }
+}
+
+impl Heuristic for MixedSearch {
+    fn name(&self) -> String {
+        "mixed search".to_string()
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        let evaluator = DeltaEvaluator::new(qubo);
+        let mut state = evaluator.state(start);
+        let mut tracker = BudgetTracker::new(budget, start, state.objective());
+
+        local_search::run_mixed_search(&mut state, &mut tracker);
+
+        tracker.finish()
+    }
+}
+
+impl Heuristic for ParticleSwarmSearch {
+    fn name(&self) -> String {
+        "particle swarm search".to_string()
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        assert_limited(self, budget);
+
+        let mut prng = seeded_prng(self.seed);
+        let evaluator = DeltaEvaluator::new(qubo);
+
+        let mut particles = vec![evaluator.state(start)];
+        particles.extend(
+            (1..self.num_particles.max(1)).map(|_| {
+                evaluator.state(&generate_random_binary_point(qubo.num_x(), &mut prng, 0.5))
+            }),
+        );
+
+        let mut tracker = BudgetTracker::new(budget, start, particles[0].objective());
+        tracker.add_evaluations(particles.len() - 1);
+
+        local_search::run_particle_swarm_search(&mut particles, &mut tracker);
+
+        tracker.finish()
+    }
+}
+
+impl Heuristic for TabuSearch {
+    fn name(&self) -> String {
+        format!("tabu search (tenure {})", self.tenure)
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        assert_limited(self, budget);
+
+        let evaluator = DeltaEvaluator::new(qubo);
+        let mut state = evaluator.state(start);
+        let mut tracker = BudgetTracker::new(budget, start, state.objective());
+
+        local_search::run_tabu_loop(&mut state, self.tenure, &mut tracker);
+
+        tracker.finish()
+    }
+}
+
+impl Heuristic for SimulatedAnnealing {
+    fn name(&self) -> String {
+        "simulated annealing".to_string()
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        let mut prng = seeded_prng(self.seed);
+        let evaluator = DeltaEvaluator::new(qubo);
+        let mut tracker = BudgetTracker::new(budget, start, qubo.eval_usize(start));
+
+        local_search::run_simulated_annealing(
+            &evaluator,
+            start,
+            &self.schedule,
+            self.num_sweeps,
+            self.num_restarts,
+            &mut tracker,
+            &mut prng,
+        );
+
+        tracker.finish()
+    }
+}
+
+impl Heuristic for IteratedLocalSearch {
+    fn name(&self) -> String {
+        "iterated local search".to_string()
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        assert_limited(self, budget);
+
+        let mut prng = seeded_prng(self.seed);
+        let evaluator = DeltaEvaluator::new(qubo);
+        let mut state = evaluator.state(start);
+        let mut tracker = BudgetTracker::new(budget, start, state.objective());
+
+        local_search::run_iterated_local_search(&mut state, &mut tracker, &mut prng);
+
+        tracker.finish()
+    }
+}
+
+impl Heuristic for VariableNeighborhoodSearch {
+    fn name(&self) -> String {
+        format!("variable neighborhood search (k_max {})", self.k_max)
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        assert_limited(self, budget);
+
+        let mut prng = seeded_prng(self.seed);
+        let evaluator = DeltaEvaluator::new(qubo);
+        let mut state = evaluator.state(start);
+        let mut tracker = BudgetTracker::new(budget, start, state.objective());
+
+        local_search::run_variable_neighborhood_search(
+            &mut state,
+            self.k_max,
+            &mut tracker,
+            &mut prng,
+        );
+
+        tracker.finish()
+    }
+}
+
+impl Heuristic for RandomSearch {
+    fn name(&self) -> String {
+        "random search".to_string()
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        assert_limited(self, budget);
+
+        let mut prng = seeded_prng(self.seed);
+        let mut tracker = BudgetTracker::new(budget, start, qubo.eval_usize(start));
+
+        local_search::run_random_search(qubo, &mut tracker, &mut prng);
+
+        tracker.finish()
+    }
+}
+
+impl<H: Heuristic> Heuristic for MultiStart<H> {
+    fn name(&self) -> String {
+        format!("multi start {}", self.heuristic.name())
+    }
+
+    fn run(&self, qubo: &Qubo, start: &Array1<usize>, budget: &Budget) -> HeuristicResult {
+        let mut prng = seeded_prng(self.seed);
+
+        let mut starts = vec![start.clone()];
+        starts.extend(
+            (1..self.num_starts.max(1))
+                .map(|_| generate_random_binary_point(qubo.num_x(), &mut prng, 0.5)),
+        );
+
+        let results: Vec<_> = starts
+            .par_iter()
+            .map(|x_0| self.heuristic.run(qubo, x_0, budget))
+            .collect();
+
+        // the counts are over all of the starts, and the trace is of the start with the best result
+        let evaluations = results.iter().map(|r| r.evaluations).sum();
+        let steps = results.iter().map(|r| r.steps).sum();
+        let elapsed = results.iter().map(|r| r.elapsed).fold(0.0, f64::max);
+
+        let mut best = results
+            .into_iter()
+            .min_by(|a, b| a.objective.total_cmp(&b.objective))
+            .unwrap_or_else(|| self.heuristic.run(qubo, start, budget));
+
+        best.evaluations = evaluations;
+        best.steps = steps;
+        best.elapsed = elapsed;
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heuristic::*;
+    use crate::local_search::AnnealingSchedule;
+    use crate::tests::{brute_force_min, make_solver_qubo, make_test_prng};
+
+    fn all_heuristics() -> Vec<Box<dyn Heuristic>> {
+        vec![
+            Box::new(SimpleLocalSearch),
+            Box::new(GainCriteriaSearch),
+            Box::new(MixedSearch),
+            Box::new(ParticleSwarmSearch {
+                num_particles: 10,
+                seed: 1,
+            }),
+            Box::new(TabuSearch { tenure: 5 }),
+            Box::new(SimulatedAnnealing {
+                schedule: AnnealingSchedule::Geometric {
+                    beta_start: 0.1,
+                    beta_end: 10.0,
+                },
+                num_sweeps: 100,
+                num_restarts: 2,
+                seed: 2,
+            }),
+            Box::new(IteratedLocalSearch { seed: 3 }),
+            Box::new(VariableNeighborhoodSearch { k_max: 5, seed: 4 }),
+            Box::new(RandomSearch { seed: 5 }),
+            Box::new(MultiStart {
+                heuristic: TabuSearch { tenure: 5 },
+                num_starts: 4,
+                seed: 6,
+            }),
+        ]
+    }
+
+    #[test]
+    fn heuristics_respect_step_budget() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+        let budget = Budget::with_steps(20);
+
+        for heuristic in all_heuristics() {
+            let result = heuristic.run(&p, &x_0, &budget);
+
+            assert!(result.steps <= 20 * 4, "{}", heuristic.name());
+            assert!(
+                result.objective <= p.eval_usize(&x_0),
+                "{}",
+                heuristic.name()
+            );
+            assert!((p.eval_usize(&result.x) - result.objective).abs() < 1E-8);
+
+            // the trace only goes down, and ends at the best point
+            assert!(result.trace.windows(2).all(|w| w[1].1 < w[0].1));
+            assert_eq!(result.trace.last().unwrap().1, result.objective);
+        }
+    }
+
+    #[test]
+    fn heuristics_stop_at_target() {
+        let mut prng = make_test_prng();
+        let p = Qubo::make_random_qubo(12, &mut prng, 0.5);
+        let x_0 = Array1::zeros(p.num_x());
+
+        // a budget that stops the run as soon as the optimum is found
+        let mut budget = Budget::with_time(1.0);
+        budget.target_objective = Some(brute_force_min(&p) + 1E-10);
+
+        let result = IteratedLocalSearch { seed: 3 }.run(&p, &x_0, &budget);
+
+        assert!(result.objective <= brute_force_min(&p) + 1E-10);
+        assert!(result.elapsed < 1.0);
+    }
+
+    #[test]
+    fn default_budget_is_limited() {
+        assert!(Budget::default().is_limited());
+        assert!(Budget::with_steps(10).is_limited());
+        assert!(!Budget::unlimited().is_limited());
+        assert!(!Budget::with_time(f64::INFINITY).is_limited());
+    }
+
+    #[test]
+    #[should_panic(expected = "does not stop on its own")]
+    fn unlimited_budget_panics() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+
+        RandomSearch { seed: 0 }.run(&p, &x_0, &Budget::unlimited());
+    }
+
+    #[test]
+    fn heuristics_respect_evaluation_budget() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+
+        let mut budget = Budget::new();
+        budget.max_evaluations = 10 * p.num_x();
+
+        let result = TabuSearch { tenure: 5 }.run(&p, &x_0, &budget);
+
+        // the budget is checked before each step, so it can only be overshot by a single step
+        assert!(result.evaluations <= 11 * p.num_x());
+        assert!(result.steps <= 10);
+    }
+
+    #[test]
+    fn free_functions_match_heuristics() {
+        let p = make_solver_qubo();
+        let mut prng = make_test_prng();
+        let x_0 = generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+
+        let result = SimpleLocalSearch.run(&p, &x_0, &Budget::unlimited());
+        assert_eq!(
+            result.x,
+            local_search::simple_local_search(&p, &x_0, usize::MAX)
+        );
+
+        let result = TabuSearch { tenure: 5 }.run(&p, &x_0, &Budget::with_steps(200));
+        assert_eq!(
+            result.x,
+            local_search::tabu_search(&p, &x_0, 5, 200, f64::INFINITY)
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! This module contains the error type returned by the fallible QUBO file readers and writers
+//!
+//! Every parsing error carries the file path, the (1-indexed) line number and the offending token, so that a malformed
+//! instance file can be fixed without having to bisect it by hand.
+
+use std::fmt;
+use std::io::BufRead;
+
+/// The error type for reading and writing QUBO problem files
+#[derive(Debug)]
+pub enum QuboIOError {
+    /// The file could not be opened, read from, or written to
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    /// The header line does not match the header of the format
+    BadHeader {
+        path: String,
+        line: usize,
+        token: String,
+        expected: &'static str,
+    },
+    /// An index could not be parsed as a non-negative integer
+    InvalidIndex {
+        path: String,
+        line: usize,
+        token: String,
+    },
+    /// An index is larger than the number of variables declared in the header
+    IndexOutOfRange {
+        path: String,
+        line: usize,
+        token: String,
+        num_x: usize,
+    },
+    /// A coefficient could not be parsed as a float
+    InvalidFloat {
+        path: String,
+        line: usize,
+        token: String,
+    },
+    /// A coefficient was parsed, but is NaN or infinite
+    NonFiniteCoefficient {
+        path: String,
+        line: usize,
+        token: String,
+    },
+    /// A line does not have the expected number of columns
+    WrongColumnCount {
+        path: String,
+        line: usize,
+        found: usize,
+        expected: &'static str,
+    },
+    /// The file describes something that can not be represented as a QUBO, such as a constraint
+    Unsupported {
+        path: String,
+        line: usize,
+        token: String,
+        reason: &'static str,
+    },
+    /// The extension of an output file does not match any of the formats that can be written
+    UnsupportedExtension { path: String },
+    /// A binary file does not have the expected layout, e.g. a wrong magic number, version or length
+    InvalidBinary { path: String, reason: String },
+    /// The checksum stored in a binary file does not match its contents
+    ChecksumMismatch {
+        path: String,
+        expected: u32,
+        found: u32,
+    },
+    /// The file ended before all of the entries declared in a header were read
+    UnexpectedEndOfFile {
+        path: String,
+        line: usize,
+        expected: &'static str,
+    },
+}
+
+impl QuboIOError {
+    /// Returns the path of the file that caused the error
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Io { path, .. }
+            | Self::InvalidBinary { path, .. }
+            | Self::UnsupportedExtension { path }
+            | Self::ChecksumMismatch { path, .. }
+            | Self::BadHeader { path, .. }
+            | Self::InvalidIndex { path, .. }
+            | Self::IndexOutOfRange { path, .. }
+            | Self::InvalidFloat { path, .. }
+            | Self::NonFiniteCoefficient { path, .. }
+            | Self::WrongColumnCount { path, .. }
+            | Self::Unsupported { path, .. }
+            | Self::UnexpectedEndOfFile { path, .. } => path,
+        }
+    }
+
+    /// Returns the line number that caused the error, if the error is tied to a specific line
+    pub const fn line(&self) -> Option<usize> {
+        match self {
+            Self::Io { .. }
+            | Self::InvalidBinary { .. }
+            | Self::UnsupportedExtension { .. }
+            | Self::ChecksumMismatch { .. } => None,
+            Self::BadHeader { line, .. }
+            | Self::InvalidIndex { line, .. }
+            | Self::IndexOutOfRange { line, .. }
+            | Self::InvalidFloat { line, .. }
+            | Self::NonFiniteCoefficient { line, .. }
+            | Self::WrongColumnCount { line, .. }
+            | Self::Unsupported { line, .. }
+            | Self::UnexpectedEndOfFile { line, .. } => Some(*line),
+        }
+    }
+}
+
+impl fmt::Display for QuboIOError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{path}: {source}"),
+            Self::InvalidBinary { path, reason } => write!(f, "{path}: invalid binary QUBO, {reason}"),
+            Self::UnsupportedExtension { path } => write!(
+                f,
+                "{path}: unsupported file extension, expected .qubo, .qubob or .qubob.gz"
+            ),
+            Self::ChecksumMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{path}: checksum mismatch, expected {expected:#010x} but found {found:#010x}"
+            ),
+            Self::BadHeader {
+                path,
+                line,
+                token,
+                expected,
+            } => write!(f, "{path}:{line}: invalid header '{token}', expected {expected}"),
+            Self::InvalidIndex { path, line, token } => {
+                write!(f, "{path}:{line}: invalid index '{token}'")
+            }
+            Self::IndexOutOfRange {
+                path,
+                line,
+                token,
+                num_x,
+            } => write!(
+                f,
+                "{path}:{line}: index '{token}' is out of range for a problem with {num_x} variables"
+            ),
+            Self::InvalidFloat { path, line, token } => {
+                write!(f, "{path}:{line}: invalid coefficient '{token}'")
+            }
+            Self::NonFiniteCoefficient { path, line, token } => {
+                write!(f, "{path}:{line}: coefficient '{token}' is not finite")
+            }
+            Self::WrongColumnCount {
+                path,
+                line,
+                found,
+                expected,
+            } => write!(
+                f,
+                "{path}:{line}: found {found} columns, expected {expected}"
+            ),
+            Self::Unsupported {
+                path,
+                line,
+                token,
+                reason,
+            } => write!(f, "{path}:{line}: unsupported '{token}', {reason}"),
+            Self::UnexpectedEndOfFile {
+                path,
+                line,
+                expected,
+            } => write!(f, "{path}:{line}: unexpected end of file, expected {expected}"),
+        }
+    }
+}
+
+impl std::error::Error for QuboIOError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Wraps an I/O error with the path of the file it came from
+pub(crate) fn io_error(path: &str, source: std::io::Error) -> QuboIOError {
+    QuboIOError::Io {
+        path: path.to_string(),
+        source,
+    }
+}
+
+/// Reads the non-blank lines of a file along with their (1-indexed) line numbers, anything after one of the comment
+/// markers on a line is dropped
+pub(crate) fn read_data_lines(
+    path: &str,
+    comment_markers: &[char],
+) -> Result<Vec<(usize, String)>, QuboIOError> {
+    let file = std::fs::File::open(path).map_err(|err| io_error(path, err))?;
+    let reader = std::io::BufReader::new(file);
+    let mut lines = Vec::new();
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| io_error(path, err))?;
+        let data = match line.find(comment_markers) {
+            Some(index) => &line[..index],
+            None => &line,
+        };
+
+        if !data.trim().is_empty() {
+            lines.push((line_index + 1, data.to_string()));
+        }
+    }
+
+    Ok(lines)
+}
+
+/// The error for a file that ended while something was still expected, pointing at the line after the last data line
+pub(crate) fn end_of_file(
+    path: &str,
+    lines: &[(usize, String)],
+    expected: &'static str,
+) -> QuboIOError {
+    QuboIOError::UnexpectedEndOfFile {
+        path: path.to_string(),
+        line: lines.last().map_or(1, |(line_number, _)| line_number + 1),
+        expected,
+    }
+}
+
+/// Parses a header line of the form `num_x [offset]`, where the offset is optional and defaults to zero
+pub(crate) fn parse_header(path: &str, header: &str) -> Result<(usize, f64), QuboIOError> {
+    let header_data: Vec<_> = header.split_whitespace().collect();
+    let bad_header = || QuboIOError::BadHeader {
+        path: path.to_string(),
+        line: 1,
+        token: header.trim().to_string(),
+        expected: "the number of variables and an optional offset",
+    };
+
+    let num_x = match header_data.first() {
+        Some(token) if header_data.len() <= 2 => {
+            token.parse::<usize>().map_err(|_| bad_header())?
+        }
+        _ => return Err(bad_header()),
+    };
+
+    let offset = match header_data.get(1) {
+        Some(token) => parse_coefficient(path, 1, token)?,
+        None => 0.0,
+    };
+
+    Ok((num_x, offset))
+}
+
+/// Parses a 1-indexed node index, as used by graph formats, and converts it to a 0-indexed variable index
+pub(crate) fn parse_one_based_index(
+    path: &str,
+    line: usize,
+    token: &str,
+    num_x: usize,
+) -> Result<usize, QuboIOError> {
+    let index = token
+        .parse::<usize>()
+        .map_err(|_| QuboIOError::InvalidIndex {
+            path: path.to_string(),
+            line,
+            token: token.to_string(),
+        })?;
+
+    if index == 0 || index > num_x {
+        return Err(QuboIOError::IndexOutOfRange {
+            path: path.to_string(),
+            line,
+            token: token.to_string(),
+            num_x,
+        });
+    }
+
+    Ok(index - 1)
+}
+
+/// Parses a variable index, checking that it is in the range [0, num_x)
+pub(crate) fn parse_index(
+    path: &str,
+    line: usize,
+    token: &str,
+    num_x: usize,
+) -> Result<usize, QuboIOError> {
+    let index = token
+        .parse::<usize>()
+        .map_err(|_| QuboIOError::InvalidIndex {
+            path: path.to_string(),
+            line,
+            token: token.to_string(),
+        })?;
+
+    if index >= num_x {
+        return Err(QuboIOError::IndexOutOfRange {
+            path: path.to_string(),
+            line,
+            token: token.to_string(),
+            num_x,
+        });
+    }
+
+    Ok(index)
+}
+
+/// Parses a coefficient, rejecting NaN and infinite values
+pub(crate) fn parse_coefficient(path: &str, line: usize, token: &str) -> Result<f64, QuboIOError> {
+    let value = token
+        .parse::<f64>()
+        .map_err(|_| QuboIOError::InvalidFloat {
+            path: path.to_string(),
+            line,
+            token: token.to_string(),
+        })?;
+
+    if !value.is_finite() {
+        return Err(QuboIOError::NonFiniteCoefficient {
+            path: path.to_string(),
+            line,
+            token: token.to_string(),
+        });
+    }
+
+    Ok(value)
+}
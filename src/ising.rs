@@ -0,0 +1,400 @@
+//! This module contains the Ising struct and the conversions between the Ising and QUBO forms
+//!
+//! The Ising model works over spins, s in {-1, 1}^n, and is stored with a sparse coupling matrix in CSR order, it is
+//! not assumed to be symmetrical. The two forms are related by the substitution s = 2x - 1.
+
+use crate::io_error::{io_error, parse_coefficient, parse_header, parse_index, QuboIOError};
+use crate::qubo::Qubo;
+use ndarray::Array1;
+use sprs::{CsMat, TriMat};
+use std::io::BufRead;
+use std::io::Write;
+
+/// The Ising struct, which contains the coupling matrix, the field vector, and a constant offset. With the following form:
+///
+/// $$ \min_{s\in \{-1,1\}^n} s^T J s + h^Ts + \text{offset} $$
+#[derive(Clone)]
+pub struct Ising {
+    /// The coupling matrix of the Ising problem
+    pub j: CsMat<f64>,
+    /// The field vector of the Ising problem
+    pub h: Array1<f64>,
+    /// The constant term of the Ising problem
+    pub offset: f64,
+}
+
+impl Ising {
+    /// Generate a new Ising struct from a sparse coupling matrix, a dense field vector and a constant offset
+    ///
+    /// Example to create an Ising problem:
+    /// ```rust
+    /// use hercules::ising::Ising;
+    /// use sprs::CsMat;
+    /// use ndarray::Array1;
+    ///
+    /// let j = CsMat::<f64>::eye(10);
+    /// let h = Array1::<f64>::zeros(10);
+    /// let p = Ising::new(j, h, 0.0);
+    /// ```
+    pub const fn new(j: CsMat<f64>, h: Array1<f64>, offset: f64) -> Self {
+        Self { j, h, offset }
+    }
+
+    /// Return the number of spins in the Ising problem
+    pub fn num_spins(&self) -> usize {
+        self.h.len()
+    }
+
+    /// Given a point, s, calculate the energy of the Ising problem
+    ///
+    /// Example of calculating the energy of an Ising problem:
+    /// ```rust
+    /// use hercules::ising::Ising;
+    /// use ndarray::Array1;
+    /// use sprs::CsMat;
+    ///
+    /// let j = CsMat::<f64>::eye(3);
+    /// let h = Array1::from_vec(vec![1.0, 0.0, -1.0]);
+    /// let p = Ising::new(j, h, 0.5);
+    /// let s = Array1::from_vec(vec![1.0, -1.0, -1.0]);
+    ///
+    /// let energy = p.eval(&s);
+    /// ```
+    pub fn eval(&self, s: &Array1<f64>) -> f64 {
+        let temp = &self.j * s;
+        s.dot(&temp) + self.h.dot(s) + self.offset
+    }
+
+    /// Given an integral point, s, calculate the energy of the Ising problem
+    ///
+    /// Example of calculating the energy of an Ising problem at integral spins:
+    /// ```rust
+    /// use hercules::ising::Ising;
+    /// use ndarray::Array1;
+    /// use sprs::CsMat;
+    ///
+    /// let j = CsMat::<f64>::eye(3);
+    /// let h = Array1::from_vec(vec![1.0, 0.0, -1.0]);
+    /// let p = Ising::new(j, h, 0.5);
+    /// let s = Array1::from_vec(vec![1, -1, -1]);
+    ///
+    /// let energy = p.eval_spins(&s);
+    /// ```
+    pub fn eval_spins(&self, s: &Array1<isize>) -> f64 {
+        let s_f64 = s.mapv(|s_i| s_i as f64);
+        self.eval(&s_f64)
+    }
+
+    /// Converts a QUBO into the equivalent Ising problem, via the substitution x = (1 + s)/2.
+    ///
+    /// The energy of the Ising problem at s is the objective of the QUBO at the matching binary point. The diagonal of
+    /// Q becomes part of the field and the offset, as s_i^2 = 1.
+    ///
+    /// Example of converting a QUBO into an Ising problem:
+    /// ```rust
+    /// use hercules::ising::Ising;
+    /// use hercules::qubo::Qubo;
+    /// use smolprng::{PRNG, JsfLarge};
+    ///
+    /// let mut prng = PRNG {
+    ///    generator: JsfLarge::default(),
+    /// };
+    /// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+    /// let ising = Ising::from_qubo(&p);
+    /// ```
+    pub fn from_qubo(qubo: &Qubo) -> Self {
+        let num_spins = qubo.num_x();
+        let mut j = TriMat::<f64>::new((num_spins, num_spins));
+        let mut h = Array1::<f64>::zeros(num_spins);
+        let mut offset = qubo.offset;
+
+        // 0.5 q_ij x_i x_j = q_ij/8 (1 + s_i + s_j + s_i s_j)
+        for (&value, (i, k)) in &qubo.q {
+            if i == k {
+                // on the diagonal x_i^2 = x_i, so this is a linear term
+                h[i] += 0.25 * value;
+                offset += 0.25 * value;
+            } else {
+                j.add_triplet(i, k, 0.125 * value);
+                h[i] += 0.125 * value;
+                h[k] += 0.125 * value;
+                offset += 0.125 * value;
+            }
+        }
+
+        // c_i x_i = c_i/2 (1 + s_i)
+        for (i, &value) in qubo.c.iter().enumerate() {
+            h[i] += 0.5 * value;
+            offset += 0.5 * value;
+        }
+
+        Self::new(j.to_csr(), h, offset)
+    }
+
+    /// Converts the Ising problem into the equivalent QUBO, via the substitution s = 2x - 1.
+    ///
+    /// The objective of the QUBO at x is the energy of the Ising problem at the matching spins. The diagonal of J is a
+    /// constant, as s_i^2 = 1, and is moved into the offset of the QUBO.
+    ///
+    /// Example of solving an Ising problem with a QUBO heuristic:
+    /// ```rust
+    /// use hercules::ising::{binary_to_spins, Ising};
+    /// use hercules::qubo::Qubo;
+    /// use hercules::{initial_points, local_search};
+    /// use smolprng::{PRNG, JsfLarge};
+    ///
+    /// let mut prng = PRNG {
+    ///    generator: JsfLarge::default(),
+    /// };
+    /// let ising = Ising::from_qubo(&Qubo::make_random_qubo(10, &mut prng, 0.5));
+    ///
+    /// // convert to a QUBO and run a local search on it
+    /// let p = ising.to_qubo();
+    /// let x_0 = initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+    /// let x_sol = local_search::simple_local_search(&p, &x_0, 1000);
+    ///
+    /// // map the solution back to spins
+    /// let s_sol = binary_to_spins(&x_sol);
+    /// assert!((ising.eval_spins(&s_sol) - p.eval_usize(&x_sol)).abs() < 1E-10);
+    /// ```
+    pub fn to_qubo(&self) -> Qubo {
+        let num_x = self.num_spins();
+        let mut q = TriMat::<f64>::new((num_x, num_x));
+        let mut c = Array1::<f64>::zeros(num_x);
+        let mut offset = self.offset;
+
+        // j_ij s_i s_j = j_ij (4 x_i x_j - 2 x_i - 2 x_j + 1)
+        // on the diagonal s_i^2 = 1, so only the constant remains
+        for (&value, (i, k)) in &self.j {
+            offset += value;
+            if i != k {
+                q.add_triplet(i, k, 8.0 * value);
+                c[i] -= 2.0 * value;
+                c[k] -= 2.0 * value;
+            }
+        }
+
+        // h_i s_i = h_i (2 x_i - 1)
+        for (i, &value) in self.h.iter().enumerate() {
+            c[i] += 2.0 * value;
+            offset -= value;
+        }
+
+        Qubo::new_with_offset(q.to_csr(), c, offset)
+    }
+
+    /// Writes the Ising problem to a file in the .ising format
+    ///
+    /// The first line holds the number of spins and the offset, every following line is either `i j J_ij` or `i h_i`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if it is not possible to write to the file.
+    pub fn write_ising(&self, filename: &str) {
+        self.try_write_ising(filename)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Writes the Ising problem to a file in the .ising format, returning an error instead of panicking
+    ///
+    /// # Errors
+    ///
+    /// Returns `QuboIOError::Io` if the file can not be created or written to.
+    pub fn try_write_ising(&self, filename: &str) -> Result<(), QuboIOError> {
+        // open the file, create file writer
+        let file = std::fs::File::create(filename).map_err(|err| io_error(filename, err))?;
+        let mut writer = std::io::BufWriter::new(file);
+
+        // write the number of spins and the offset
+        writeln!(writer, "{} {}", self.num_spins(), self.offset)
+            .map_err(|err| io_error(filename, err))?;
+
+        // for every nonzero J_ij, write the indices and the value
+        for (value, (i, j)) in &self.j {
+            writeln!(writer, "{i} {j} {value}").map_err(|err| io_error(filename, err))?;
+        }
+
+        // for every nonzero h_i, write the index and the value
+        for (i, &value) in self.h.iter().enumerate() {
+            if value != 0.0 {
+                writeln!(writer, "{i} {value}").map_err(|err| io_error(filename, err))?;
+            }
+        }
+
+        writer.flush().map_err(|err| io_error(filename, err))
+    }
+
+    /// Reads an Ising problem from a file in the .ising format
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is not a file at the given filename in the .ising format.
+    #[must_use]
+    pub fn read_ising(filename: &str) -> Self {
+        Self::try_read_ising(filename).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Reads an Ising problem from a file in the .ising format, returning an error instead of panicking
+    ///
+    /// The offset in the header is optional, and is taken as zero if missing. Blank lines are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be read, or if any line is malformed.
+    pub fn try_read_ising(filename: &str) -> Result<Self, QuboIOError> {
+        // open the file
+        let file = std::fs::File::open(filename).map_err(|err| io_error(filename, err))?;
+        let reader = std::io::BufReader::new(file);
+        let mut lines = reader.lines();
+
+        // get the number of spins and the offset
+        let header = match lines.next() {
+            Some(line) => line.map_err(|err| io_error(filename, err))?,
+            None => String::new(),
+        };
+        let (num_spins, offset) = parse_header(filename, &header)?;
+
+        // set up the sparse matrix and dense vector
+        let mut j = TriMat::<f64>::new((num_spins, num_spins));
+        let mut h = Array1::<f64>::zeros(num_spins);
+
+        // read the file, the header is line 1
+        for (line_index, line) in lines.enumerate() {
+            let line = line.map_err(|err| io_error(filename, err))?;
+            let line_number = line_index + 2;
+            let row_data: Vec<_> = line.split_whitespace().collect();
+
+            match row_data.len() {
+                0 => {}
+                2 => {
+                    let i = parse_index(filename, line_number, row_data[0], num_spins)?;
+                    h[i] = parse_coefficient(filename, line_number, row_data[1])?;
+                }
+                3 => {
+                    let i = parse_index(filename, line_number, row_data[0], num_spins)?;
+                    let k = parse_index(filename, line_number, row_data[1], num_spins)?;
+                    let value = parse_coefficient(filename, line_number, row_data[2])?;
+                    j.add_triplet(i, k, value);
+                }
+                found => {
+                    return Err(QuboIOError::WrongColumnCount {
+                        path: filename.to_string(),
+                        line: line_number,
+                        found,
+                        expected: "2 (i h_i) or 3 (i j J_ij)",
+                    });
+                }
+            }
+        }
+
+        Ok(Self::new(j.to_csr(), h, offset))
+    }
+}
+
+/// Maps a spin vector, s in {-1, 1}^n, to the matching binary vector, x = (1 + s)/2
+///
+/// Example:
+/// ```rust
+/// use hercules::ising::spins_to_binary;
+/// use ndarray::Array1;
+///
+/// let s = Array1::from_vec(vec![1, -1, 1]);
+/// assert_eq!(spins_to_binary(&s), Array1::from_vec(vec![1, 0, 1]));
+/// ```
+pub fn spins_to_binary(s: &Array1<isize>) -> Array1<usize> {
+    s.mapv(|s_i| usize::from(s_i > 0))
+}
+
+/// Maps a binary vector, x in {0, 1}^n, to the matching spin vector, s = 2x - 1
+///
+/// Example:
+/// ```rust
+/// use hercules::ising::binary_to_spins;
+/// use ndarray::Array1;
+///
+/// let x = Array1::from_vec(vec![1, 0, 1]);
+/// assert_eq!(binary_to_spins(&x), Array1::from_vec(vec![1, -1, 1]));
+/// ```
+pub fn binary_to_spins(x: &Array1<usize>) -> Array1<isize> {
+    x.mapv(|x_i| if x_i == 0 { -1 } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::initial_points::generate_random_binary_points;
+    use crate::ising::{binary_to_spins, spins_to_binary, Ising};
+    use crate::qubo::Qubo;
+    use crate::tests::{make_solver_qubo, make_test_prng};
+    use ndarray::Array1;
+    use sprs::TriMat;
+
+    #[test]
+    fn qubo_to_ising_energy() {
+        let p = make_solver_qubo();
+        let ising = Ising::from_qubo(&p);
+
+        let mut prng = make_test_prng();
+        let xs = generate_random_binary_points(p.num_x(), 50, &mut prng);
+
+        for x in &xs {
+            let s = binary_to_spins(x);
+            assert!((p.eval_usize(x) - ising.eval_spins(&s)).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn ising_to_qubo_energy() {
+        let mut couplings = TriMat::new((3, 3));
+        couplings.add_triplet(0, 1, 1.0);
+        couplings.add_triplet(1, 2, -0.5);
+        couplings.add_triplet(2, 2, 2.0);
+        let field = Array1::from_vec(vec![0.5, -1.0, 0.25]);
+        let ising = Ising::new(couplings.to_csr(), field, 1.5);
+
+        let p = ising.to_qubo();
+
+        // check every point, as there are only 8 of them
+        for mask in 0..8usize {
+            let x = Array1::from_vec(vec![mask & 1, (mask >> 1) & 1, (mask >> 2) & 1]);
+            let s = binary_to_spins(&x);
+            assert!((p.eval_usize(&x) - ising.eval_spins(&s)).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn qubo_ising_round_trip() {
+        let mut p = make_solver_qubo();
+        p.offset = 3.5;
+        let p_round_trip = Ising::from_qubo(&p).to_qubo();
+
+        let mut prng = make_test_prng();
+        let xs = generate_random_binary_points(p.num_x(), 50, &mut prng);
+
+        for x in &xs {
+            assert!((p.eval_usize(x) - p_round_trip.eval_usize(x)).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn spin_binary_mapping() {
+        let x = Array1::from_vec(vec![0, 1, 1, 0]);
+        let s = binary_to_spins(&x);
+
+        assert_eq!(s, Array1::from_vec(vec![-1, 1, 1, -1]));
+        assert_eq!(spins_to_binary(&s), x);
+    }
+
+    #[test]
+    fn ising_read_write_consistency() {
+        let p = Ising::from_qubo(&Qubo::make_random_qubo(10, &mut make_test_prng(), 0.3));
+        let path = std::env::temp_dir().join("hercules_test.ising");
+        let path = path.to_str().unwrap();
+
+        p.write_ising(path);
+        let q = Ising::read_ising(path);
+
+        assert_eq!(p.j, q.j);
+        assert_eq!(p.h, q.h);
+        assert!((p.offset - q.offset).abs() < 1E-12);
+    }
+}
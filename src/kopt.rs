@@ -1,19 +1,105 @@
-// Main Backend for running K-Opt via the branch and bound algorithm
+//! Main backend for running k-opt, a local search over neighborhoods of k variables
+//!
+//! Starting from an incumbent, k-opt picks a subset of k variables, fixes every other variable at its incumbent value and
+//! solves the induced sub-QUBO over the subset exactly. If the optimal assignment of the subset is better than the
+//! incumbent, then it is accepted. Small subsets are solved by enumerating all 2^k assignments in Gray code order, so each
+//! assignment costs O(k), and larger subsets are solved with the B&B solver.
+//!
+//! Checking every subset is exponential in k, so the search stops once a number of subsets in a row fail to improve the
+//! incumbent, or at a time limit.
 
-use crate::branch_stratagy::BranchStrategySelection;
-use crate::branch_subproblem::SubProblemSelection;
 use crate::branchbound::BBSolver;
+use crate::branchbound_utils::get_current_time;
+use crate::flip_state::{DeltaEvaluator, FlipState};
 use crate::persistence::compute_iterative_persistence;
 use crate::qubo::Qubo;
 use crate::solver_options::SolverOptions;
 use ndarray::Array1;
+use smolprng::{Algorithm, JsfLarge, PRNG};
+use sprs::TriMat;
 use std::collections::HashMap;
 
-/// run k-opt on a given QUBO
+/// How the subsets of variables are picked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubsetSelection {
+    /// k variables picked uniformly at random
+    Random,
+    /// A random variable, grown into a connected subset by adding the variable most strongly coupled to the subset
+    Connectivity,
+    /// k variables picked at random out of the 2k variables with the lowest flip gain
+    FlipGain,
+}
+
+/// The largest subsets that are solved by enumeration, larger subsets are always solved with B&B
+const MAX_ENUMERATION_LIMIT: usize = 30;
+
+/// Options for k-opt
+pub struct KOptOptions {
+    /// The number of variables in each subset
+    pub k: usize,
+    pub selection: SubsetSelection,
+    /// The search stops after this many subsets in a row do not improve the incumbent
+    pub max_failures: usize,
+    pub max_time: f64,
+    /// Subsets of up to this many variables, and at most 30, are solved by enumeration, and larger subsets with B&B
+    pub enumeration_limit: usize,
+    pub seed: u64,
+    /// The options of the B&B solver for the subsets that are too large to enumerate
+    pub solver_options: SolverOptions,
+}
+
+impl KOptOptions {
+    pub fn new() -> Self {
+        let mut solver_options = SolverOptions::new();
+        solver_options.max_time = 10.0;
+        solver_options.verbose = 0;
+
+        Self {
+            k: 8,
+            selection: SubsetSelection::Connectivity,
+            max_failures: 100,
+            max_time: 100.0,
+            enumeration_limit: 16,
+            seed: 0,
+            solver_options,
+        }
+    }
+}
+
+impl Default for KOptOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// run k-opt on a given QUBO, after fixing the persistent variables
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use hercules::kopt::{solve_kopt, KOptOptions, SubsetSelection};
+/// use smolprng::{PRNG, JsfLarge};
+/// use std::collections::HashMap;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(30, &mut prng, 0.2);
+///
+/// // search over random subsets of 6 variables, until 50 subsets in a row do not improve
+/// let mut options = KOptOptions::new();
+/// options.k = 6;
+/// options.selection = SubsetSelection::Random;
+/// options.max_failures = 50;
+///
+/// let x_sol = solve_kopt(&p, &HashMap::new(), None, &options);
+/// ```
 pub fn solve_kopt(
     qubo: &Qubo,
     fixed_variables: &HashMap<usize, usize>,
     initial_guess: Option<Array1<usize>>,
+    options: &KOptOptions,
 ) -> Array1<usize> {
     // create a hashmap to store the persistent variables
     let mut persistent = fixed_variables.clone();
@@ -21,30 +107,424 @@ pub fn solve_kopt(
     // compute the persistent variables
     persistent = compute_iterative_persistence(qubo, &persistent, 100);
 
-    // create a new QUBO to store the reduced QUBO
-    let reduced_qubo = qubo.clone();
-
-    let options = SolverOptions {
-        fixed_variables: persistent,
-        branch_strategy: BranchStrategySelection::MostViolated,
-        sub_problem_solver: SubProblemSelection::Clarabel,
-        max_time: 100.0,
-        seed: 0,
-        verbose: 1,
-        threads: 1,
+    // create a new QUBO to store the reduced QUBO, only the free variables are left to search over
+    let (reduced_qubo, _, index_map) = qubo.fix_variables(&persistent);
+
+    // if everything is fixed, then there is nothing left to solve
+    if index_map.is_empty() {
+        return Qubo::expand_solution(&Array1::zeros(0), &persistent, &index_map);
+    }
+
+    // start from the guess, if we are provided one, by restricting it to the free variables
+    let x_0 = initial_guess.as_ref().map_or_else(
+        || Array1::zeros(index_map.len()),
+        |x_0| index_map.iter().map(|&i| x_0[i]).collect(),
+    );
+
+    let x_reduced = kopt_search(&reduced_qubo, &x_0, options);
+    let x = Qubo::expand_solution(&x_reduced, &persistent, &index_map);
+
+    // the guess might not agree with the persistent variables, so keep it if it is still better
+    initial_guess
+        .filter(|x_0| qubo.eval_usize(x_0) < qubo.eval_usize(&x))
+        .unwrap_or(x)
+}
+
+/// Runs the k-opt local search from x_0, and returns the best point found
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use hercules::kopt::{kopt_search, KOptOptions};
+/// use smolprng::{PRNG, JsfLarge};
+/// use ndarray::Array1;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(30, &mut prng, 0.2);
+/// let x_0 = Array1::zeros(p.num_x());
+///
+/// // the result is at least as good as the start, and can not be improved by changing any connected set of 8 variables
+/// // that was tried
+/// let x_sol = kopt_search(&p, &x_0, &KOptOptions::new());
+///
+/// assert!(p.eval_usize(&x_sol) <= p.eval_usize(&x_0));
+/// ```
+pub fn kopt_search(qubo: &Qubo, x_0: &Array1<usize>, options: &KOptOptions) -> Array1<usize> {
+    // there is no subset to pick without variables
+    if qubo.num_x() == 0 {
+        return x_0.clone();
+    }
+
+    let start_time = get_current_time();
+    let evaluator = DeltaEvaluator::new(qubo);
+    let mut state = evaluator.state(x_0);
+    let mut prng = PRNG {
+        generator: JsfLarge::from(options.seed),
     };
 
-    // use branch and bound to solve the problem
-    let mut solver = BBSolver::new(reduced_qubo, options);
+    let k = options.k.clamp(1, qubo.num_x());
+    let enumeration_limit = options.enumeration_limit.min(MAX_ENUMERATION_LIMIT);
+    let mut failures = 0;
+
+    while failures < options.max_failures && get_current_time() - start_time < options.max_time {
+        let subset = match options.selection {
+            SubsetSelection::Random => random_subset(&state, k, &mut prng),
+            SubsetSelection::Connectivity => connected_subset(&evaluator, &state, k, &mut prng),
+            SubsetSelection::FlipGain => flip_gain_subset(&state, k, &mut prng),
+        };
+
+        let (q_sub, c_sub) = induced_subproblem(&evaluator, &state, &subset);
+        let y_0: Vec<_> = subset.iter().map(|&i| state.x()[i]).collect();
+
+        let (y, delta) = match subset.len() <= enumeration_limit {
+            true => enumerate_subproblem(&q_sub, &c_sub, &y_0),
+            false => branch_and_bound_subproblem(&q_sub, &c_sub, &y_0, &options.solver_options),
+        };
+
+        // only accept strict improvements, so that the search can not cycle between equal points
+        if delta < -1E-12 {
+            for (&i, &y_i) in subset.iter().zip(y.iter()) {
+                if state.x()[i] != y_i {
+                    state.flip(i);
+                }
+            }
+            failures = 0;
+        } else {
+            failures += 1;
+        }
+    }
+
+    state.into_x()
+}
+
+/// Picks k distinct variables uniformly at random
+fn random_subset<T: Algorithm>(state: &FlipState, k: usize, prng: &mut PRNG<T>) -> Vec<usize> {
+    let mut indices: Vec<_> = (0..state.x().len()).collect();
+    partial_shuffle(&mut indices, k, prng);
+    indices.truncate(k);
+    indices
+}
+
+/// Grows a connected subset from a random variable, adding the variable with the strongest total coupling to the subset
+/// at each step, or a random variable if nothing is coupled to the subset
+fn connected_subset<T: Algorithm>(
+    evaluator: &DeltaEvaluator,
+    state: &FlipState,
+    k: usize,
+    prng: &mut PRNG<T>,
+) -> Vec<usize> {
+    let num_x = state.x().len();
+    let mut in_subset = vec![false; num_x];
+    let mut strength = HashMap::new();
+    let mut subset = Vec::with_capacity(k);
+
+    #[allow(clippy::cast_possible_truncation)]
+    let mut next = prng.gen_u64() as usize % num_x;
+
+    while subset.len() < k {
+        in_subset[next] = true;
+        subset.push(next);
+        strength.remove(&next);
+
+        // the total coupling of each variable outside of the subset to the subset
+        for (j, value) in evaluator.couplings(next) {
+            if !in_subset[j] {
+                *strength.entry(j).or_insert(0.0) += value.abs();
+            }
+        }
+
+        let strongest = strength
+            .iter()
+            .max_by(|(i, a), (j, b)| a.total_cmp(b).then(j.cmp(i)))
+            .map(|(&i, _)| i);
+
+        if let Some(i) = strongest {
+            next = i;
+            continue;
+        }
+
+        // nothing outside is coupled to the subset, so jump to a random variable outside of it
+        let outside: Vec<_> = (0..num_x).filter(|&i| !in_subset[i]).collect();
+        if outside.is_empty() {
+            break;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let r = prng.gen_u64() as usize % outside.len();
+        next = outside[r];
+    }
+
+    subset
+}
+
+/// Picks k variables at random out of the 2k variables with the lowest flip gain
+fn flip_gain_subset<T: Algorithm>(state: &FlipState, k: usize, prng: &mut PRNG<T>) -> Vec<usize> {
+    let deltas = state.deltas();
+    let mut indices: Vec<_> = (0..state.x().len()).collect();
+    indices.sort_by(|&i, &j| deltas[i].total_cmp(&deltas[j]).then(i.cmp(&j)));
+    indices.truncate(2 * k);
+
+    partial_shuffle(&mut indices, k, prng);
+    indices.truncate(k);
+    indices
+}
+
+/// Shuffles the first k entries, so that they are a uniform random subset of the entries
+fn partial_shuffle<T: Algorithm>(indices: &mut [usize], k: usize, prng: &mut PRNG<T>) {
+    for j in 0..k.min(indices.len()) {
+        #[allow(clippy::cast_possible_truncation)]
+        let r = j + prng.gen_u64() as usize % (indices.len() - j);
+        indices.swap(j, r);
+    }
+}
+
+/// Builds the sub-QUBO over the subset with every other variable fixed at its current value, as a dense symmetric Hessian
+/// and a linear term. The objective of the sub-QUBO differs from the full objective by a constant.
+fn induced_subproblem(
+    evaluator: &DeltaEvaluator,
+    state: &FlipState,
+    subset: &[usize],
+) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let q_sub: Vec<Vec<f64>> = subset
+        .iter()
+        .map(|&i| subset.iter().map(|&j| evaluator.coupling(i, j)).collect())
+        .collect();
+
+    // the gradient includes the couplings inside the subset, which are not part of the linear term
+    let c_sub = subset
+        .iter()
+        .enumerate()
+        .map(|(a, &i)| {
+            let inside: f64 = subset
+                .iter()
+                .enumerate()
+                .map(|(b, &j)| q_sub[a][b] * state.x()[j] as f64)
+                .sum();
+            state.gradient()[i] - inside
+        })
+        .collect();
+
+    (q_sub, c_sub)
+}
 
-    // warm start the solver, if we are provided a guess
+/// The objective 0.5 y^T Q y + c^T y of an assignment of a sub-QUBO
+fn subproblem_objective(q_sub: &[Vec<f64>], c_sub: &[f64], y: &[usize]) -> f64 {
+    let mut objective = 0.0;
 
-    if let Some(x_0) = initial_guess {
-        solver.warm_start(x_0);
+    for (a, &y_a) in y.iter().enumerate() {
+        if y_a == 1 {
+            objective += c_sub[a];
+            for (b, &y_b) in y.iter().enumerate() {
+                objective += 0.5 * q_sub[a][b] * y_b as f64;
+            }
+        }
     }
 
-    // solve the problem
+    objective
+}
+
+/// Finds the optimal assignment of a sub-QUBO by visiting every assignment in Gray code order, and returns it along with
+/// the change in the objective from y_0
+fn enumerate_subproblem(q_sub: &[Vec<f64>], c_sub: &[f64], y_0: &[usize]) -> (Vec<usize>, f64) {
+    let k = c_sub.len();
+
+    // start at y = 0, where the objective is 0 and the gradient is c
+    let mut y = vec![0usize; k];
+    let mut grad = c_sub.to_vec();
+    let mut objective = 0.0;
+
+    let mut best_y = y.clone();
+    let mut best_obj = objective;
+
+    // each step of the Gray code flips the lowest set bit of the step number
+    for step in 1usize..1 << k {
+        let i = step.trailing_zeros() as usize;
+        let direction = 1.0 - 2.0 * y[i] as f64;
+
+        objective += 0.5 * q_sub[i][i] + direction * grad[i];
+        y[i] = 1 - y[i];
+
+        for (j, g_j) in grad.iter_mut().enumerate() {
+            *g_j += direction * q_sub[j][i];
+        }
+
+        if objective < best_obj {
+            best_obj = objective;
+            best_y.clone_from(&y);
+        }
+    }
+
+    let delta = best_obj - subproblem_objective(q_sub, c_sub, y_0);
+    (best_y, delta)
+}
+
+/// Finds the optimal assignment of a sub-QUBO with the B&B solver, warm started at y_0, and returns it along with the
+/// change in the objective from y_0
+fn branch_and_bound_subproblem(
+    q_sub: &[Vec<f64>],
+    c_sub: &[f64],
+    y_0: &[usize],
+    solver_options: &SolverOptions,
+) -> (Vec<usize>, f64) {
+    let k = c_sub.len();
+
+    let mut q = TriMat::new((k, k));
+    for (a, row) in q_sub.iter().enumerate() {
+        for (b, &value) in row.iter().enumerate() {
+            if value != 0.0 {
+                q.add_triplet(a, b, value);
+            }
+        }
+    }
+    let p = Qubo::new_with_c(q.to_csr(), Array1::from_vec(c_sub.to_vec()));
+
+    // the relaxation of the sub-problem needs to be convex, and shifting the diagonal does not change binary objectives
+    let min_eig = p
+        .hess_eigenvalues()
+        .iter()
+        .fold(f64::INFINITY, |a, &b| a.min(b));
+    let p = match min_eig > 0.0 {
+        true => p,
+        false => p.make_diag_transform(min_eig.abs() + 1.0),
+    };
+
+    let mut solver = BBSolver::new(p, solver_options.clone());
+    solver.warm_start(Array1::from_vec(y_0.to_vec()));
     solver.solve();
 
-    solver.best_solution
+    let y = solver.best_solution.to_vec();
+    let delta = subproblem_objective(q_sub, c_sub, &y) - subproblem_objective(q_sub, c_sub, y_0);
+
+    (y, delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flip_state::DeltaEvaluator;
+    use crate::kopt::*;
+    use crate::tests::{brute_force_min, make_solver_qubo, make_test_prng};
+    use sprs::CsMat;
+
+    #[test]
+    fn induced_subproblem_matches_full_objective() {
+        let p = make_solver_qubo();
+        let mut prng = make_test_prng();
+        let evaluator = DeltaEvaluator::new(&p);
+        let x_0 = crate::initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+        let state = evaluator.state(&x_0);
+
+        let subset = vec![3, 17, 4, 40, 22];
+        let (q_sub, c_sub) = induced_subproblem(&evaluator, &state, &subset);
+        let y_0: Vec<_> = subset.iter().map(|&i| x_0[i]).collect();
+        let base = p.eval_usize(&x_0) - subproblem_objective(&q_sub, &c_sub, &y_0);
+
+        // every assignment of the subset differs from the full objective by the same constant
+        for mask in 0..1usize << subset.len() {
+            let mut x = x_0.clone();
+            let y: Vec<_> = (0..subset.len()).map(|a| (mask >> a) & 1).collect();
+            for (a, &i) in subset.iter().enumerate() {
+                x[i] = y[a];
+            }
+
+            let sub_obj = subproblem_objective(&q_sub, &c_sub, &y);
+            assert!((p.eval_usize(&x) - (sub_obj + base)).abs() < 1E-8);
+        }
+
+        // and the enumeration finds the best of them
+        let (y, delta) = enumerate_subproblem(&q_sub, &c_sub, &y_0);
+        let best = (0..1usize << subset.len())
+            .map(|mask| {
+                let y: Vec<_> = (0..subset.len()).map(|a| (mask >> a) & 1).collect();
+                subproblem_objective(&q_sub, &c_sub, &y)
+            })
+            .fold(f64::INFINITY, f64::min);
+        assert!((subproblem_objective(&q_sub, &c_sub, &y) - best).abs() < 1E-10);
+        assert!(delta <= 0.0);
+    }
+
+    #[test]
+    fn kopt_search_is_optimal_with_k_equal_n() {
+        let mut prng = make_test_prng();
+        let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+
+        let mut options = KOptOptions::new();
+        options.k = 10;
+        options.max_failures = 1;
+
+        let x = kopt_search(&p, &Array1::zeros(p.num_x()), &options);
+
+        assert!((p.eval_usize(&x) - brute_force_min(&p)).abs() < 1E-10);
+    }
+
+    #[test]
+    fn kopt_selection_strategies_improve() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+
+        for selection in [
+            SubsetSelection::Random,
+            SubsetSelection::Connectivity,
+            SubsetSelection::FlipGain,
+        ] {
+            let mut options = KOptOptions::new();
+            options.k = 4;
+            options.selection = selection;
+            options.max_failures = 20;
+
+            let x = kopt_search(&p, &x_0, &options);
+            assert!(p.eval_usize(&x) < p.eval_usize(&x_0));
+        }
+    }
+
+    #[test]
+    fn kopt_branch_and_bound_subproblems() {
+        let mut prng = make_test_prng();
+        let p = Qubo::make_random_qubo(6, &mut prng, 0.5);
+
+        // force every subset through the B&B solver
+        let mut options = KOptOptions::new();
+        options.k = 6;
+        options.max_failures = 1;
+        options.enumeration_limit = 0;
+
+        let x = solve_kopt(&p, &HashMap::new(), Some(Array1::zeros(6)), &options);
+
+        assert!((p.eval_usize(&x) - brute_force_min(&p)).abs() < 1E-10);
+    }
+
+    #[test]
+    fn kopt_large_enumeration_limit() {
+        let mut prng = make_test_prng();
+        let p = Qubo::make_random_qubo(70, &mut prng, 0.02);
+        let x_0 = Array1::zeros(p.num_x());
+
+        // a subset of 70 variables can not be enumerated, so it goes to the B&B solver instead
+        let mut options = KOptOptions::new();
+        options.k = 70;
+        options.max_failures = 1;
+        options.enumeration_limit = usize::MAX;
+
+        let x = kopt_search(&p, &x_0, &options);
+
+        assert!(p.eval_usize(&x) <= p.eval_usize(&x_0));
+    }
+
+    #[test]
+    fn kopt_without_variables() {
+        let p = Qubo::new(CsMat::zero((0, 0)));
+        let x_0 = Array1::zeros(0);
+
+        for selection in [
+            SubsetSelection::Random,
+            SubsetSelection::Connectivity,
+            SubsetSelection::FlipGain,
+        ] {
+            let mut options = KOptOptions::new();
+            options.selection = selection;
+
+            assert!(kopt_search(&p, &x_0, &options).is_empty());
+        }
+    }
 }
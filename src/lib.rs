@@ -21,25 +21,36 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 use pyo3::prelude::*;
 
-mod branch_node;
-mod branch_stratagy;
-mod branch_subproblem;
-mod branchbound;
+pub mod branch_node;
+pub mod branch_stratagy;
+pub mod branch_subproblem;
+pub mod branchbound;
 pub mod branchbound_utils;
 mod branchboundlogger;
-mod constraint;
+pub mod constraint;
 pub mod constraint_reduction;
 pub mod early_termination;
+pub mod elite_pool;
+pub mod flip_state;
+pub mod heuristic;
 pub mod initial_points;
-mod kopt;
+pub mod io_error;
+pub mod ising;
+pub mod kopt;
 pub mod local_search;
 pub mod local_search_utils;
 mod lower_bound;
+pub mod maxcut;
+pub mod memetic;
+pub mod parallel_tempering;
+pub mod path_relinking;
 pub mod persistence;
+pub mod portfolio;
 mod preprocess;
 pub mod python_interopt;
 pub mod qubo;
-mod solver_options;
+pub mod qubo_binary;
+pub mod solver_options;
 pub mod utils;
 pub mod variable_reduction;
 
@@ -60,8 +71,17 @@ fn hercules(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(gls, m)?)?;
     m.add_function(wrap_pyfunction!(mls, m)?)?;
     m.add_function(wrap_pyfunction!(msls, m)?)?;
+    m.add_function(wrap_pyfunction!(tabu_from_file, m)?)?;
+    m.add_function(wrap_pyfunction!(tabu, m)?)?;
+    m.add_function(wrap_pyfunction!(mtabu, m)?)?;
+    m.add_function(wrap_pyfunction!(ils, m)?)?;
+    m.add_function(wrap_pyfunction!(vns, m)?)?;
+    m.add_function(wrap_pyfunction!(portfolio_search, m)?)?;
     m.add_function(wrap_pyfunction!(read_qubo, m)?)?;
+    m.add_function(wrap_pyfunction!(read_orlib, m)?)?;
     m.add_function(wrap_pyfunction!(write_qubo, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_qubo, m)?)?;
+    m.add_function(wrap_pyfunction!(read_gset, m)?)?;
     m.add_function(wrap_pyfunction!(get_persistence, m)?)?;
     m.add_function(wrap_pyfunction!(solve_branch_bound, m)?)?;
     m.add_function(wrap_pyfunction!(convex_symmetric_form, m)?)?;
@@ -97,6 +117,34 @@ mod tests {
         }
     }
 
+    /// finds the minimum objective by enumerating every binary point, only for small problems
+    pub(crate) fn brute_force_min(p: &Qubo) -> f64 {
+        (0..1usize << p.num_x())
+            .map(|k| p.eval_usize(&Array1::from_shape_fn(p.num_x(), |i| (k >> i) & 1)))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// checks that a solver finds the optimal objective of a small random QUBO, the solver is given the QUBO and the
+    /// prng that made it, and returns its best point
+    pub(crate) fn assert_finds_optimum<F>(num_x: usize, solver: F)
+    where
+        F: FnOnce(&Qubo, &mut PRNG<JsfLarge>) -> Array1<usize>,
+    {
+        let mut prng = make_test_prng();
+        let p = Qubo::make_random_qubo(num_x, &mut prng, 0.5);
+
+        let x = solver(&p, &mut prng);
+
+        assert!((p.eval_usize(&x) - brute_force_min(&p)).abs() < 1E-10);
+    }
+
+    /// writes the given contents to a file in the temp directory, and returns the path
+    pub(crate) fn write_temp_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
     // pub(crate) fn pardalos_hard_problem(n:usize) -> Qubo{
     //
     // }
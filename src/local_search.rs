@@ -8,14 +8,25 @@
 //! - Multi simple local search
 //! - Multi simple gain criteria search
 //! - Simple Particle Swarm Search
+//! - Tabu search
+//! - Multi tabu search
+//! - Simulated annealing
+//! - Parallel simulated annealing
+//! - Iterated local search
+//! - Variable neighborhood search
+//!
+//! The single flip searches are built on `FlipState`, so that each step costs O(n) + O(degree) instead of O(|Q|). Each
+//! search is also available as a `heuristic::Heuristic`, which shares the loop of the search through a `BudgetTracker`.
 
+use crate::flip_state::{DeltaEvaluator, FlipState};
+use crate::heuristic::{Budget, BudgetTracker};
 use crate::initial_points::generate_random_binary_point;
 use crate::local_search_utils;
 use crate::qubo::Qubo;
-use crate::utils::get_best_point;
+use crate::utils::mutate_solution;
 use ndarray::Array1;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use smolprng::{Algorithm, PRNG};
+use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+use smolprng::{Algorithm, JsfLarge, PRNG};
 
 /// Given a QUBO and an integral initial point, run simple local search until the point converges or the step limit is hit.
 ///
@@ -39,19 +50,25 @@ use smolprng::{Algorithm, PRNG};
 /// let x_sol = local_search::simple_local_search(&p, &x_0, 1000);
 /// ```
 pub fn simple_local_search(qubo: &Qubo, x_0: &Array1<usize>, max_steps: usize) -> Array1<usize> {
-    let mut x = x_0.clone();
-    let variables = (0..qubo.num_x()).collect();
-    let mut x_1 = local_search_utils::one_step_local_search_improved(qubo, &x, &variables);
-    let mut steps = 0;
+    let evaluator = DeltaEvaluator::new(qubo);
+    let mut state = evaluator.state(x_0);
 
-    while x_1 != x && steps <= max_steps {
-        x = x_1.clone();
-        // apply the local search to the selected variables
-        x_1 = local_search_utils::one_step_local_search_improved(qubo, &x, &variables);
-        steps += 1;
-    }
+    // the step limit allows max_steps + 1 flips
+    let budget = Budget::with_steps(max_steps.saturating_add(1));
+    let mut tracker = BudgetTracker::new(&budget, x_0, state.objective());
+
+    run_simple_local_search(&mut state, &mut tracker);
 
-    x_1
+    state.into_x()
+}
+
+/// The simple local search loop, flipping the best variable until there are no improving flips or the budget is used up
+pub(crate) fn run_simple_local_search(state: &mut FlipState, tracker: &mut BudgetTracker) {
+    while !tracker.is_exhausted() && local_search_utils::one_step_flip(state) {
+        tracker.add_step();
+        tracker.add_evaluations(state.x().len());
+        tracker.observe(state.x(), state.objective());
+    }
 }
 
 /// Given a QUBO and a vector of initial points, run local searches on each initial point and return all of the solutions.
@@ -112,17 +129,25 @@ pub fn simple_gain_criteria_search(
     x_0: &Array1<usize>,
     max_steps: usize,
 ) -> Array1<usize> {
-    let mut x = x_0.clone();
-    let mut x_1 = local_search_utils::get_gain_criteria(qubo, &x);
-    let mut steps = 0;
+    let evaluator = DeltaEvaluator::new(qubo);
+    let mut state = evaluator.state(x_0);
 
-    while x_1 != x && steps <= max_steps {
-        x = x_1.clone();
-        x_1 = local_search_utils::get_gain_criteria(qubo, &x);
-        steps += 1;
-    }
+    // the step limit allows the first move to the gain criteria, and then max_steps + 1 more
+    let budget = Budget::with_steps(max_steps.saturating_add(2));
+    let mut tracker = BudgetTracker::new(&budget, x_0, state.objective());
+
+    run_gain_criteria_search(&mut state, &mut tracker);
+
+    state.into_x()
+}
 
-    x_1
+/// The gain criteria search loop, moving to the gain criteria until the point does not change or the budget is used up
+pub(crate) fn run_gain_criteria_search(state: &mut FlipState, tracker: &mut BudgetTracker) {
+    while !tracker.is_exhausted() && local_search_utils::gain_criteria_flip(state) {
+        tracker.add_step();
+        tracker.add_evaluations(state.x().len());
+        tracker.observe(state.x(), state.objective());
+    }
 }
 
 /// Given a QUBO and a vector of initial points, run gain searches on each initial point and return all of the solutions.
@@ -183,19 +208,39 @@ pub fn multi_simple_gain_criteria_search(
 /// let x_sol = local_search::simple_mixed_search(&p, &x_0, 1000);
 /// ```
 pub fn simple_mixed_search(qubo: &Qubo, x_0: &Array1<usize>, max_steps: usize) -> Array1<usize> {
-    let mut x = x_0.clone();
-    let mut x_1 = local_search_utils::get_gain_criteria(qubo, &x);
-    let mut steps = 0;
-    let vars = (0..qubo.num_x()).collect();
+    let evaluator = DeltaEvaluator::new(qubo);
+    let mut state = evaluator.state(x_0);
 
-    while x_1 != x && steps <= max_steps {
-        x = x_1.clone();
-        x_1 = local_search_utils::one_step_local_search_improved(qubo, &x, &vars);
-        x_1 = local_search_utils::get_gain_criteria(qubo, &x_1);
-        steps += 1;
-    }
+    // the step limit allows max_steps + 1 steps after the first move to the gain criteria
+    let budget = Budget::with_steps(max_steps.saturating_add(1));
+    let mut tracker = BudgetTracker::new(&budget, x_0, state.objective());
 
-    x_1
+    run_mixed_search(&mut state, &mut tracker);
+
+    state.into_x()
+}
+
+/// The mixed search loop, alternating local search and gain criteria steps until the point does not change or the
+/// budget is used up
+pub(crate) fn run_mixed_search(state: &mut FlipState, tracker: &mut BudgetTracker) {
+    let num_x = state.x().len();
+
+    let mut changed = local_search_utils::gain_criteria_flip(state);
+    tracker.add_evaluations(num_x);
+    tracker.observe(state.x(), state.objective());
+
+    while changed && !tracker.is_exhausted() {
+        let x = state.x().clone();
+        local_search_utils::one_step_flip(state);
+        local_search_utils::gain_criteria_flip(state);
+
+        // the gain criteria can undo the local search step, so compare the points rather than the flips
+        changed = state.x() != x;
+
+        tracker.add_step();
+        tracker.add_evaluations(2 * num_x);
+        tracker.observe(state.x(), state.objective());
+    }
 }
 
 /// Performs a particle swarm search on a QUBO.
@@ -226,36 +271,634 @@ pub fn particle_swarm_search<T: Algorithm>(
     let num_dim = qubo.num_x();
 
     // generate random starting points
+    let evaluator = DeltaEvaluator::new(qubo);
     let mut particles: Vec<_> = (0..num_particles)
-        .map(|_| generate_random_binary_point(num_dim, prng, 0.5))
+        .map(|_| evaluator.state(&generate_random_binary_point(num_dim, prng, 0.5)))
         .collect();
 
-    // select all variables
-    let selected_vars = (0..num_dim).collect();
+    let first = best_particle(&particles);
+    let mut tracker =
+        BudgetTracker::new(&Budget::with_steps(max_steps), first.x(), first.objective());
+
+    run_particle_swarm_search(&mut particles, &mut tracker);
+
+    // find the best particle
+    best_particle(&particles).x().clone()
+}
+
+/// The particle swarm loop, stepping each particle with local search and contracting them towards the best particle until
+/// the budget is used up
+pub(crate) fn run_particle_swarm_search(particles: &mut [FlipState], tracker: &mut BudgetTracker) {
+    let num_dim = particles[0].x().len();
 
     // say at each particular point that we will contract 10% of the variables
-    let num_contract = qubo.num_x() / 10 + 1;
+    let num_contract = num_dim / 10 + 1;
 
-    // loop over the number of iterations
-    for _ in 0..max_steps {
+    // loop until the budget is used up
+    while !tracker.is_exhausted() {
         // apply local search to each particle
-        particles = particles
-            .par_iter()
-            .map(|x| local_search_utils::one_step_local_search_improved(qubo, x, &selected_vars))
-            .collect();
+        particles.par_iter_mut().for_each(|particle| {
+            local_search_utils::one_step_flip(particle);
+        });
 
         // find the best particle
-        let best_particle = get_best_point(qubo, &particles);
+        let best = best_particle(particles);
+        let best_particle = best.x().clone();
+
+        tracker.add_step();
+        tracker.add_evaluations(particles.len() * num_dim);
+        tracker.observe(&best_particle, best.objective());
 
         // contract the particles towards the best particle
-        particles = particles
-            .iter()
-            .map(|x| local_search_utils::contract_point(&best_particle, x, num_contract))
-            .collect();
+        particles.par_iter_mut().for_each(|particle| {
+            local_search_utils::contract_state(&best_particle, particle, num_contract);
+        });
     }
+}
 
-    // find the best particle
-    get_best_point(qubo, &particles)
+/// Finds the particle with the lowest objective, the first one in the case of ties
+fn best_particle<'a, 'b>(particles: &'a [FlipState<'b>]) -> &'a FlipState<'b> {
+    particles.iter().fold(&particles[0], |best, particle| {
+        match particle.objective() < best.objective() {
+            true => particle,
+            false => best,
+        }
+    })
+}
+
+/// Given a QUBO and an integral initial point, run a tabu search and return the best point found.
+///
+/// At every step the best single bit flip that is not tabu is taken, even if it makes the objective worse, and the flipped
+/// variable is then tabu for the next `tenure` steps. A tabu flip is still taken if it leads to a point better than the
+/// best point found so far (the aspiration criterion). The search stops after `max_steps` steps, or after `max_time`
+/// seconds, whichever comes first.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use smolprng::{PRNG, JsfLarge};
+/// use hercules::initial_points;
+/// use hercules::local_search;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+///
+/// // generate a random point inside with x in {0, 1}^10
+/// let x_0 = initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+///
+/// // perform a tabu search starting at x_0, with a tenure of 3 steps, for 1000 steps or 1 second
+/// let x_sol = local_search::tabu_search(&p, &x_0, 3, 1000, 1.0);
+///
+/// assert!(p.eval_usize(&x_sol) <= p.eval_usize(&x_0));
+/// ```
+pub fn tabu_search(
+    qubo: &Qubo,
+    x_0: &Array1<usize>,
+    tenure: usize,
+    max_steps: usize,
+    max_time: f64,
+) -> Array1<usize> {
+    let evaluator = DeltaEvaluator::new(qubo);
+    run_tabu_search(&evaluator, x_0, tenure, max_steps, max_time)
+}
+
+/// Given a QUBO and a vector of initial points, run tabu searches on each initial point and return all of the solutions.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use smolprng::{PRNG, JsfLarge};
+/// use hercules::initial_points;
+/// use hercules::local_search;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+///
+/// // generate 4 random points inside with x in {0, 1}^10
+/// let xs = initial_points::generate_random_binary_points(p.num_x(), 4, &mut prng);
+///
+/// // perform a tabu search from each point, with a tenure of 3 steps, for 1000 steps or 1 second
+/// let x_sols = local_search::multi_tabu_search(&p, &xs, 3, 1000, 1.0);
+/// ```
+pub fn multi_tabu_search(
+    qubo: &Qubo,
+    xs: &Vec<Array1<usize>>,
+    tenure: usize,
+    max_steps: usize,
+    max_time: f64,
+) -> Vec<Array1<usize>> {
+    // the evaluator only depends on the problem, so it is shared by all of the searches
+    let evaluator = DeltaEvaluator::new(qubo);
+
+    xs.par_iter()
+        .map(|x| run_tabu_search(&evaluator, x, tenure, max_steps, max_time))
+        .collect()
+}
+
+/// A short tabu tenure for improving solutions inside of other heuristics, scaled with the problem size
+pub fn default_tenure(num_x: usize) -> usize {
+    (num_x / 100 + 10).min(num_x / 4 + 1)
+}
+
+/// Runs a tabu search for max_steps steps or max_time seconds, taking the evaluator so that it can be shared between
+/// searches on the same problem
+pub(crate) fn run_tabu_search(
+    evaluator: &DeltaEvaluator,
+    x_0: &Array1<usize>,
+    tenure: usize,
+    max_steps: usize,
+    max_time: f64,
+) -> Array1<usize> {
+    let mut state = evaluator.state(x_0);
+
+    let budget = Budget {
+        max_time,
+        max_steps,
+        ..Budget::unlimited()
+    };
+    let mut tracker = BudgetTracker::new(&budget, x_0, state.objective());
+
+    run_tabu_loop(&mut state, tenure, &mut tracker);
+
+    tracker.finish().x
+}
+
+/// The tabu search loop, where the best point is kept by the tracker, runs until every move is tabu or the budget is
+/// used up
+pub(crate) fn run_tabu_loop(state: &mut FlipState, tenure: usize, tracker: &mut BudgetTracker) {
+    let num_x = state.x().len();
+
+    // the step until which each variable is tabu
+    let mut tabu_until = vec![0usize; num_x];
+
+    while !tracker.is_exhausted() {
+        let step = tracker.steps();
+        let best_obj = tracker.best_objective();
+
+        // find the best move that is either not tabu, or is tabu but improves on the best solution
+        let candidates = (0..num_x)
+            .filter(|&i| tabu_until[i] <= step || state.objective() + state.delta(i) < best_obj);
+
+        // if every move is tabu, then there is nowhere to go
+        let Some((i, _)) = state.best_flip_of(candidates) else {
+            break;
+        };
+
+        state.flip(i);
+        tabu_until[i] = step + tenure + 1;
+
+        tracker.add_step();
+        tracker.add_evaluations(num_x);
+        tracker.observe(state.x(), state.objective());
+    }
+}
+
+/// The inverse temperature schedule of simulated annealing, giving beta = 1/T for each sweep
+pub enum AnnealingSchedule {
+    /// beta grows geometrically from beta_start on the first sweep to beta_end on the last sweep
+    Geometric { beta_start: f64, beta_end: f64 },
+    /// beta grows linearly from beta_start on the first sweep to beta_end on the last sweep
+    Linear { beta_start: f64, beta_end: f64 },
+    /// beta is given by a function of the sweep number
+    Custom(Box<dyn Fn(usize) -> f64 + Send + Sync>),
+}
+
+impl AnnealingSchedule {
+    /// Returns the inverse temperature of the sweep out of num_sweeps sweeps
+    pub fn beta(&self, sweep: usize, num_sweeps: usize) -> f64 {
+        // the fraction of the schedule that has been completed
+        let t = match num_sweeps > 1 {
+            true => sweep as f64 / (num_sweeps - 1) as f64,
+            false => 1.0,
+        };
+
+        match self {
+            Self::Geometric {
+                beta_start,
+                beta_end,
+            } => beta_start * (beta_end / beta_start).powf(t),
+            Self::Linear {
+                beta_start,
+                beta_end,
+            } => beta_start + (beta_end - beta_start) * t,
+            Self::Custom(schedule) => schedule(sweep),
+        }
+    }
+}
+
+/// The result of a simulated annealing run
+#[derive(Clone)]
+pub struct AnnealingResult {
+    /// The best point seen at the end of any sweep
+    pub x: Array1<usize>,
+    /// The objective of the best point
+    pub objective: f64,
+    /// The objective of the current point at the end of each sweep, over all of the restarts
+    pub energy_trace: Vec<f64>,
+}
+
+/// Given a QUBO and an integral initial point, run simulated annealing and return the best point seen along with the
+/// energy trace.
+///
+/// Each sweep proposes flipping every variable in order, a flip that changes the objective by delta is accepted with
+/// probability min(1, exp(-beta delta)), where beta is given by the schedule. After num_sweeps sweeps the search is
+/// restarted from a random point, num_restarts times, and the schedule starts over. The result is reproducible from the
+/// state of the prng.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use smolprng::{PRNG, JsfLarge};
+/// use hercules::initial_points;
+/// use hercules::local_search::{self, AnnealingSchedule};
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+///
+/// // generate a random point inside with x in {0, 1}^10
+/// let x_0 = initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+///
+/// // anneal from a hot start to a cold end over 100 sweeps, with 2 restarts
+/// let schedule = AnnealingSchedule::Geometric { beta_start: 0.1, beta_end: 10.0 };
+/// let result = local_search::simulated_annealing(&p, &x_0, &schedule, 100, 2, &mut prng);
+///
+/// assert_eq!(result.energy_trace.len(), 300);
+/// assert!(result.objective <= p.eval_usize(&x_0));
+/// ```
+pub fn simulated_annealing<T: Algorithm>(
+    qubo: &Qubo,
+    x_0: &Array1<usize>,
+    schedule: &AnnealingSchedule,
+    num_sweeps: usize,
+    num_restarts: usize,
+    prng: &mut PRNG<T>,
+) -> AnnealingResult {
+    let evaluator = DeltaEvaluator::new(qubo);
+    let mut tracker = BudgetTracker::new(&Budget::unlimited(), x_0, qubo.eval_usize(x_0));
+
+    let energy_trace = run_simulated_annealing(
+        &evaluator,
+        x_0,
+        schedule,
+        num_sweeps,
+        num_restarts,
+        &mut tracker,
+        prng,
+    );
+
+    AnnealingResult {
+        x: tracker.best_x().clone(),
+        objective: tracker.best_objective(),
+        energy_trace,
+    }
+}
+
+/// Runs independent simulated annealing chains in parallel, each starting from a random point, and returns the result of
+/// each chain.
+///
+/// Each chain gets its own prng seeded from the given prng, so the results are reproducible from the state of the prng
+/// regardless of the number of threads.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use smolprng::{PRNG, JsfLarge};
+/// use hercules::local_search::{self, AnnealingSchedule};
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+///
+/// // run 4 chains, with a linear schedule over 100 sweeps and no restarts
+/// let schedule = AnnealingSchedule::Linear { beta_start: 0.1, beta_end: 10.0 };
+/// let results = local_search::parallel_simulated_annealing(&p, 4, &schedule, 100, 0, &mut prng);
+///
+/// // find the best point over all of the chains
+/// let best = results.iter().min_by(|a, b| a.objective.total_cmp(&b.objective)).unwrap();
+/// ```
+pub fn parallel_simulated_annealing<T: Algorithm>(
+    qubo: &Qubo,
+    num_chains: usize,
+    schedule: &AnnealingSchedule,
+    num_sweeps: usize,
+    num_restarts: usize,
+    prng: &mut PRNG<T>,
+) -> Vec<AnnealingResult> {
+    let evaluator = DeltaEvaluator::new(qubo);
+
+    // draw the seeds up front, so the chains do not depend on the order they are run in
+    let seeds: Vec<_> = (0..num_chains).map(|_| prng.gen_u64()).collect();
+
+    seeds
+        .par_iter()
+        .map(|&seed| {
+            let mut chain_prng = PRNG {
+                generator: JsfLarge::from(seed),
+            };
+            let x_0 = generate_random_binary_point(qubo.num_x(), &mut chain_prng, 0.5);
+            let mut tracker = BudgetTracker::new(&Budget::unlimited(), &x_0, qubo.eval_usize(&x_0));
+
+            let energy_trace = run_simulated_annealing(
+                &evaluator,
+                &x_0,
+                schedule,
+                num_sweeps,
+                num_restarts,
+                &mut tracker,
+                &mut chain_prng,
+            );
+
+            AnnealingResult {
+                x: tracker.best_x().clone(),
+                objective: tracker.best_objective(),
+                energy_trace,
+            }
+        })
+        .collect()
+}
+
+/// The simulated annealing loop, taking the evaluator so that it can be shared between chains on the same problem. The
+/// best point is kept by the tracker, and the energy trace is returned.
+pub(crate) fn run_simulated_annealing<T: Algorithm>(
+    evaluator: &DeltaEvaluator,
+    x_0: &Array1<usize>,
+    schedule: &AnnealingSchedule,
+    num_sweeps: usize,
+    num_restarts: usize,
+    tracker: &mut BudgetTracker,
+    prng: &mut PRNG<T>,
+) -> Vec<f64> {
+    let mut state = evaluator.state(x_0);
+    let mut energy_trace = Vec::with_capacity(num_sweeps * (num_restarts + 1));
+
+    'restarts: for restart in 0..=num_restarts {
+        // every restart after the first starts from a new random point
+        if restart > 0 {
+            let x_restart = generate_random_binary_point(evaluator.num_x(), prng, 0.5);
+            state = evaluator.state(&x_restart);
+            tracker.add_evaluations(1);
+        }
+
+        for sweep in 0..num_sweeps {
+            if tracker.is_exhausted() {
+                break 'restarts;
+            }
+
+            let beta = schedule.beta(sweep, num_sweeps);
+
+            local_search_utils::metropolis_sweep(&mut state, beta, prng);
+
+            energy_trace.push(state.objective());
+
+            tracker.add_step();
+            tracker.add_evaluations(evaluator.num_x());
+            tracker.observe(state.x(), state.objective());
+        }
+    }
+
+    energy_trace
+}
+
+/// Given a QUBO and an integral initial point, run iterated local search and return the best solution found.
+///
+/// Each iteration perturbs the current local minimum by flipping `strength` random variables with
+/// `utils::mutate_solution`, and then descends with single flips to a new local minimum, which replaces the current one
+/// if it is no worse. The strength starts at 1, grows by 1 after every iteration that does not find a new best solution
+/// up to n/4, and goes back to 1 when a new best solution is found. The search stops after max_iterations iterations,
+/// after max_time seconds, or once the objective is at most target_objective if a target is given. The target is only
+/// checked after the first descent, so a starting point that already reaches it is still taken to a local minimum.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use smolprng::{PRNG, JsfLarge};
+/// use hercules::initial_points;
+/// use hercules::local_search;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+///
+/// // generate a random point inside with x in {0, 1}^10
+/// let x_0 = initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+///
+/// // perform iterated local search for 100 iterations or 1 second, with no target objective
+/// let x_sol = local_search::iterated_local_search(&p, &x_0, 100, 1.0, None, &mut prng);
+/// ```
+pub fn iterated_local_search<T: Algorithm>(
+    qubo: &Qubo,
+    x_0: &Array1<usize>,
+    max_iterations: usize,
+    max_time: f64,
+    target_objective: Option<f64>,
+    prng: &mut PRNG<T>,
+) -> Array1<usize> {
+    let evaluator = DeltaEvaluator::new(qubo);
+    let mut state = evaluator.state(x_0);
+
+    let budget = Budget {
+        max_time,
+        max_steps: max_iterations,
+        target_objective,
+        ..Budget::unlimited()
+    };
+    let mut tracker = BudgetTracker::new(&budget, x_0, state.objective());
+
+    run_iterated_local_search(&mut state, &mut tracker, prng);
+
+    tracker.finish().x
+}
+
+/// The iterated local search loop, where each iteration is a step and the best point is kept by the tracker
+pub(crate) fn run_iterated_local_search<T: Algorithm>(
+    state: &mut FlipState,
+    tracker: &mut BudgetTracker,
+    prng: &mut PRNG<T>,
+) {
+    // there is nothing to perturb without any variables
+    if state.x().is_empty() {
+        return;
+    }
+
+    let max_strength = (state.x().len() / 4).max(1);
+
+    first_descent(state, tracker);
+
+    let mut current_x = state.x().clone();
+    let mut current_obj = state.objective();
+    let mut strength = 1;
+
+    while !tracker.is_exhausted() {
+        let best_obj = tracker.best_objective();
+
+        // kick the current local minimum, and descend to a new one
+        state.move_to(&mutate_solution(&current_x, strength, prng));
+        descend(state, tracker);
+        tracker.add_step();
+
+        strength = next_strength(strength, state.objective() < best_obj, max_strength);
+
+        // accept the new local minimum if it is no worse, and otherwise go back to the current one
+        if state.objective() <= current_obj {
+            current_obj = state.objective();
+            current_x.clone_from(state.x());
+        } else {
+            state.move_to(&current_x);
+        }
+    }
+}
+
+/// Given a QUBO and an integral initial point, run variable neighborhood search and return the best solution found.
+///
+/// Each iteration shakes the best solution by flipping k distinct random variables, and then descends with single
+/// flips. If this finds a better solution the search moves to it and k goes back to 1, and otherwise k is increased,
+/// wrapping back around to 1 after k_max. The search stops after max_iterations iterations, after max_time seconds, or
+/// once the objective is at most target_objective if a target is given, which is only checked after the first descent.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use smolprng::{PRNG, JsfLarge};
+/// use hercules::initial_points;
+/// use hercules::local_search;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+///
+/// // generate a random point inside with x in {0, 1}^10
+/// let x_0 = initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+///
+/// // perform variable neighborhood search with shakes of up to 5 flips, for 100 iterations or 1 second
+/// let x_sol = local_search::variable_neighborhood_search(&p, &x_0, 5, 100, 1.0, None, &mut prng);
+/// ```
+pub fn variable_neighborhood_search<T: Algorithm>(
+    qubo: &Qubo,
+    x_0: &Array1<usize>,
+    k_max: usize,
+    max_iterations: usize,
+    max_time: f64,
+    target_objective: Option<f64>,
+    prng: &mut PRNG<T>,
+) -> Array1<usize> {
+    let evaluator = DeltaEvaluator::new(qubo);
+    let mut state = evaluator.state(x_0);
+
+    let budget = Budget {
+        max_time,
+        max_steps: max_iterations,
+        target_objective,
+        ..Budget::unlimited()
+    };
+    let mut tracker = BudgetTracker::new(&budget, x_0, state.objective());
+
+    run_variable_neighborhood_search(&mut state, k_max, &mut tracker, prng);
+
+    tracker.finish().x
+}
+
+/// The variable neighborhood search loop, where each shake is a step and the best point is kept by the tracker
+pub(crate) fn run_variable_neighborhood_search<T: Algorithm>(
+    state: &mut FlipState,
+    k_max: usize,
+    tracker: &mut BudgetTracker,
+    prng: &mut PRNG<T>,
+) {
+    let k_max = k_max.clamp(1, state.x().len().max(1));
+
+    // the search always shakes the best point, which is the first local minimum to start with
+    first_descent(state, tracker);
+    let mut k = 1;
+
+    while !tracker.is_exhausted() {
+        let best_obj = tracker.best_objective();
+
+        // shake in the k-th neighborhood of the best solution, and descend to a local minimum
+        shake(state, k, prng);
+        descend(state, tracker);
+        tracker.add_step();
+
+        let improved = state.objective() < best_obj;
+        if !improved {
+            state.move_to(tracker.best_x());
+        }
+        k = next_neighborhood(k, improved, k_max);
+    }
+}
+
+/// The perturbation strength of iterated local search after an iteration, which goes back to 1 after an improvement and
+/// otherwise grows by 1 up to max_strength
+const fn next_strength(strength: usize, improved: bool, max_strength: usize) -> usize {
+    if improved {
+        1
+    } else if strength < max_strength {
+        strength + 1
+    } else {
+        max_strength
+    }
+}
+
+/// The neighborhood of variable neighborhood search after an iteration, which goes back to 1 after an improvement and
+/// otherwise moves on to the next neighborhood, wrapping around to 1 after k_max
+const fn next_neighborhood(k: usize, improved: bool, k_max: usize) -> usize {
+    if improved {
+        1
+    } else {
+        k % k_max + 1
+    }
+}
+
+/// Flips the best variable until there are no improving flips or the budget is used up, this is the descent of
+/// `simple_local_search`
+fn descend(state: &mut FlipState, tracker: &mut BudgetTracker) {
+    descend_until(state, tracker, BudgetTracker::is_exhausted);
+}
+
+/// The descent from the starting point, which ignores the target objective, so that a starting point that already
+/// reaches the target is still taken to a local minimum
+fn first_descent(state: &mut FlipState, tracker: &mut BudgetTracker) {
+    descend_until(state, tracker, BudgetTracker::is_out_of_resources);
+}
+
+/// Flips the best variable until there are no improving flips or the stopping rule holds
+fn descend_until(
+    state: &mut FlipState,
+    tracker: &mut BudgetTracker,
+    stop: fn(&BudgetTracker) -> bool,
+) {
+    while !stop(tracker) && local_search_utils::one_step_flip(state) {
+        tracker.add_evaluations(state.x().len());
+    }
+
+    tracker.observe(state.x(), state.objective());
+}
+
+/// Flips k distinct variables picked at random
+fn shake<T: Algorithm>(state: &mut FlipState, k: usize, prng: &mut PRNG<T>) {
+    let mut indices: Vec<_> = (0..state.x().len()).collect();
+
+    // a partial Fisher-Yates shuffle, so that the first k indices are a uniform random subset
+    for j in 0..k.min(indices.len()) {
+        #[allow(clippy::cast_possible_truncation)]
+        let r = j + prng.gen_u64() as usize % (indices.len() - j);
+        indices.swap(j, r);
+        state.flip(indices[j]);
+    }
 }
 
 /// Performs a random search on a QUBO, where points are randomly generated and the best point is returned. This to
@@ -283,30 +926,39 @@ pub fn random_search<T: Algorithm>(
     prng: &mut PRNG<T>,
 ) -> Array1<usize> {
     // set up an initial best point and objective
-    let mut best_point = generate_random_binary_point(qubo.num_x(), prng, 0.5);
-    let mut best_objective = qubo.eval_usize(&best_point);
+    let x_0 = generate_random_binary_point(qubo.num_x(), prng, 0.5);
+    let mut tracker =
+        BudgetTracker::new(&Budget::with_steps(num_points), &x_0, qubo.eval_usize(&x_0));
 
-    // loop over the number of points
-    for _ in 0..num_points {
+    run_random_search(qubo, &mut tracker, prng);
+
+    tracker.finish().x
+}
+
+/// The random search loop, where each random point is a step and the best point is kept by the tracker
+pub(crate) fn run_random_search<T: Algorithm>(
+    qubo: &Qubo,
+    tracker: &mut BudgetTracker,
+    prng: &mut PRNG<T>,
+) {
+    while !tracker.is_exhausted() {
         // generate a new point and evaluate it
         let new_point = generate_random_binary_point(qubo.num_x(), prng, 0.5);
         let new_obj = qubo.eval_usize(&new_point);
 
-        // if the new point is better, update the best point
-        if new_obj <= best_objective {
-            best_point = new_point;
-            best_objective = new_obj;
-        }
-    }
+        tracker.add_step();
+        tracker.add_evaluations(1);
 
-    best_point
+        // the last of the best points is kept, as ties replace the best point
+        tracker.observe_no_worse(&new_point, new_obj);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::local_search::*;
     use crate::qubo::Qubo;
-    use crate::tests::{make_solver_qubo, make_test_prng};
+    use crate::tests::{assert_finds_optimum, make_solver_qubo, make_test_prng};
     use crate::{initial_points, local_search_utils};
     use ndarray::Array1;
     use sprs::CsMat;
@@ -384,6 +1036,208 @@ mod tests {
         );
     }
 
+    #[test]
+    fn local_search_is_one_flip_optimal() {
+        let p = make_solver_qubo();
+        let mut prng = make_test_prng();
+
+        let x_0 = initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+        let x_1 = simple_local_search(&p, &x_0, usize::MAX);
+        let obj_1 = p.eval_usize(&x_1);
+
+        assert!(obj_1 <= p.eval_usize(&x_0));
+
+        // no single bit flip can improve the solution
+        for i in 0..p.num_x() {
+            let mut x_2 = x_1.clone();
+            x_2[i] = 1 - x_2[i];
+            assert!(p.eval_usize(&x_2) >= obj_1 - 1E-10);
+        }
+
+        // a single step from the local minimum stays at the local minimum
+        let vars: Vec<_> = (0..p.num_x()).collect();
+        assert_eq!(
+            local_search_utils::one_step_local_search(&p, &x_1, &vars),
+            x_1
+        );
+    }
+
+    #[test]
+    fn tabu_search_finds_optimum() {
+        assert_finds_optimum(12, |p, prng| {
+            let x_0 = initial_points::generate_random_binary_point(p.num_x(), prng, 0.5);
+            tabu_search(p, &x_0, 3, 1000, 10.0)
+        });
+    }
+
+    #[test]
+    fn multi_tabu_search_improves() {
+        let p = make_solver_qubo();
+        let mut prng = make_test_prng();
+
+        let xs = initial_points::generate_random_binary_points(p.num_x(), 4, &mut prng);
+        let x_sols = multi_tabu_search(&p, &xs, 5, 500, 10.0);
+
+        assert_eq!(x_sols.len(), xs.len());
+
+        // tabu search keeps the best point, so it can never be worse than its starting point
+        for (x, x_sol) in xs.iter().zip(x_sols.iter()) {
+            assert!(p.eval_usize(x_sol) < p.eval_usize(x));
+        }
+    }
+
+    #[test]
+    fn tabu_search_time_limit() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+
+        // without a step limit, the search has to stop on the time limit
+        let x_sol = tabu_search(&p, &x_0, 5, usize::MAX, 0.05);
+
+        assert!(p.eval_usize(&x_sol) <= p.eval_usize(&x_0));
+    }
+
+    #[test]
+    fn iterated_local_search_finds_optimum() {
+        assert_finds_optimum(14, |p, prng| {
+            iterated_local_search(p, &Array1::zeros(p.num_x()), 500, f64::INFINITY, None, prng)
+        });
+    }
+
+    #[test]
+    fn variable_neighborhood_search_finds_optimum() {
+        assert_finds_optimum(14, |p, prng| {
+            let x_0 = Array1::zeros(p.num_x());
+            variable_neighborhood_search(p, &x_0, 5, 500, f64::INFINITY, None, prng)
+        });
+    }
+
+    #[test]
+    fn ils_strength_schedule() {
+        // the strength grows without improvements up to the maximum, and is reset by an improvement
+        assert_eq!(next_strength(1, false, 3), 2);
+        assert_eq!(next_strength(3, false, 3), 3);
+        assert_eq!(next_strength(3, true, 3), 1);
+    }
+
+    #[test]
+    fn vns_neighborhood_schedule() {
+        // the neighborhood moves on without improvements, wrapping around after k_max, and is reset by an improvement
+        assert_eq!(next_neighborhood(1, false, 3), 2);
+        assert_eq!(next_neighborhood(3, false, 3), 1);
+        assert_eq!(next_neighborhood(2, true, 3), 1);
+        assert_eq!(next_neighborhood(1, false, 1), 1);
+    }
+
+    #[test]
+    fn ils_vns_stopping_rules() {
+        let p = make_solver_qubo();
+        let mut prng = make_test_prng();
+        let x_0 = Array1::zeros(p.num_x());
+
+        // a target that any local minimum reaches stops the search right after the first descent
+        let x_ils = iterated_local_search(
+            &p,
+            &x_0,
+            usize::MAX,
+            f64::INFINITY,
+            Some(f64::INFINITY),
+            &mut prng,
+        );
+        assert_eq!(x_ils, simple_local_search(&p, &x_0, usize::MAX));
+
+        // without an iteration limit, the searches have to stop on the time limit
+        let x_ils = iterated_local_search(&p, &x_0, usize::MAX, 0.05, None, &mut prng);
+        let x_vns = variable_neighborhood_search(&p, &x_0, 10, usize::MAX, 0.05, None, &mut prng);
+
+        assert!(p.eval_usize(&x_ils) <= p.eval_usize(&x_0));
+        assert!(p.eval_usize(&x_vns) <= p.eval_usize(&x_0));
+    }
+
+    #[test]
+    fn ils_vns_without_variables() {
+        let p = Qubo::new(CsMat::zero((0, 0)));
+        let mut prng = make_test_prng();
+        let x_0 = Array1::zeros(0);
+
+        let x_ils = iterated_local_search(&p, &x_0, 10, f64::INFINITY, None, &mut prng);
+        let x_vns = variable_neighborhood_search(&p, &x_0, 5, 10, f64::INFINITY, None, &mut prng);
+
+        assert!(x_ils.is_empty());
+        assert!(x_vns.is_empty());
+    }
+
+    #[test]
+    fn simulated_annealing_finds_optimum() {
+        assert_finds_optimum(12, |p, prng| {
+            let schedule = AnnealingSchedule::Geometric {
+                beta_start: 0.1,
+                beta_end: 20.0,
+            };
+            let result = simulated_annealing(p, &Array1::zeros(p.num_x()), &schedule, 200, 3, prng);
+
+            assert_eq!(result.energy_trace.len(), 800);
+            assert!((result.objective - p.eval_usize(&result.x)).abs() < 1E-10);
+            result.x
+        });
+    }
+
+    #[test]
+    fn annealing_schedules() {
+        let geometric = AnnealingSchedule::Geometric {
+            beta_start: 0.1,
+            beta_end: 10.0,
+        };
+        let linear = AnnealingSchedule::Linear {
+            beta_start: 0.0,
+            beta_end: 2.0,
+        };
+        let custom = AnnealingSchedule::Custom(Box::new(|sweep| sweep as f64));
+
+        assert!((geometric.beta(0, 3) - 0.1).abs() < 1E-12);
+        assert!((geometric.beta(1, 3) - 1.0).abs() < 1E-12);
+        assert!((geometric.beta(2, 3) - 10.0).abs() < 1E-12);
+        assert!((linear.beta(1, 3) - 1.0).abs() < 1E-12);
+        assert!((custom.beta(7, 3) - 7.0).abs() < 1E-12);
+    }
+
+    #[test]
+    fn parallel_simulated_annealing_is_reproducible() {
+        let p = make_solver_qubo();
+        let schedule = AnnealingSchedule::Linear {
+            beta_start: 0.5,
+            beta_end: 10.0,
+        };
+
+        let results_1 =
+            parallel_simulated_annealing(&p, 4, &schedule, 50, 1, &mut make_test_prng());
+        let results_2 =
+            parallel_simulated_annealing(&p, 4, &schedule, 50, 1, &mut make_test_prng());
+
+        assert_eq!(results_1.len(), 4);
+        for (a, b) in results_1.iter().zip(results_2.iter()) {
+            assert_eq!(a.x, b.x);
+            assert_eq!(a.energy_trace, b.energy_trace);
+            assert!(a.energy_trace.iter().all(|&obj| obj >= a.objective));
+        }
+    }
+
+    #[test]
+    fn random_search_keeps_ties() {
+        // every point has the same objective, so the last random point is the best point
+        let p = Qubo::new(CsMat::zero((5, 5)));
+
+        let mut prng = make_test_prng();
+        let x_rand = random_search(&p, 3, &mut prng);
+
+        let mut prng = make_test_prng();
+        let xs: Vec<_> = (0..4)
+            .map(|_| initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5))
+            .collect();
+
+        assert_eq!(x_rand, xs[3]);
+    }
+
     #[test]
     fn qubo_heuristics() {
         let eye = CsMat::eye(3);
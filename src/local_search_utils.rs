@@ -5,13 +5,18 @@
 //! These include:
 //! - 1-opt local search
 //! - 1-step gain criteria local search
+//! - Metropolis sweeps
+//!
+//! The functions that take a `FlipState` update the point in place, in O(degree) time per flipped variable.
 
+use crate::flip_state::FlipState;
 use crate::qubo::Qubo;
 use ndarray::Array1;
+use smolprng::{Algorithm, PRNG};
 
 /// Performs a single step of local search, which is to say that it will flip a single bit and return the best solution out of all
 /// of the possible bit flips.
-/// This takes O(n|Q|) + O(n) time, where |Q| is the number of non-zero elements in the QUBO matrix.
+/// This takes O(|Q|) + O(n) time, where |Q| is the number of non-zero elements in the QUBO matrix.
 ///
 /// # Panics
 ///
@@ -39,20 +44,17 @@ use ndarray::Array1;
 pub fn one_step_local_search_improved(
     qubo: &Qubo,
     x_0: &Array1<usize>,
-    selected_vars: &Vec<usize>,
+    selected_vars: &[usize],
 ) -> Array1<usize> {
     // Do a neighborhood search of up to one bit flip and returns the best solution
     // found, this can include the original solution, out of the selected variables.
 
     let (_, objs) = one_flip_objective(qubo, x_0);
 
-    let best_neighbor = objs
+    let best_neighbor = *selected_vars
         .iter()
-        .enumerate()
-        .filter(|(i, _)| selected_vars.contains(i))
-        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .0;
+        .min_by(|&&a, &&b| objs[a].partial_cmp(&objs[b]).unwrap())
+        .unwrap();
 
     let best_obj = objs[best_neighbor];
 
@@ -88,6 +90,62 @@ pub fn get_gain_criteria(qubo: &Qubo, x: &Array1<usize>) -> Array1<usize> {
     fixed
 }
 
+/// Flips the variable that decreases the objective the most, if there is one. Returns true if a variable was flipped.
+///
+/// This is the in place version of `one_step_local_search_improved` on all the variables, and takes O(n) + O(degree) time.
+pub fn one_step_flip(state: &mut FlipState) -> bool {
+    match state.best_flip() {
+        Some((i, delta)) if delta < 0.0 => {
+            state.flip(i);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Moves every variable to the value given by the gain criteria at the current point. Returns true if any variable
+/// was flipped.
+///
+/// This is the in place version of `get_gain_criteria`, the criteria is evaluated at the point before any of the flips.
+pub fn gain_criteria_flip(state: &mut FlipState) -> bool {
+    let to_flip: Vec<_> = state
+        .gradient()
+        .iter()
+        .zip(state.x().iter())
+        .enumerate()
+        .filter(|(_, (&grad, &x_i))| usize::from(grad <= 0.0) != x_i)
+        .map(|(i, _)| i)
+        .collect();
+
+    for &i in &to_flip {
+        state.flip(i);
+    }
+
+    !to_flip.is_empty()
+}
+
+/// Proposes flipping every variable in order, accepting a flip that changes the objective by delta with probability
+/// min(1, exp(-beta delta)). Returns the number of accepted flips.
+///
+/// This is a single sweep of the Metropolis algorithm at inverse temperature beta, and takes O(n + |Q|) time.
+pub fn metropolis_sweep<T: Algorithm>(
+    state: &mut FlipState,
+    beta: f64,
+    prng: &mut PRNG<T>,
+) -> usize {
+    let mut accepted = 0;
+
+    for i in 0..state.x().len() {
+        let delta = state.delta(i);
+        if delta <= 0.0 || prng.gen_f64() < (-beta * delta).exp() {
+            state.flip(i);
+            accepted += 1;
+        }
+    }
+
+    accepted
+}
+
 /// Auxiliary function to calculate Delta, as defined in Boros2007
 pub fn compute_d(x_0: &Array1<f64>, grad: &Array1<f64>) -> Array1<f64> {
     // compute the variable importance function
@@ -131,53 +189,35 @@ pub fn one_flip_objective(qubo: &Qubo, x_0: &Array1<usize>) -> (f64, Array1<f64>
     }
 
     // calculate the objective function for the original solution
-    let obj_0 = x_0f.dot(&x_q) + qubo.c.dot(&x_0f);
+    let obj_0 = x_0f.dot(&x_q) + qubo.c.dot(&x_0f) + qubo.offset;
 
     (obj_0, objs)
 }
 
 /// Performs a single gain local search, which is to say that it will flip a single bit and return the best solution out of all
-/// of the possible bit flips.
-/// This takes O(n|Q|) + O(n) time, where |Q| is the number of non-zero elements in the QUBO matrix.
+/// of the possible bit flips in the subset.
+/// This takes O(|Q|) + O(n) time, where |Q| is the number of non-zero elements in the QUBO matrix.
 ///
 /// # Panics
 ///
 /// Will panic is the subset of variables is zero.
-pub fn one_step_local_search(
-    qubo: &Qubo,
-    x_0: &Array1<usize>,
-    subset: &Vec<usize>,
-) -> Array1<usize> {
-    let current_obj = qubo.eval_usize(x_0);
-
-    let y = 1 - x_0;
-    let mut objs = Array1::<f64>::zeros(qubo.num_x());
-
-    // calculate the objective function for each variable in our selected subset and each term in the delta formula
-    for i in subset {
-        let mut x = x_0.clone();
-        x[*i] = y[*i];
-        objs[*i] = qubo.eval_usize(&x);
-    }
+pub fn one_step_local_search(qubo: &Qubo, x_0: &Array1<usize>, subset: &[usize]) -> Array1<usize> {
+    // calculate the change in the objective function for flipping each variable
+    let (_, objs) = one_flip_objective(qubo, x_0);
 
-    // find the index of the best neighbor
-    let best_neighbor = objs
+    // find the index of the best neighbor in our selected subset
+    let best_neighbor = *subset
         .iter()
-        .enumerate()
-        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .0;
-
-    // get the objective of this best neighbor
-    let best_obj = objs[best_neighbor];
-
-    // generate the vector relating to this best neighbor
-    let mut x_1 = x_0.clone();
-    x_1[best_neighbor] = 1 - x_1[best_neighbor];
+        .min_by(|&&a, &&b| objs[a].partial_cmp(&objs[b]).unwrap())
+        .unwrap();
 
     // return the best neighbor if it is better than the current solution
-    match best_obj < current_obj {
-        true => x_1,
+    match objs[best_neighbor] < 0.0 {
+        true => {
+            let mut x_1 = x_0.clone();
+            x_1[best_neighbor] = 1 - x_1[best_neighbor];
+            x_1
+        }
         false => x_0.clone(),
     }
 }
@@ -240,3 +280,19 @@ pub fn contract_point(
 
     x_1
 }
+
+/// The in place version of `contract_point`, which flips the variables of the state that differ from x_0 in the same way
+pub fn contract_state(x_0: &Array1<usize>, state: &mut FlipState, num_contract: usize) {
+    let mut flipped = 0;
+
+    for i in 0..x_0.len() {
+        if x_0[i] != state.x()[i] {
+            flipped += 1;
+            state.flip(i);
+
+            if flipped > num_contract {
+                break;
+            }
+        }
+    }
+}
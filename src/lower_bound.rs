@@ -48,7 +48,8 @@ pub fn pardalos_rodgers_lower_bound(qubo: &Qubo, fixed_variables: &HashMap<usize
         }
     }
 
-    lower_bound
+    // the constant term shifts the bound directly
+    lower_bound + qubo.offset
 }
 
 /// Calculates an initial lower bound for a qubo, based on equation 15 of li2012
@@ -103,7 +104,8 @@ pub fn li_lower_bound(qubo: &Qubo, fixed_variables: &HashMap<usize, usize>) -> f
         }
     }
 
-    lower_bound
+    // the constant term shifts the bound directly
+    lower_bound + qubo.offset
 }
 
 #[cfg(test)]
@@ -0,0 +1,306 @@
+//! This module contains the weighted Max-Cut front end, and the reader for graphs in the Gset edge list format
+//!
+//! Max-Cut is a maximization problem, while every solver in hercules minimizes, so the graph is mapped to the QUBO whose
+//! objective is the negative of the cut weight. A solution of the QUBO is a partition of the nodes, x_i = 1 if node i is
+//! on the one side of the cut and x_i = 0 if it is on the zero side.
+
+use crate::io_error::{io_error, parse_coefficient, parse_one_based_index, QuboIOError};
+use crate::qubo::Qubo;
+use ndarray::Array1;
+use std::io::BufRead;
+
+/// A weighted undirected graph, stored as an edge list with 0-indexed nodes
+#[derive(Clone)]
+pub struct MaxCutGraph {
+    /// The number of nodes in the graph
+    pub num_nodes: usize,
+    /// The edges of the graph, as (u, v, w_uv)
+    pub edges: Vec<(usize, usize, f64)>,
+}
+
+impl MaxCutGraph {
+    /// Generate a new graph from the number of nodes and a list of 0-indexed weighted edges
+    ///
+    /// Example to create a weighted triangle:
+    /// ```rust
+    /// use hercules::maxcut::MaxCutGraph;
+    ///
+    /// let g = MaxCutGraph::new(3, vec![(0, 1, 1.0), (1, 2, 2.0), (0, 2, 3.0)]);
+    /// ```
+    pub const fn new(num_nodes: usize, edges: Vec<(usize, usize, f64)>) -> Self {
+        Self { num_nodes, edges }
+    }
+
+    /// Converts the graph into the QUBO that minimizes the negative cut weight
+    ///
+    /// The cut weight of an edge is w_uv (x_u + x_v - 2 x_u x_v), so the QUBO has q_uv = q_vu = 2 w_uv and
+    /// c_u = -sum_v w_uv. Self loops can never be cut and are skipped.
+    ///
+    /// Example of building the QUBO of a graph:
+    /// ```rust
+    /// use hercules::maxcut::MaxCutGraph;
+    /// use ndarray::Array1;
+    ///
+    /// let g = MaxCutGraph::new(3, vec![(0, 1, 1.0), (1, 2, 2.0), (0, 2, 3.0)]);
+    /// let p = g.to_qubo();
+    ///
+    /// let x = Array1::from_vec(vec![1, 0, 0]);
+    /// assert_eq!(p.eval_usize(&x), -g.cut_weight(&x));
+    /// ```
+    pub fn to_qubo(&self) -> Qubo {
+        let mut i = Vec::new();
+        let mut j = Vec::new();
+        let mut q = Vec::new();
+        let mut c = vec![0.0; self.num_nodes];
+
+        for &(u, v, w) in &self.edges {
+            if u == v {
+                continue;
+            }
+
+            i.push(u);
+            j.push(v);
+            q.push(2.0 * w);
+
+            i.push(v);
+            j.push(u);
+            q.push(2.0 * w);
+
+            c[u] -= w;
+            c[v] -= w;
+        }
+
+        Qubo::from_vec(i, j, q, c, self.num_nodes)
+    }
+
+    /// Computes the weight of the cut given by the partition x
+    pub fn cut_weight(&self, x: &Array1<usize>) -> f64 {
+        self.edges
+            .iter()
+            .filter(|&&(u, v, _)| x[u] != x[v])
+            .map(|&(_, _, w)| w)
+            .sum()
+    }
+
+    /// Reads a graph from a file in the Gset edge list format
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is not a file at the given filename in the Gset format.
+    #[must_use]
+    pub fn read_gset(filename: &str) -> Self {
+        Self::try_read_gset(filename).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Reads a graph from a file in the Gset edge list format, returning an error instead of panicking
+    ///
+    /// The first line is `n m`, the number of nodes and edges, every following line is an edge `u v w` with 1-indexed
+    /// nodes. The weight is optional and is taken as one if missing. Blank lines are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be read, if any line is malformed, or if the number of edges does not
+    /// match the header.
+    pub fn try_read_gset(filename: &str) -> Result<Self, QuboIOError> {
+        // open the file
+        let file = std::fs::File::open(filename).map_err(|err| io_error(filename, err))?;
+        let reader = std::io::BufReader::new(file);
+        let mut lines = reader.lines();
+
+        // get the number of nodes and edges
+        let header = match lines.next() {
+            Some(line) => line.map_err(|err| io_error(filename, err))?,
+            None => String::new(),
+        };
+        let header_data: Vec<_> = header.split_whitespace().map(str::parse::<usize>).collect();
+
+        let [Ok(num_nodes), Ok(num_edges)] = header_data[..] else {
+            return Err(QuboIOError::BadHeader {
+                path: filename.to_string(),
+                line: 1,
+                token: header.trim().to_string(),
+                expected: "the number of nodes and the number of edges",
+            });
+        };
+
+        let mut edges = Vec::with_capacity(num_edges);
+        let mut last_line = 1;
+
+        // read the file, the header is line 1
+        for (line_index, line) in lines.enumerate() {
+            let line = line.map_err(|err| io_error(filename, err))?;
+            let line_number = line_index + 2;
+            let row_data: Vec<_> = line.split_whitespace().collect();
+
+            if !row_data.is_empty() {
+                last_line = line_number;
+            }
+
+            match row_data.len() {
+                0 => {}
+                2 | 3 => {
+                    let u = parse_one_based_index(filename, line_number, row_data[0], num_nodes)?;
+                    let v = parse_one_based_index(filename, line_number, row_data[1], num_nodes)?;
+                    let w = match row_data.get(2) {
+                        Some(token) => parse_coefficient(filename, line_number, token)?,
+                        None => 1.0,
+                    };
+                    edges.push((u, v, w));
+                }
+                found => {
+                    return Err(QuboIOError::WrongColumnCount {
+                        path: filename.to_string(),
+                        line: line_number,
+                        found,
+                        expected: "2 (u v) or 3 (u v w_uv)",
+                    });
+                }
+            }
+        }
+
+        // the number of edges has to match the header, so that a truncated file is not read as a smaller graph
+        if edges.len() < num_edges {
+            return Err(QuboIOError::UnexpectedEndOfFile {
+                path: filename.to_string(),
+                line: last_line + 1,
+                expected: "the number of edges given in the header",
+            });
+        }
+        if edges.len() > num_edges {
+            return Err(QuboIOError::BadHeader {
+                path: filename.to_string(),
+                line: 1,
+                token: header.trim().to_string(),
+                expected: "the number of nodes and the number of edges in the file",
+            });
+        }
+
+        Ok(Self::new(num_nodes, edges))
+    }
+}
+
+/// Solves the Max-Cut problem on the graph with any QUBO solver, and returns the partition and the cut weight
+///
+/// The solver is given the QUBO of the graph, and should return the best binary solution it finds.
+///
+/// Example of solving a Max-Cut problem with the particle swarm heuristic:
+/// ```rust
+/// use hercules::maxcut::{solve_maxcut, MaxCutGraph};
+/// use hercules::local_search::particle_swarm_search;
+/// use smolprng::{JsfLarge, PRNG};
+///
+/// let g = MaxCutGraph::new(4, vec![(0, 1, 1.0), (1, 2, 1.0), (2, 3, 1.0), (3, 0, 1.0)]);
+/// let mut prng = PRNG {
+///     generator: JsfLarge::default(),
+/// };
+///
+/// let (x, cut) = solve_maxcut(&g, |p| particle_swarm_search(p, 10, 100, &mut prng));
+/// assert_eq!(cut, g.cut_weight(&x));
+/// ```
+pub fn solve_maxcut<F>(graph: &MaxCutGraph, solver: F) -> (Array1<usize>, f64)
+where
+    F: FnOnce(&Qubo) -> Array1<usize>,
+{
+    let p = graph.to_qubo();
+    let x = solver(&p);
+    let cut = graph.cut_weight(&x);
+
+    (x, cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io_error::QuboIOError;
+    use crate::local_search::simple_mixed_search;
+    use crate::maxcut::{solve_maxcut, MaxCutGraph};
+    use crate::qubo::Qubo;
+    use crate::tests::write_temp_file;
+    use ndarray::Array1;
+
+    fn make_test_graph() -> MaxCutGraph {
+        MaxCutGraph::new(
+            4,
+            vec![
+                (0, 1, 1.0),
+                (1, 2, 2.0),
+                (2, 3, -1.0),
+                (0, 3, 3.0),
+                (0, 2, 0.5),
+            ],
+        )
+    }
+
+    #[test]
+    fn maxcut_qubo_is_negative_cut() {
+        let g = make_test_graph();
+        let p = g.to_qubo();
+
+        // check every partition, as there are only 16 of them
+        for k in 0..16usize {
+            let x = Array1::from_shape_fn(4, |i| (k >> i) & 1);
+            assert!((p.eval_usize(&x) + g.cut_weight(&x)).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn read_gset_valid() {
+        let path = write_temp_file("hercules_valid.gset", "3 3\n1 2 1\n2 3 -1\n\n1 3\n");
+        let g = MaxCutGraph::try_read_gset(&path).unwrap();
+
+        assert_eq!(g.num_nodes, 3);
+        assert_eq!(g.edges, vec![(0, 1, 1.0), (1, 2, -1.0), (0, 2, 1.0)]);
+
+        let p = Qubo::read_gset(&path);
+        assert_eq!(p.num_x(), 3);
+    }
+
+    #[test]
+    fn read_gset_zero_index() {
+        let path = write_temp_file("hercules_zero_index.gset", "3 1\n0 2 1\n");
+        let err = MaxCutGraph::try_read_gset(&path).map(|_| ()).unwrap_err();
+
+        assert_eq!(err.line(), Some(2));
+    }
+
+    #[test]
+    fn read_gset_bad_header() {
+        let path = write_temp_file("hercules_bad_header.gset", "3\n1 2 1\n");
+        let err = MaxCutGraph::try_read_gset(&path).map(|_| ()).unwrap_err();
+
+        assert_eq!(err.line(), Some(1));
+    }
+
+    #[test]
+    fn solve_maxcut_local_search() {
+        let g = make_test_graph();
+
+        let (x, cut) = solve_maxcut(&g, |p| {
+            let x_0 = Array1::zeros(p.num_x());
+            simple_mixed_search(p, &x_0, 100)
+        });
+
+        // the best cut found by enumerating every partition
+        let best_cut = (0..16usize)
+            .map(|k| g.cut_weight(&Array1::from_shape_fn(4, |i| (k >> i) & 1)))
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        assert!((cut - g.cut_weight(&x)).abs() < 1E-10);
+        assert!((cut - best_cut).abs() < 1E-10);
+    }
+
+    #[test]
+    fn read_gset_edge_count() {
+        // the header declares 3 edges, but the file only has 2
+        let path = write_temp_file("hercules_truncated.gset", "3 3\n1 2 1\n2 3 -1\n");
+        let err = MaxCutGraph::try_read_gset(&path).map(|_| ()).unwrap_err();
+
+        assert!(matches!(err, QuboIOError::UnexpectedEndOfFile { .. }));
+        assert_eq!(err.line(), Some(4));
+
+        // the header declares 1 edge, but the file has 2
+        let path = write_temp_file("hercules_extra_edge.gset", "3 1\n1 2 1\n2 3 -1\n");
+        let err = MaxCutGraph::try_read_gset(&path).map(|_| ()).unwrap_err();
+
+        assert_eq!(err.line(), Some(1));
+    }
+}
@@ -0,0 +1,257 @@
+//! This module contains the memetic algorithm, a genetic algorithm where every offspring is improved by local search
+//!
+//! Each generation, pairs of parents are picked from the population by binary tournament, crossed over, mutated with
+//! `utils::mutate_solution` and improved with tabu search. The offspring of a generation are made in parallel, each with
+//! its own generator seeded from the main one, so that a run is reproducible from the seed. The population is an
+//! [`ElitePool`], so an offspring only replaces a member of the population if it adds quality or diversity.
+
+use crate::branchbound_utils::get_current_time;
+use crate::elite_pool::ElitePool;
+use crate::flip_state::DeltaEvaluator;
+use crate::local_search::{default_tenure, run_tabu_search};
+use crate::path_relinking::fill_pool;
+use crate::qubo::Qubo;
+use crate::utils::mutate_solution;
+use ndarray::Array1;
+use rayon::prelude::*;
+use smolprng::{Algorithm, JsfLarge, PRNG};
+
+/// The crossover operator used to make the offspring
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Crossover {
+    /// Each variable where the parents differ is taken from one of the parents at random
+    Uniform,
+    /// The variables where the parents differ are set by a one flip descent over only those variables, starting from the
+    /// first parent, so the offspring keeps what the parents agree on and is locally optimal on the rest
+    Greedy,
+}
+
+/// Options for the memetic algorithm
+pub struct MemeticOptions {
+    pub population_size: usize,
+    pub max_generations: usize,
+    pub max_time: f64,
+    pub crossover: Crossover,
+    /// The number of random bit flips applied to each offspring
+    pub mutation_sites: usize,
+    /// The number of tabu search steps used to improve each offspring
+    pub improvement_steps: usize,
+}
+
+impl MemeticOptions {
+    pub const fn new() -> Self {
+        Self {
+            population_size: 10,
+            max_generations: 100,
+            max_time: 100.0,
+            crossover: Crossover::Greedy,
+            mutation_sites: 2,
+            improvement_steps: 1000,
+        }
+    }
+}
+
+impl Default for MemeticOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs the memetic algorithm until either the generation limit or the time limit is hit, and returns the best solution
+/// found.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use hercules::memetic::{memetic_search, Crossover, MemeticOptions};
+/// use smolprng::{PRNG, JsfLarge};
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(20, &mut prng, 0.5);
+///
+/// // evolve a population of 8 solutions for 10 generations or 1 second, with uniform crossover
+/// let mut options = MemeticOptions::new();
+/// options.population_size = 8;
+/// options.max_generations = 10;
+/// options.max_time = 1.0;
+/// options.crossover = Crossover::Uniform;
+///
+/// let x_sol = memetic_search(&p, &options, &mut prng);
+/// ```
+pub fn memetic_search<T: Algorithm>(
+    qubo: &Qubo,
+    options: &MemeticOptions,
+    prng: &mut PRNG<T>,
+) -> Array1<usize> {
+    let start_time = get_current_time();
+    let evaluator = DeltaEvaluator::new(qubo);
+    let tenure = default_tenure(qubo.num_x());
+
+    let improve = |x: &Array1<usize>| {
+        let x_improved = run_tabu_search(
+            &evaluator,
+            x,
+            tenure,
+            options.improvement_steps,
+            f64::INFINITY,
+        );
+        let objective = qubo.eval_usize(&x_improved);
+        (x_improved, objective)
+    };
+
+    // the initial population is improved random points
+    let mut population = ElitePool::new(options.population_size);
+    fill_pool(&mut population, qubo.num_x(), prng, &improve);
+
+    for _ in 0..options.max_generations {
+        if get_current_time() - start_time > options.max_time || population.len() < 2 {
+            break;
+        }
+
+        let seeds: Vec<_> = (0..options.population_size)
+            .map(|_| prng.gen_u64())
+            .collect();
+
+        let offspring: Vec<_> = seeds
+            .par_iter()
+            .map(|&seed| {
+                let mut child_prng = PRNG {
+                    generator: JsfLarge::from(seed),
+                };
+
+                let parent_a = tournament(&population, &mut child_prng);
+                let parent_b = tournament(&population, &mut child_prng);
+
+                let child = match options.crossover {
+                    Crossover::Uniform => uniform_crossover(parent_a, parent_b, &mut child_prng),
+                    Crossover::Greedy => greedy_crossover(&evaluator, parent_a, parent_b),
+                };
+
+                improve(&mutate_solution(
+                    &child,
+                    options.mutation_sites,
+                    &mut child_prng,
+                ))
+            })
+            .collect();
+
+        for (x, objective) in offspring {
+            population.insert(x, objective);
+        }
+    }
+
+    population
+        .best()
+        .map_or_else(|| Array1::zeros(qubo.num_x()), |(x, _)| x.clone())
+}
+
+/// Picks two members of the population at random, and returns the one with the lower objective
+fn tournament<'a, T: Algorithm>(
+    population: &'a ElitePool,
+    prng: &mut PRNG<T>,
+) -> &'a Array1<usize> {
+    #[allow(clippy::cast_possible_truncation)]
+    let mut pick = || prng.gen_u64() as usize % population.len();
+    let (a, b) = (pick(), pick());
+
+    if population.objectives()[a] <= population.objectives()[b] {
+        &population.solutions()[a]
+    } else {
+        &population.solutions()[b]
+    }
+}
+
+/// Takes each variable from one of the two parents at random
+fn uniform_crossover<T: Algorithm>(
+    parent_a: &Array1<usize>,
+    parent_b: &Array1<usize>,
+    prng: &mut PRNG<T>,
+) -> Array1<usize> {
+    Array1::from_shape_fn(parent_a.len(), |i| {
+        if prng.gen_u64() & 1 == 0 {
+            parent_a[i]
+        } else {
+            parent_b[i]
+        }
+    })
+}
+
+/// Keeps the variables the parents agree on, and sets the rest with a one flip descent over only those variables
+fn greedy_crossover(
+    evaluator: &DeltaEvaluator,
+    parent_a: &Array1<usize>,
+    parent_b: &Array1<usize>,
+) -> Array1<usize> {
+    let differing: Vec<_> = (0..parent_a.len())
+        .filter(|&i| parent_a[i] != parent_b[i])
+        .collect();
+
+    let mut state = evaluator.state(parent_a);
+
+    while let Some((i, delta)) = state.best_flip_of(differing.iter().copied()) {
+        if delta >= 0.0 {
+            break;
+        }
+        state.flip(i);
+    }
+
+    state.into_x()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flip_state::DeltaEvaluator;
+    use crate::memetic::{greedy_crossover, memetic_search, Crossover, MemeticOptions};
+    use crate::tests::{assert_finds_optimum, make_solver_qubo};
+    use ndarray::Array1;
+    use smolprng::{JsfLarge, PRNG};
+
+    #[test]
+    fn memetic_search_finds_optimum() {
+        for crossover in [Crossover::Uniform, Crossover::Greedy] {
+            let mut options = MemeticOptions::new();
+            options.population_size = 6;
+            options.max_generations = 10;
+            options.improvement_steps = 50;
+            options.crossover = crossover;
+
+            assert_finds_optimum(14, |p, prng| memetic_search(p, &options, prng));
+        }
+    }
+
+    #[test]
+    fn memetic_search_is_reproducible() {
+        let p = make_solver_qubo();
+        let mut options = MemeticOptions::new();
+        options.max_generations = 5;
+        options.improvement_steps = 20;
+
+        let run = |seed: u64| {
+            let mut prng = PRNG {
+                generator: JsfLarge::from(seed),
+            };
+            memetic_search(&p, &options, &mut prng)
+        };
+
+        assert_eq!(run(7), run(7));
+    }
+
+    #[test]
+    fn greedy_crossover_keeps_common_variables() {
+        let p = make_solver_qubo();
+        let evaluator = DeltaEvaluator::new(&p);
+
+        let parent_a = Array1::from_shape_fn(p.num_x(), |i| usize::from(i < 30));
+        let parent_b = Array1::from_shape_fn(p.num_x(), |i| usize::from(i >= 20));
+
+        let child = greedy_crossover(&evaluator, &parent_a, &parent_b);
+        let state = evaluator.state(&child);
+
+        // the parents agree on 20..30, and the child is one flip optimal on the rest
+        assert!((20..30).all(|i| child[i] == 1));
+        assert!((0..20).chain(30..p.num_x()).all(|i| state.delta(i) >= 0.0));
+    }
+}
@@ -0,0 +1,372 @@
+//! This module contains the parallel tempering (replica exchange Monte Carlo) solver
+//!
+//! A set of replicas of the problem are simulated at a ladder of inverse temperatures beta_0 < beta_1 < ... < beta_k.
+//! Every round each replica does a Metropolis sweep at its own temperature, in parallel, and then replicas at neighboring
+//! temperatures attempt to swap their states. Swapping lets a state that is stuck in a local minimum at a cold temperature
+//! move up the ladder, where it can escape, and come back down to be refined.
+//!
+//! The ladder can be adapted during the run, so that the swap acceptance rate is roughly the same between every pair of
+//! neighboring temperatures, which keeps states moving along the whole ladder.
+
+use crate::flip_state::{DeltaEvaluator, FlipState};
+use crate::initial_points::generate_random_binary_point;
+use crate::local_search_utils::metropolis_sweep;
+use crate::qubo::Qubo;
+use ndarray::Array1;
+use rayon::prelude::*;
+use smolprng::{Algorithm, JsfLarge, PRNG};
+
+/// The inverse temperatures of the replicas, sorted from the hottest (smallest beta) to the coldest (largest beta)
+#[derive(Clone)]
+pub struct TemperatureLadder {
+    pub betas: Vec<f64>,
+}
+
+/// The statistics of a single temperature of the ladder over the whole run
+#[derive(Clone)]
+pub struct ReplicaStatistics {
+    /// The inverse temperature at the end of the run
+    pub beta: f64,
+    /// The fraction of the proposed flips that were accepted at this temperature
+    pub move_acceptance_rate: f64,
+    /// The fraction of the attempted swaps with the next colder temperature that were accepted, zero for the coldest
+    pub swap_acceptance_rate: f64,
+    /// The best objective seen at this temperature
+    pub best_objective: f64,
+    /// The objective of the state at this temperature at the end of the run
+    pub final_objective: f64,
+}
+
+/// The result of a parallel tempering run
+#[derive(Clone)]
+pub struct TemperingResult {
+    /// The best point seen by any of the replicas
+    pub x: Array1<usize>,
+    /// The objective of the best point
+    pub objective: f64,
+    /// The statistics of each temperature, in the order of the ladder
+    pub replicas: Vec<ReplicaStatistics>,
+}
+
+impl TemperatureLadder {
+    /// Creates a ladder from the given inverse temperatures, which are sorted from the hottest to the coldest
+    ///
+    /// # Panics
+    ///
+    /// Will panic if any of the inverse temperatures is not positive.
+    pub fn new(mut betas: Vec<f64>) -> Self {
+        assert!(
+            betas.iter().all(|&beta| beta > 0.0),
+            "the inverse temperatures of a ladder must be positive"
+        );
+        betas.sort_by(f64::total_cmp);
+        Self { betas }
+    }
+
+    /// Creates a ladder of num_replicas inverse temperatures, spaced geometrically from beta_min to beta_max
+    ///
+    /// Example:
+    /// ```rust
+    /// use hercules::parallel_tempering::TemperatureLadder;
+    ///
+    /// let ladder = TemperatureLadder::geometric(0.1, 10.0, 3);
+    ///
+    /// assert!((ladder.betas[1] - 1.0).abs() < 1E-12);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if beta_min or beta_max is not positive.
+    pub fn geometric(beta_min: f64, beta_max: f64, num_replicas: usize) -> Self {
+        assert!(
+            beta_min > 0.0 && beta_max > 0.0,
+            "the inverse temperatures of a ladder must be positive"
+        );
+
+        let betas = match num_replicas {
+            0 => Vec::new(),
+            1 => vec![beta_max],
+            _ => (0..num_replicas)
+                .map(|k| {
+                    let t = k as f64 / (num_replicas - 1) as f64;
+                    beta_min * (beta_max / beta_min).powf(t)
+                })
+                .collect(),
+        };
+
+        Self { betas }
+    }
+
+    /// Returns the number of replicas on the ladder
+    pub const fn len(&self) -> usize {
+        self.betas.len()
+    }
+
+    /// Returns true if the ladder has no replicas
+    pub const fn is_empty(&self) -> bool {
+        self.betas.is_empty()
+    }
+
+    /// Respaces the interior of the ladder given the swap acceptance rate of each pair of neighboring temperatures,
+    /// the hottest and coldest temperatures are kept fixed.
+    ///
+    /// The gap in log(beta) of each pair is scaled by its acceptance rate relative to the mean, so pairs that rarely swap
+    /// are moved closer together and pairs that nearly always swap are moved apart. A ladder with a temperature that is
+    /// not positive, which can only be set directly, is left as is, as it has no log(beta).
+    pub fn adapt(&mut self, swap_acceptance_rates: &[f64]) {
+        if self.len() < 3 || swap_acceptance_rates.len() + 1 != self.len() {
+            return;
+        }
+
+        if !self.betas.iter().all(|&beta| beta > 0.0) {
+            return;
+        }
+
+        // keeps a pair with no accepted swaps from collapsing onto a single temperature
+        let floor = 0.05;
+        let mean_rate =
+            swap_acceptance_rates.iter().sum::<f64>() / swap_acceptance_rates.len() as f64;
+
+        let log_betas: Vec<_> = self.betas.iter().map(|beta| beta.ln()).collect();
+        let gaps: Vec<_> = log_betas
+            .windows(2)
+            .zip(swap_acceptance_rates)
+            .map(|(pair, rate)| (pair[1] - pair[0]) * (rate + floor) / (mean_rate + floor))
+            .collect();
+
+        // rescale the gaps so that the end points do not move
+        let total_gap = log_betas[log_betas.len() - 1] - log_betas[0];
+        let scale = total_gap / gaps.iter().sum::<f64>();
+
+        let mut log_beta = log_betas[0];
+        for (k, gap) in gaps.iter().enumerate().take(gaps.len() - 1) {
+            log_beta += gap * scale;
+            self.betas[k + 1] = log_beta.exp();
+        }
+    }
+}
+
+/// Runs parallel tempering on a QUBO with a replica at each temperature of the ladder, and returns the best point seen
+/// along with the statistics of each temperature.
+///
+/// Each of the num_sweeps rounds runs one Metropolis sweep per replica in parallel, and then attempts to swap the states of
+/// neighboring temperatures i and i + 1 with probability min(1, exp((beta_i - beta_{i+1}) (E_i - E_{i+1}))), alternating
+/// between the even and the odd pairs. If adapt_interval is nonzero, the ladder is adapted every adapt_interval rounds
+/// with the swap acceptance rates since the last adaptation. All of the replicas have their own prng seeded from the
+/// given prng, so the result is reproducible from the state of the prng regardless of the number of threads.
+///
+/// Example:
+/// ```rust
+/// use hercules::qubo::Qubo;
+/// use hercules::parallel_tempering::{parallel_tempering, TemperatureLadder};
+/// use smolprng::{PRNG, JsfLarge};
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(10, &mut prng, 0.5);
+///
+/// // 8 replicas from beta = 0.1 to beta = 10, for 200 rounds, adapting the ladder every 50 rounds
+/// let ladder = TemperatureLadder::geometric(0.1, 10.0, 8);
+/// let result = parallel_tempering(&p, &ladder, 200, 50, &mut prng);
+///
+/// assert_eq!(result.replicas.len(), 8);
+/// assert!((p.eval_usize(&result.x) - result.objective).abs() < 1E-10);
+/// ```
+pub fn parallel_tempering<T: Algorithm>(
+    qubo: &Qubo,
+    ladder: &TemperatureLadder,
+    num_sweeps: usize,
+    adapt_interval: usize,
+    prng: &mut PRNG<T>,
+) -> TemperingResult {
+    let evaluator = DeltaEvaluator::new(qubo);
+    let mut ladder = ladder.clone();
+    let num_replicas = ladder.len();
+
+    // every temperature has its own prng and starts from its own random point
+    let mut prngs: Vec<_> = (0..num_replicas)
+        .map(|_| PRNG {
+            generator: JsfLarge::from(prng.gen_u64()),
+        })
+        .collect();
+    let mut states: Vec<FlipState> = prngs
+        .iter_mut()
+        .map(|replica_prng| {
+            evaluator.state(&generate_random_binary_point(
+                qubo.num_x(),
+                replica_prng,
+                0.5,
+            ))
+        })
+        .collect();
+
+    let mut best_x = Array1::zeros(qubo.num_x());
+    let mut best_obj = f64::INFINITY;
+
+    for state in &states {
+        if state.objective() < best_obj {
+            best_obj = state.objective();
+            best_x.clone_from(state.x());
+        }
+    }
+
+    // the counters of each temperature, over the whole run and since the last adaptation
+    let mut accepted_moves = vec![0usize; num_replicas];
+    let mut best_objectives: Vec<_> = states.iter().map(FlipState::objective).collect();
+    let mut swap_attempts = vec![0usize; num_replicas];
+    let mut swap_accepts = vec![0usize; num_replicas];
+    let mut window_attempts = vec![0usize; num_replicas];
+    let mut window_accepts = vec![0usize; num_replicas];
+
+    for sweep in 0..num_sweeps {
+        // a metropolis sweep of every replica at its own temperature
+        let accepted: Vec<_> = states
+            .par_iter_mut()
+            .zip(prngs.par_iter_mut())
+            .zip(ladder.betas.par_iter())
+            .map(|((state, replica_prng), &beta)| metropolis_sweep(state, beta, replica_prng))
+            .collect();
+
+        for (k, state) in states.iter().enumerate() {
+            accepted_moves[k] += accepted[k];
+            best_objectives[k] = best_objectives[k].min(state.objective());
+
+            if state.objective() < best_obj {
+                best_obj = state.objective();
+                best_x.clone_from(state.x());
+            }
+        }
+
+        // attempt to swap the states of neighboring temperatures, alternating between the even and the odd pairs
+        for k in (sweep % 2..num_replicas.saturating_sub(1)).step_by(2) {
+            let exponent = (ladder.betas[k] - ladder.betas[k + 1])
+                * (states[k].objective() - states[k + 1].objective());
+
+            swap_attempts[k] += 1;
+            window_attempts[k] += 1;
+
+            if exponent >= 0.0 || prng.gen_f64() < exponent.exp() {
+                states.swap(k, k + 1);
+                swap_accepts[k] += 1;
+                window_accepts[k] += 1;
+            }
+        }
+
+        // respace the ladder with the swap acceptance rates since the last adaptation
+        if adapt_interval > 0 && (sweep + 1) % adapt_interval == 0 && sweep + 1 < num_sweeps {
+            let rates: Vec<_> = (0..num_replicas.saturating_sub(1))
+                .map(|k| rate(window_accepts[k], window_attempts[k]))
+                .collect();
+            ladder.adapt(&rates);
+
+            window_attempts.fill(0);
+            window_accepts.fill(0);
+        }
+    }
+
+    let replicas = (0..num_replicas)
+        .map(|k| ReplicaStatistics {
+            beta: ladder.betas[k],
+            move_acceptance_rate: rate(accepted_moves[k], num_sweeps * qubo.num_x()),
+            swap_acceptance_rate: rate(swap_accepts[k], swap_attempts[k]),
+            best_objective: best_objectives[k],
+            final_objective: states[k].objective(),
+        })
+        .collect();
+
+    // evaluate the best point from scratch, so the objective does not carry the rounding of the incremental updates
+    TemperingResult {
+        objective: qubo.eval_usize(&best_x),
+        x: best_x,
+        replicas,
+    }
+}
+
+/// The fraction of accepted out of attempted, or zero if nothing was attempted
+fn rate(accepted: usize, attempted: usize) -> f64 {
+    match attempted {
+        0 => 0.0,
+        _ => accepted as f64 / attempted as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel_tempering::{parallel_tempering, TemperatureLadder};
+    use crate::tests::{assert_finds_optimum, make_solver_qubo, make_test_prng};
+
+    #[test]
+    fn parallel_tempering_finds_optimum() {
+        assert_finds_optimum(12, |p, prng| {
+            let ladder = TemperatureLadder::geometric(0.1, 20.0, 6);
+            let result = parallel_tempering(p, &ladder, 300, 0, prng);
+
+            assert!((result.objective - p.eval_usize(&result.x)).abs() < 1E-10);
+            result.x
+        });
+    }
+
+    #[test]
+    fn parallel_tempering_statistics() {
+        let p = make_solver_qubo();
+
+        let ladder = TemperatureLadder::geometric(0.1, 10.0, 5);
+        let result_1 = parallel_tempering(&p, &ladder, 100, 20, &mut make_test_prng());
+        let result_2 = parallel_tempering(&p, &ladder, 100, 20, &mut make_test_prng());
+
+        // the run is reproducible from the seed
+        assert_eq!(result_1.x, result_2.x);
+
+        assert_eq!(result_1.replicas.len(), 5);
+        for replica in &result_1.replicas {
+            assert!((0.0..=1.0).contains(&replica.move_acceptance_rate));
+            assert!((0.0..=1.0).contains(&replica.swap_acceptance_rate));
+            assert!(replica.best_objective >= result_1.objective - 1E-10);
+            assert!(replica.final_objective >= replica.best_objective);
+        }
+
+        // the coldest temperature has no colder neighbor to swap with
+        assert_eq!(result_1.replicas[4].swap_acceptance_rate, 0.0);
+
+        // adaptation keeps the end points of the ladder, and keeps it sorted
+        assert!((result_1.replicas[0].beta - 0.1).abs() < 1E-12);
+        assert!((result_1.replicas[4].beta - 10.0).abs() < 1E-12);
+        assert!(result_1.replicas.windows(2).all(|w| w[0].beta < w[1].beta));
+    }
+
+    #[test]
+    fn ladder_adapt_moves_towards_slow_pairs() {
+        let mut ladder = TemperatureLadder::geometric(0.1, 10.0, 3);
+
+        // the hot pair swaps often, and the cold pair rarely, so the middle temperature moves towards the cold end
+        ladder.adapt(&[0.9, 0.1]);
+
+        assert!((ladder.betas[0] - 0.1).abs() < 1E-12);
+        assert!((ladder.betas[2] - 10.0).abs() < 1E-12);
+        assert!(ladder.betas[1] > 1.0 && ladder.betas[1] < 10.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be positive")]
+    fn ladder_rejects_zero_beta() {
+        TemperatureLadder::geometric(0.0, 10.0, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be positive")]
+    fn ladder_rejects_negative_beta() {
+        TemperatureLadder::new(vec![1.0, -1.0, 2.0]);
+    }
+
+    #[test]
+    fn ladder_adapt_skips_non_positive_beta() {
+        let mut ladder = TemperatureLadder {
+            betas: vec![0.0, 1.0, 10.0],
+        };
+        ladder.adapt(&[0.9, 0.1]);
+
+        assert_eq!(ladder.betas, vec![0.0, 1.0, 10.0]);
+    }
+}
@@ -0,0 +1,194 @@
+//! This module contains the path relinking heuristic over an elite pool of solutions
+//!
+//! This follows the path relinking of Wang, Lü, Glover and Hao (2012) for QUBO. A pool of good and diverse solutions is
+//! built with tabu search, and then for every ordered pair of solutions in the pool a greedy path is walked from the
+//! initiating solution to the guiding solution. Each step of the path flips the differing bit that gives the best
+//! objective, and the best point in the middle part of the path is improved with tabu search and offered to the pool.
+//! When a round over all of the pairs does not change the pool, the pool is rebuilt around its best solution.
+
+use crate::elite_pool::ElitePool;
+use crate::flip_state::DeltaEvaluator;
+use crate::initial_points::generate_random_binary_point;
+use crate::local_search::{default_tenure, run_tabu_search};
+use crate::qubo::Qubo;
+use ndarray::Array1;
+use rayon::prelude::*;
+use smolprng::{Algorithm, PRNG};
+
+/// Runs path relinking over an elite pool of pool_size solutions for max_rounds rounds, and returns the best solution
+/// found.
+///
+/// Every solution that enters the pool is first improved by a tabu search of improvement_steps steps.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use hercules::path_relinking::path_relinking;
+/// use smolprng::{PRNG, JsfLarge};
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(20, &mut prng, 0.5);
+///
+/// // path relinking with a pool of 5 solutions for 3 rounds, improving each new solution with 100 tabu steps
+/// let x_sol = path_relinking(&p, 5, 3, 100, &mut prng);
+/// ```
+pub fn path_relinking<T: Algorithm>(
+    qubo: &Qubo,
+    pool_size: usize,
+    max_rounds: usize,
+    improvement_steps: usize,
+    prng: &mut PRNG<T>,
+) -> Array1<usize> {
+    let evaluator = DeltaEvaluator::new(qubo);
+
+    let tenure = default_tenure(qubo.num_x());
+    let improve = |x: &Array1<usize>| {
+        let x_improved = run_tabu_search(&evaluator, x, tenure, improvement_steps, f64::INFINITY);
+        let objective = qubo.eval_usize(&x_improved);
+        (x_improved, objective)
+    };
+
+    let mut pool = ElitePool::new(pool_size);
+    fill_pool(&mut pool, qubo.num_x(), prng, &improve);
+
+    for _ in 0..max_rounds {
+        let solutions = pool.solutions().to_vec();
+
+        // every ordered pair of distinct solutions, as the path from x_a to x_b is not the path from x_b to x_a
+        let pairs: Vec<_> = (0..solutions.len())
+            .flat_map(|a| (0..solutions.len()).map(move |b| (a, b)))
+            .filter(|(a, b)| a != b)
+            .collect();
+
+        let candidates: Vec<_> = pairs
+            .par_iter()
+            .filter_map(|&(a, b)| relink(&evaluator, &solutions[a], &solutions[b]))
+            .map(|x| improve(&x))
+            .collect();
+
+        let mut updated = false;
+        for (x, objective) in candidates {
+            updated |= pool.insert(x, objective);
+        }
+
+        // if relinking did not find anything new, then start over from the best solution and new random solutions
+        if !updated {
+            let (best_x, best_obj) = match pool.best() {
+                Some((x, objective)) => (x.clone(), objective),
+                None => break,
+            };
+
+            pool = ElitePool::new(pool_size);
+            pool.insert(best_x, best_obj);
+            fill_pool(&mut pool, qubo.num_x(), prng, &improve);
+        }
+    }
+
+    pool.best()
+        .map_or_else(|| Array1::zeros(qubo.num_x()), |(x, _)| x.clone())
+}
+
+/// Fills the pool with improved random solutions, giving up after a number of attempts if the problem does not have
+/// enough distinct local minima
+pub(crate) fn fill_pool<T: Algorithm, F: Fn(&Array1<usize>) -> (Array1<usize>, f64)>(
+    pool: &mut ElitePool,
+    num_x: usize,
+    prng: &mut PRNG<T>,
+    improve: &F,
+) {
+    let max_attempts = 10 * pool.capacity();
+    let mut attempts = 0;
+
+    while !pool.is_full() && attempts < max_attempts {
+        let (x, objective) = improve(&generate_random_binary_point(num_x, prng, 0.5));
+        pool.insert(x, objective);
+        attempts += 1;
+    }
+}
+
+/// Walks the greedy path from x_initial to x_guiding, and returns the best point on the middle third of the path, or None
+/// if the points are too close together to have a middle.
+///
+/// Each step flips the bit out of the bits that still differ from x_guiding that gives the lowest objective.
+fn relink(
+    evaluator: &DeltaEvaluator,
+    x_initial: &Array1<usize>,
+    x_guiding: &Array1<usize>,
+) -> Option<Array1<usize>> {
+    let mut differing: Vec<_> = (0..x_initial.len())
+        .filter(|&i| x_initial[i] != x_guiding[i])
+        .collect();
+
+    let distance = differing.len();
+    if distance < 3 {
+        return None;
+    }
+
+    // the points within a third of the distance to either end are too close to one of the two solutions
+    let margin = distance.div_ceil(3);
+
+    let mut state = evaluator.state(x_initial);
+    let mut best: Option<(Array1<usize>, f64)> = None;
+
+    for step in 1..=distance - margin {
+        // the position of the best flip out of the bits that still differ
+        let k = (0..differing.len())
+            .min_by(|&a, &b| {
+                state
+                    .delta(differing[a])
+                    .total_cmp(&state.delta(differing[b]))
+            })
+            .unwrap_or(0);
+
+        state.flip(differing.swap_remove(k));
+
+        let improved = match &best {
+            Some((_, objective)) => state.objective() < *objective,
+            None => true,
+        };
+
+        if step >= margin && improved {
+            best = Some((state.x().clone(), state.objective()));
+        }
+    }
+
+    best.map(|(x, _)| x)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flip_state::DeltaEvaluator;
+    use crate::path_relinking::{path_relinking, relink};
+    use crate::tests::{assert_finds_optimum, make_solver_qubo};
+    use crate::utils::calculate_hamming_distance;
+    use ndarray::Array1;
+
+    #[test]
+    fn path_relinking_finds_optimum() {
+        assert_finds_optimum(14, |p, prng| path_relinking(p, 4, 3, 50, prng));
+    }
+
+    #[test]
+    fn relink_stays_in_the_middle_of_the_path() {
+        let p = make_solver_qubo();
+        let evaluator = DeltaEvaluator::new(&p);
+
+        let x_initial = Array1::zeros(p.num_x());
+        let x_guiding = Array1::ones(p.num_x());
+
+        let x = relink(&evaluator, &x_initial, &x_guiding).unwrap();
+
+        // 50 bits differ, so the point is at least 17 flips from either end
+        assert!(calculate_hamming_distance(&x, &x_initial) >= 17);
+        assert!(calculate_hamming_distance(&x, &x_guiding) >= 17);
+
+        // points that are too close do not have a middle
+        let mut x_close = x_initial.clone();
+        x_close[0] = 1;
+        x_close[1] = 1;
+        assert!(relink(&evaluator, &x_initial, &x_close).is_none());
+    }
+}
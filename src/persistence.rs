@@ -1,6 +1,7 @@
+use crate::constraint_reduction::strongly_connected_components;
 use crate::qubo::Qubo;
 use std::cmp::min;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 /// This function takes a QUBO and a set of persistent variables and returns a new set of persistent variables by repeatedly re
 /// computing the persistent variables until.
@@ -118,11 +119,297 @@ pub fn grad_bounds(
     (lower, upper)
 }
 
+/// The result of roof duality, the lower bound and the persistent variables of the roof dual
+#[derive(Clone, Debug, Default)]
+pub struct RoofDuality {
+    /// The roof dual, a lower bound on the objective of every solution with the given fixed variables
+    pub lower_bound: f64,
+    /// The variables that take these values in every optimal solution, along with the given fixed variables
+    pub strong: HashMap<usize, usize>,
+    /// The variables that take these values in at least one optimal solution, along with the given fixed variables, this
+    /// includes the strong persistencies
+    pub weak: HashMap<usize, usize>,
+}
+
+/// Computes the roof dual of the QUBO with a single max-flow, as in the QPBO method of Hammer, Boros and Hansen, and
+/// the strong and weak persistencies that come with it.
+///
+/// The QUBO is first written as a posiform, a constant plus a sum of products of literals (x_i or 1 - x_i) with positive
+/// coefficients. Each literal is a node of the implication network, along with x_0 = 1 and its complement, and each term
+/// a u v is the two arcs u -> !v and v -> !u with capacity a/2, where a linear term a u is a u x_0. The cut between the
+/// true and false literals of any solution is its objective minus the constant, so the max-flow from x_0 to !x_0 plus
+/// the constant is a lower bound, the roof dual.
+///
+/// The literals that can be reached from x_0 in the residual network are true in every min cut, and are the strong
+/// persistencies. Every min cut corresponds to an optimal solution of the roof dual, and the variables that are integral
+/// in any of these are weak persistencies. The min cut with the most integral variables is found as in 2-SAT, from the
+/// strongly connected components of the residual network.
+///
+/// Example:
+/// ```rust
+/// use hercules::qubo::Qubo;
+/// use hercules::persistence::compute_roof_duality;
+/// use smolprng::{PRNG, JsfLarge};
+/// use std::collections::HashMap;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(50, &mut prng, 0.1);
+///
+/// let roof = compute_roof_duality(&p, &HashMap::new());
+///
+/// // the strong persistencies are also weak persistencies
+/// assert!(roof.strong.iter().all(|(i, value)| roof.weak[i] == *value));
+/// ```
+pub fn compute_roof_duality(qubo: &Qubo, fixed_variables: &HashMap<usize, usize>) -> RoofDuality {
+    // only the free variables are in the network
+    let (reduced_qubo, _, index_map) = qubo.fix_variables(fixed_variables);
+    let num_x = reduced_qubo.num_x();
+
+    // the literal x_i is node 2i and !x_i is node 2i + 1, so that the complement of a node is node ^ 1, and x_0 and !x_0
+    // are the last two nodes
+    let source = 2 * num_x;
+    let sink = source + 1;
+
+    // write the QUBO as the constant plus u^T x plus the sum of w_ij x_i x_j over i < j
+    let mut constant = reduced_qubo.offset;
+    let mut linear = reduced_qubo.c.clone();
+    let mut quadratic = HashMap::new();
+    for (&value, (i, j)) in &reduced_qubo.q {
+        match i.cmp(&j) {
+            std::cmp::Ordering::Equal => linear[i] += 0.5 * value,
+            std::cmp::Ordering::Less => *quadratic.entry((i, j)).or_insert(0.0) += 0.5 * value,
+            std::cmp::Ordering::Greater => *quadratic.entry((j, i)).or_insert(0.0) += 0.5 * value,
+        }
+    }
+
+    // the terms of the posiform, as (coefficient, literal, literal), with negative quadratic terms rewritten as
+    // w x_i x_j = w x_i - w x_i !x_j
+    let mut terms = Vec::with_capacity(quadratic.len() + num_x);
+    for (&(i, j), &w) in &quadratic {
+        if w > 0.0 {
+            terms.push((w, 2 * i, 2 * j));
+        } else if w < 0.0 {
+            linear[i] += w;
+            terms.push((-w, 2 * i, 2 * j + 1));
+        }
+    }
+
+    // and negative linear terms rewritten as u x_i = u - u !x_i
+    for (i, &u) in linear.iter().enumerate() {
+        if u > 0.0 {
+            terms.push((u, 2 * i, source));
+        } else if u < 0.0 {
+            constant += u;
+            terms.push((-u, 2 * i + 1, source));
+        }
+    }
+
+    // each term a u v is the pair of mirrored arcs u -> !v and v -> !u
+    let mut network = FlowNetwork::new(2 * num_x + 2);
+    let mut mirrored_arcs = Vec::with_capacity(terms.len());
+    for &(a, u, v) in &terms {
+        let arc = network.add_arc(u, v ^ 1, 0.5 * a);
+        let mirror = network.add_arc(v, u ^ 1, 0.5 * a);
+        mirrored_arcs.push((arc, mirror));
+    }
+
+    let flow = network.max_flow(source, sink);
+    let tolerance = network.tolerance;
+
+    // average the flow with its mirror, which is also a max-flow, so that the residual network is symmetric under
+    // complementing the literals and reversing the arcs
+    let mut residual = vec![Vec::new(); 2 * num_x + 2];
+    for &(arc, mirror) in &mirrored_arcs {
+        let arc_flow = 0.5 * (network.flow(arc) + network.flow(mirror));
+        for e in [arc, mirror] {
+            let (from, to) = (network.to[e ^ 1], network.to[e]);
+            if network.original[e] - arc_flow > tolerance {
+                residual[from].push(to);
+            }
+            if arc_flow > tolerance {
+                residual[to].push(from);
+            }
+        }
+    }
+
+    // the strong persistencies are the literals that are reachable from x_0
+    let mut reachable = vec![false; 2 * num_x + 2];
+    let mut queue = VecDeque::from([source]);
+    reachable[source] = true;
+    while let Some(v) = queue.pop_front() {
+        for &w in &residual[v] {
+            if !reachable[w] {
+                reachable[w] = true;
+                queue.push_back(w);
+            }
+        }
+    }
+
+    // with this arc x_0 is always true in the assignment from the components, it never changes the closed sets as !x_0 is
+    // never in one
+    residual[sink].push(source);
+    let component = strongly_connected_components(&residual);
+
+    let mut roof = RoofDuality {
+        lower_bound: constant + flow,
+        strong: fixed_variables.clone(),
+        weak: fixed_variables.clone(),
+    };
+
+    for (k, &i) in index_map.iter().enumerate() {
+        if reachable[2 * k] {
+            roof.strong.insert(i, 1);
+        } else if reachable[2 * k + 1] {
+            roof.strong.insert(i, 0);
+        }
+
+        // a variable is integral in the min cut of the components, if its literals are in different components
+        if component[2 * k] != component[2 * k + 1] {
+            let value = usize::from(component[2 * k] < component[2 * k + 1]);
+            roof.weak.insert(i, value);
+        }
+    }
+
+    roof
+}
+
+/// A flow network, where each arc is stored next to its reverse arc, so that the reverse of arc e is e ^ 1
+struct FlowNetwork {
+    adjacency: Vec<Vec<usize>>,
+    to: Vec<usize>,
+    capacity: Vec<f64>,
+    original: Vec<f64>,
+    tolerance: f64,
+}
+
+impl FlowNetwork {
+    fn new(num_nodes: usize) -> Self {
+        Self {
+            adjacency: vec![Vec::new(); num_nodes],
+            to: Vec::new(),
+            capacity: Vec::new(),
+            original: Vec::new(),
+            tolerance: 0.0,
+        }
+    }
+
+    /// Adds an arc and its reverse arc with no capacity, and returns the index of the arc
+    fn add_arc(&mut self, from: usize, to: usize, capacity: f64) -> usize {
+        let arc = self.to.len();
+
+        self.adjacency[from].push(arc);
+        self.to.push(to);
+        self.capacity.push(capacity);
+        self.original.push(capacity);
+
+        self.adjacency[to].push(arc + 1);
+        self.to.push(from);
+        self.capacity.push(0.0);
+        self.original.push(0.0);
+
+        arc
+    }
+
+    /// The flow on an arc
+    fn flow(&self, arc: usize) -> f64 {
+        self.original[arc] - self.capacity[arc]
+    }
+
+    /// Computes the max-flow from the source to the sink with Dinic's algorithm, leaving the residual capacities in the
+    /// network
+    fn max_flow(&mut self, source: usize, sink: usize) -> f64 {
+        // residual capacities below the tolerance are rounding errors, and are treated as saturated
+        let max_capacity = self.original.iter().fold(1.0f64, |a, &b| a.max(b));
+        self.tolerance = 1E-12 * max_capacity;
+
+        let num_nodes = self.adjacency.len();
+        let mut total = 0.0;
+
+        loop {
+            // build the level graph with a BFS from the source
+            let mut level = vec![usize::MAX; num_nodes];
+            let mut queue = VecDeque::from([source]);
+            level[source] = 0;
+            while let Some(v) = queue.pop_front() {
+                for &e in &self.adjacency[v] {
+                    let w = self.to[e];
+                    if level[w] == usize::MAX && self.capacity[e] > self.tolerance {
+                        level[w] = level[v] + 1;
+                        queue.push_back(w);
+                    }
+                }
+            }
+
+            if level[sink] == usize::MAX {
+                return total;
+            }
+
+            total += self.blocking_flow(source, sink, &mut level);
+        }
+    }
+
+    /// Saturates every shortest path from the source to the sink in the level graph, following the arcs with a stack
+    /// instead of recursion so that long paths can not overflow the stack
+    fn blocking_flow(&mut self, source: usize, sink: usize, level: &mut [usize]) -> f64 {
+        let mut next_arc = vec![0; self.adjacency.len()];
+        let mut path: Vec<usize> = Vec::new();
+        let mut v = source;
+        let mut total = 0.0;
+
+        loop {
+            if v == sink {
+                // push the bottleneck capacity along the path, and start again from the source
+                let bottleneck = path
+                    .iter()
+                    .map(|&e| self.capacity[e])
+                    .fold(f64::INFINITY, f64::min);
+                for &e in &path {
+                    self.capacity[e] -= bottleneck;
+                    self.capacity[e ^ 1] += bottleneck;
+                }
+                total += bottleneck;
+                path.clear();
+                v = source;
+                continue;
+            }
+
+            // advance along the first arc into the next level that still has capacity
+            while next_arc[v] < self.adjacency[v].len() {
+                let e = self.adjacency[v][next_arc[v]];
+                if self.capacity[e] > self.tolerance && level[self.to[e]] == level[v] + 1 {
+                    break;
+                }
+                next_arc[v] += 1;
+            }
+
+            if next_arc[v] < self.adjacency[v].len() {
+                let e = self.adjacency[v][next_arc[v]];
+                path.push(e);
+                v = self.to[e];
+                continue;
+            }
+
+            // a dead end, so remove it from the level graph and retreat
+            if v == source {
+                return total;
+            }
+            level[v] = usize::MAX;
+            if let Some(e) = path.pop() {
+                v = self.to[e ^ 1];
+                next_arc[v] += 1;
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::qubo::Qubo;
-    use crate::tests::make_solver_qubo;
+    use crate::tests::{brute_force_min, make_solver_qubo, make_test_prng};
     use ndarray::Array1;
     use sprs::CsMat;
     use std::collections::HashMap;
@@ -187,4 +474,83 @@ mod tests {
 
         assert_eq!(persist.len(), 41);
     }
+
+    /// every solution of a small QUBO, with its objective
+    fn all_solutions(p: &Qubo) -> Vec<(Array1<usize>, f64)> {
+        (0..1usize << p.num_x())
+            .map(|mask| {
+                let x = Array1::from_shape_fn(p.num_x(), |i| (mask >> i) & 1);
+                let obj = p.eval_usize(&x);
+                (x, obj)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_roof_duality_persistencies() {
+        let mut prng = make_test_prng();
+
+        for _ in 0..50 {
+            let p = Qubo::make_random_qubo(10, &mut prng, 0.4);
+            let roof = compute_roof_duality(&p, &HashMap::new());
+
+            let solutions = all_solutions(&p);
+            let best = brute_force_min(&p);
+            let agrees = |x: &Array1<usize>, fixed: &HashMap<usize, usize>| {
+                fixed.iter().all(|(&i, &value)| x[i] == value)
+            };
+
+            assert!(roof.lower_bound <= best + 1E-10);
+
+            // every optimal solution agrees with the strong persistencies, and some optimal solution with the weak ones
+            for (x, obj) in &solutions {
+                if (obj - best).abs() < 1E-10 {
+                    assert!(agrees(x, &roof.strong));
+                }
+            }
+            assert!(solutions
+                .iter()
+                .any(|(x, obj)| (obj - best).abs() < 1E-10 && agrees(x, &roof.weak)));
+            assert!(roof.strong.iter().all(|(i, value)| roof.weak[i] == *value));
+        }
+    }
+
+    #[test]
+    fn test_roof_duality_submodular() {
+        let mut prng = make_test_prng();
+
+        // without positive couplings, the roof dual is tight and every variable is a weak persistency
+        for _ in 0..20 {
+            let p = Qubo::make_random_qubo(10, &mut prng, 0.4);
+            let q = p.q.map(|&value| -value.abs());
+            let p = Qubo::new_with_c(q, p.c);
+
+            let roof = compute_roof_duality(&p, &HashMap::new());
+            let x = Array1::from_shape_fn(p.num_x(), |i| roof.weak[&i]);
+
+            assert!((roof.lower_bound - brute_force_min(&p)).abs() < 1E-10);
+            assert!((p.eval_usize(&x) - roof.lower_bound).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn test_roof_duality_with_fixed_variables() {
+        let p = make_solver_qubo();
+        let fixed = HashMap::from([(0, 1), (3, 0), (10, 1)]);
+        let roof = compute_roof_duality(&p, &fixed);
+
+        assert!(fixed.iter().all(|(i, value)| roof.strong[i] == *value));
+        assert!(fixed.iter().all(|(i, value)| roof.weak[i] == *value));
+
+        // the bound holds for the solutions with the variables fixed
+        let mut prng = make_test_prng();
+        for _ in 0..100 {
+            let mut x =
+                crate::initial_points::generate_random_binary_point(p.num_x(), &mut prng, 0.5);
+            for (&i, &value) in &fixed {
+                x[i] = value;
+            }
+            assert!(roof.lower_bound <= p.eval_usize(&x) + 1E-10);
+        }
+    }
 }
@@ -0,0 +1,360 @@
+//! This module contains the parallel algorithm portfolio runner
+//!
+//! Different instances favour different heuristics, so the portfolio runs a set of heuristics at the same time on a rayon
+//! pool, under a shared wall clock budget, and returns the best point found by any of them. All of the heuristics offer
+//! their improvements to a [`SharedIncumbent`], so that the whole portfolio can be stopped as soon as one of them reaches
+//! a target objective.
+//!
+//! If the incumbent is shared, the time budget is split into rounds of sync_interval seconds, and every round restarts
+//! each heuristic from the best point found so far by any heuristic. A heuristic that stops on its own during a round
+//! without improving on its starting point has converged, and is not run again.
+
+use crate::branchbound_utils::get_current_time;
+use crate::heuristic::{Budget, Heuristic, SharedIncumbent};
+use crate::qubo::Qubo;
+use ndarray::Array1;
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// Options for the portfolio runner
+pub struct PortfolioOptions {
+    /// The wall clock time limit in seconds, shared by all of the heuristics
+    pub max_time: f64,
+    /// If given, every heuristic is stopped as soon as any of them finds a point with an objective at most this value
+    pub target_objective: Option<f64>,
+    /// If true, every round restarts each heuristic from the best point found so far
+    pub share_incumbent: bool,
+    /// The length of a round in seconds, when the incumbent is shared, a round that is not positive uses the rest of the
+    /// time limit
+    pub sync_interval: f64,
+    /// The number of threads of the rayon pool, or 0 for one thread per heuristic
+    pub threads: usize,
+}
+
+impl PortfolioOptions {
+    pub const fn new() -> Self {
+        Self {
+            max_time: 10.0,
+            target_objective: None,
+            share_incumbent: true,
+            sync_interval: 1.0,
+            threads: 0,
+        }
+    }
+}
+
+impl Default for PortfolioOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The statistics of a single heuristic over a portfolio run
+#[derive(Clone, Debug)]
+pub struct MethodStatistics {
+    /// The name of the heuristic
+    pub name: String,
+    /// The best objective found by the heuristic
+    pub objective: f64,
+    /// The number of evaluations over all of the rounds
+    pub evaluations: usize,
+    /// The number of steps over all of the rounds
+    pub steps: usize,
+    /// The time spent running the heuristic over all of the rounds
+    pub elapsed: f64,
+    /// The time from the start of the portfolio until the heuristic found its best point
+    pub time_to_best: f64,
+    /// The number of rounds the heuristic was run for
+    pub rounds: usize,
+}
+
+/// The result of a portfolio run
+#[derive(Clone, Debug)]
+pub struct PortfolioResult {
+    /// The best point found by any of the heuristics
+    pub x: Array1<usize>,
+    /// The objective of the best point
+    pub objective: f64,
+    /// The index of the heuristic that found the best point, or None if no heuristic improved on the starting point
+    pub best_method: Option<usize>,
+    /// The wall clock time of the run in seconds
+    pub elapsed: f64,
+    /// The statistics of each heuristic, in the order they were given
+    pub methods: Vec<MethodStatistics>,
+}
+
+/// Runs the heuristics concurrently from the starting point, and returns the best point along with the statistics of each
+/// heuristic.
+///
+/// Example:
+/// ``` rust
+/// use hercules::qubo::Qubo;
+/// use hercules::heuristic::{Heuristic, IteratedLocalSearch, TabuSearch, VariableNeighborhoodSearch};
+/// use hercules::portfolio::{run_portfolio, PortfolioOptions};
+/// use smolprng::{PRNG, JsfLarge};
+/// use ndarray::Array1;
+///
+/// // generate a random QUBO
+/// let mut prng = PRNG {
+///   generator: JsfLarge::default(),
+/// };
+/// let p = Qubo::make_random_qubo(50, &mut prng, 0.2);
+///
+/// let heuristics: Vec<Box<dyn Heuristic>> = vec![
+///     Box::new(TabuSearch { tenure: 10 }),
+///     Box::new(IteratedLocalSearch { seed: 1 }),
+///     Box::new(VariableNeighborhoodSearch { k_max: 10, seed: 2 }),
+/// ];
+///
+/// // race the heuristics for 0.5 seconds, sharing the incumbent every 0.1 seconds
+/// let mut options = PortfolioOptions::new();
+/// options.max_time = 0.5;
+/// options.sync_interval = 0.1;
+///
+/// let result = run_portfolio(&p, &Array1::zeros(p.num_x()), &heuristics, &options);
+///
+/// for method in &result.methods {
+///     println!("{}: {} after {} seconds", method.name, method.objective, method.time_to_best);
+/// }
+/// ```
+pub fn run_portfolio(
+    qubo: &Qubo,
+    start: &Array1<usize>,
+    heuristics: &[Box<dyn Heuristic>],
+    options: &PortfolioOptions,
+) -> PortfolioResult {
+    let run = || run_rounds(qubo, start, heuristics, options);
+
+    // with fewer threads than heuristics, the heuristics that have to wait for a thread get less of the time budget
+    let threads = match options.threads {
+        0 => heuristics.len(),
+        threads => threads,
+    };
+
+    // fall back to the global pool if a pool with the given number of threads can not be made
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_or_else(|_| run(), |pool| pool.install(run))
+}
+
+/// The round loop of the portfolio
+fn run_rounds(
+    qubo: &Qubo,
+    start: &Array1<usize>,
+    heuristics: &[Box<dyn Heuristic>],
+    options: &PortfolioOptions,
+) -> PortfolioResult {
+    let start_time = get_current_time();
+    let start_objective = qubo.eval_usize(start);
+
+    let incumbent = Arc::new(SharedIncumbent::new(
+        start.clone(),
+        start_objective,
+        options.target_objective,
+    ));
+
+    let mut methods: Vec<_> = heuristics
+        .iter()
+        .map(|heuristic| MethodStatistics {
+            name: heuristic.name(),
+            objective: start_objective,
+            evaluations: 0,
+            steps: 0,
+            elapsed: 0.0,
+            time_to_best: 0.0,
+            rounds: 0,
+        })
+        .collect();
+    let mut active = vec![true; heuristics.len()];
+    let mut best_method = None;
+
+    loop {
+        let remaining = options.max_time - (get_current_time() - start_time);
+
+        if remaining <= 0.0 || incumbent.is_stopped() || !active.contains(&true) {
+            break;
+        }
+
+        let (x_round, obj_round) = incumbent.best();
+
+        // the time limit of each heuristic starts when it does, as the pool can have fewer threads than heuristics, and
+        // without sharing there is a single round that uses the whole budget
+        let results: Vec<_> = (0..heuristics.len())
+            .into_par_iter()
+            .filter(|&k| active[k])
+            .filter_map(|k| {
+                let launch = get_current_time() - start_time;
+                let time_limit = match options.share_incumbent && options.sync_interval > 0.0 {
+                    true => options.sync_interval.min(options.max_time - launch),
+                    false => options.max_time - launch,
+                };
+
+                if time_limit <= 0.0 {
+                    return None;
+                }
+
+                let budget = Budget {
+                    max_time: time_limit,
+                    incumbent: Some(Arc::clone(&incumbent)),
+                    ..Budget::unlimited()
+                };
+
+                Some((
+                    k,
+                    launch,
+                    time_limit,
+                    heuristics[k].run(qubo, &x_round, &budget),
+                ))
+            })
+            .collect();
+
+        for (k, launch, time_limit, result) in results {
+            let method = &mut methods[k];
+            method.evaluations += result.evaluations;
+            method.steps += result.steps;
+            method.elapsed += result.elapsed;
+            method.rounds += 1;
+
+            if result.objective < method.objective {
+                method.objective = result.objective;
+                method.time_to_best = launch + result.trace.last().map_or(0.0, |t| t.0);
+            }
+
+            // the heuristic that found the best point is the one with the lowest objective, the first one in the case of ties
+            let best_objective =
+                best_method.map_or(start_objective, |b: usize| methods[b].objective);
+            if methods[k].objective < best_objective {
+                best_method = Some(k);
+            }
+
+            // a heuristic that stopped on its own without improving has converged, where the objective is compared with
+            // the starting objective of the heuristic itself, as it can differ from the incumbent in the last bits
+            let run_start = result.trace.first().map_or(obj_round, |t| t.1);
+            let converged = result.elapsed < time_limit && result.objective >= run_start;
+            if converged || !options.share_incumbent {
+                active[k] = false;
+            }
+        }
+    }
+
+    let (x, objective) = incumbent.best();
+
+    PortfolioResult {
+        x,
+        objective,
+        best_method,
+        elapsed: get_current_time() - start_time,
+        methods,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heuristic::*;
+    use crate::portfolio::{run_portfolio, PortfolioOptions};
+    use crate::tests::{assert_finds_optimum, make_solver_qubo};
+    use ndarray::Array1;
+
+    fn make_portfolio() -> Vec<Box<dyn Heuristic>> {
+        vec![
+            Box::new(SimpleLocalSearch),
+            Box::new(TabuSearch { tenure: 5 }),
+            Box::new(IteratedLocalSearch { seed: 1 }),
+            Box::new(VariableNeighborhoodSearch { k_max: 5, seed: 2 }),
+        ]
+    }
+
+    #[test]
+    fn portfolio_finds_optimum() {
+        assert_finds_optimum(12, |p, _| {
+            let mut options = PortfolioOptions::new();
+            options.max_time = 0.2;
+            options.sync_interval = 0.05;
+
+            let result = run_portfolio(p, &Array1::zeros(p.num_x()), &make_portfolio(), &options);
+
+            assert!((p.eval_usize(&result.x) - result.objective).abs() < 1E-8);
+            assert_eq!(result.methods.len(), 4);
+
+            // the best method found the best point, and no method is better than the best point
+            let best = &result.methods[result.best_method.unwrap()];
+            assert!((best.objective - result.objective).abs() < 1E-8);
+            assert!(result
+                .methods
+                .iter()
+                .all(|m| m.objective >= result.objective - 1E-8));
+
+            result.x
+        });
+    }
+
+    #[test]
+    fn portfolio_non_positive_sync_interval() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+
+        let mut options = PortfolioOptions::new();
+        options.max_time = 0.1;
+
+        // a round that is not positive falls back to a single round over the whole time limit, instead of spinning
+        for sync_interval in [0.0, -1.0, f64::NAN] {
+            options.sync_interval = sync_interval;
+
+            let heuristics: Vec<Box<dyn Heuristic>> = vec![Box::new(TabuSearch { tenure: 5 })];
+            let result = run_portfolio(&p, &x_0, &heuristics, &options);
+
+            assert_eq!(result.methods[0].rounds, 1);
+            assert!((p.eval_usize(&result.x) - result.objective).abs() < 1E-8);
+        }
+    }
+
+    #[test]
+    fn portfolio_stops_converged_methods() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+
+        let mut options = PortfolioOptions::new();
+        options.max_time = 10.0;
+
+        let heuristics: Vec<Box<dyn Heuristic>> = vec![Box::new(SimpleLocalSearch)];
+        let result = run_portfolio(&p, &x_0, &heuristics, &options);
+
+        // the first round descends to a local minimum, and the second round can not improve on it, after which the
+        // portfolio stops well before the time limit
+        assert!(result.methods[0].rounds <= 2);
+        assert!(result.elapsed < options.max_time);
+    }
+
+    #[test]
+    fn portfolio_stops_at_target() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+
+        // any improvement on the starting point reaches the target
+        let mut options = PortfolioOptions::new();
+        options.max_time = 10.0;
+        options.target_objective = Some(p.eval_usize(&x_0) - 1E-6);
+
+        let result = run_portfolio(&p, &x_0, &make_portfolio(), &options);
+
+        assert!(result.objective <= p.eval_usize(&x_0) - 1E-6);
+        assert!(result.elapsed < 10.0);
+    }
+
+    #[test]
+    fn portfolio_without_sharing() {
+        let p = make_solver_qubo();
+        let x_0 = Array1::zeros(p.num_x());
+
+        let mut options = PortfolioOptions::new();
+        options.max_time = 0.1;
+        options.share_incumbent = false;
+
+        let result = run_portfolio(&p, &x_0, &make_portfolio(), &options);
+
+        // every heuristic runs exactly once, for the whole budget
+        assert!(result.methods.iter().all(|m| m.rounds == 1));
+        assert!(result.objective <= p.eval_usize(&x_0));
+    }
+}
@@ -1,8 +1,15 @@
-use crate::persistence::compute_iterative_persistence;
+use crate::branchbound_utils::get_current_time;
+use crate::constraint::{Constraint, ConstraintType};
+use crate::constraint_reduction::{ImplicationGraph, VariableMerge};
+use crate::lower_bound::li_lower_bound;
+use crate::persistence::{compute_iterative_persistence, compute_roof_duality};
 /// This file is the main module that defines the preprocessing functions
 ///
 /// Currently the following features are implemented:
 /// - Iterative persistence
+/// - Roof duality (QPBO) persistence
+/// - Propagation of the pairwise rules of glover2018 through an implication graph
+/// - Probing, which tentatively fixes each variable to both values
 use crate::qubo::Qubo;
 use ndarray::Array1;
 use std::collections::HashMap;
@@ -12,55 +19,308 @@ pub fn preprocess_qubo(
     qubo: &Qubo,
     fixed_variables: &HashMap<usize, usize>,
 ) -> HashMap<usize, usize> {
+    let (fixed_variables, _) =
+        preprocess_with_implications(qubo, fixed_variables, &ProbingOptions::new());
+
+    fixed_variables
+}
+
+/// Preprocesses the QUBO, and also returns the implication graph of the pairwise rules, so that it can be used to
+/// propagate fixings later on, such as in the nodes of the B&B tree
+///
+/// If the objective of a known solution is given in the probing options, then probing can also fix variables where one
+/// of the values can not lead to a better solution.
+pub fn preprocess_with_implications(
+    qubo: &Qubo,
+    fixed_variables: &HashMap<usize, usize>,
+    probing_options: &ProbingOptions,
+) -> (HashMap<usize, usize>, ImplicationGraph) {
     let initial_fixed = fixed_variables.clone();
 
     // start with an initial persistence check
     let fixed_variables = compute_iterative_persistence(qubo, &initial_fixed, qubo.num_x());
 
-    fixed_variables
+    // roof duality finds every persistency of the linear relaxation, the weak persistencies keep at least one optimal
+    // solution, which is all we need
+    let fixed_variables = compute_roof_duality(qubo, &fixed_variables).weak;
+
+    // generate the rules with the persistent variables fixed, they hold for every optimal solution with these fixings
+    let implications = ImplicationGraph::generate(qubo, &fixed_variables);
+
+    // as the rules hold at the optimal solutions, this can not find a contradiction, but it is cheap to fall back on
+    let fixed_variables =
+        propagate_fixed_variables(qubo, &fixed_variables, &implications).unwrap_or(fixed_variables);
+
+    // probe the remaining free variables, keeping the implications it finds for later propagation
+    match probe_variables(qubo, &fixed_variables, &implications, probing_options) {
+        Some(probing) => {
+            let mut implications = implications;
+            for constraint in probing.implications {
+                implications.add_constraint(constraint);
+            }
+            (probing.fixed_variables, implications)
+        }
+        None => (fixed_variables, implications),
+    }
+}
+
+/// The amount of work that probing does by default, counted in the nonzeros and variables that are visited
+const PROBING_WORK_LIMIT: usize = 10_000_000;
+
+/// Options for probing, missing fields take their default value when deserialized
+///
+/// By default, probing is only limited by the number of probes, so that the fixings do not depend on the machine.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ProbingOptions {
+    /// The time limit of probing in seconds, if any
+    pub max_time: Option<f64>,
+    /// The maximum number of variables to probe, where 0 turns off probing, by default this is scaled with the size
+    /// of the problem
+    pub max_probes: Option<usize>,
+    /// The objective of a known solution, values of a variable whose lower bound is above this are not optimal
+    pub incumbent: Option<f64>,
+}
+
+impl ProbingOptions {
+    pub const fn new() -> Self {
+        Self {
+            max_time: None,
+            max_probes: None,
+            incumbent: None,
+        }
+    }
+
+    /// Returns the maximum number of variables to probe on the QUBO, where by default every variable is probed unless
+    /// the problem is so large that this would be more than the work limit
+    pub fn probe_limit(&self, qubo: &Qubo) -> usize {
+        self.max_probes.unwrap_or_else(|| {
+            // every probe propagates both sides, which is at least a pass over the nonzeros
+            let work_per_probe = qubo.q.nnz() + qubo.num_x();
+            qubo.num_x()
+                .min((PROBING_WORK_LIMIT / work_per_probe.max(1)).max(1))
+        })
+    }
+}
+
+impl Default for ProbingOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The result of probing
+#[derive(Clone, Debug, Default)]
+pub struct ProbingResult {
+    /// The given fixed variables, along with every variable fixed by probing
+    pub fixed_variables: HashMap<usize, usize>,
+    /// The implications between the probed variables and the variables they fix, which hold in every optimal solution
+    pub implications: Vec<Constraint>,
+    /// The free variables that are equal to, or the complement of, another free variable
+    pub merges: Vec<VariableMerge>,
+    /// The number of variables that were probed
+    pub probes: usize,
+}
+
+/// Probes the free variables, by tentatively fixing each variable to 0 and to 1 and propagating both sides with the
+/// implication graph and persistence.
+///
+/// A side that is infeasible, or whose lower bound is above the incumbent, can not hold an optimal solution, so the
+/// variable is fixed to the other side. Variables that are fixed the same way on both sides are fixed, and the variables
+/// fixed on only one side, or to different values on each side, give implications, equalities and complements between
+/// them and the probed variable.
+///
+/// Returns None if both sides of a variable can not hold an optimal solution, such as if the incumbent is below the
+/// optimal objective.
+pub fn probe_variables(
+    qubo: &Qubo,
+    fixed_variables: &HashMap<usize, usize>,
+    implications: &ImplicationGraph,
+    options: &ProbingOptions,
+) -> Option<ProbingResult> {
+    let start_time = get_current_time();
+    let max_probes = options.probe_limit(qubo);
+
+    let mut result = ProbingResult {
+        fixed_variables: fixed_variables.clone(),
+        ..ProbingResult::default()
+    };
+
+    for i in 0..qubo.num_x() {
+        let out_of_time = options
+            .max_time
+            .is_some_and(|max_time| get_current_time() - start_time > max_time);
+
+        if result.probes >= max_probes || out_of_time {
+            break;
+        }
+
+        if result.fixed_variables.contains_key(&i) {
+            continue;
+        }
+        result.probes += 1;
+
+        // propagate both sides, where a side with a lower bound above the incumbent is as good as infeasible
+        let probe = |value: usize| {
+            let mut fixed = result.fixed_variables.clone();
+            fixed.insert(i, value);
+            propagate_fixed_variables(qubo, &fixed, implications).filter(|fixed| {
+                !matches!(options.incumbent, Some(incumbent) if li_lower_bound(qubo, fixed) > incumbent)
+            })
+        };
+
+        let (zero_side, one_side) = match (probe(0), probe(1)) {
+            (None, None) => return None,
+            // only one side is left, so everything it fixes is fixed
+            (Some(fixed), None) | (None, Some(fixed)) => {
+                result.fixed_variables = fixed;
+                continue;
+            }
+            (Some(zero_side), Some(one_side)) => (zero_side, one_side),
+        };
+
+        let mut new_fixed = HashMap::new();
+        for (&j, &zero_value) in &zero_side {
+            if result.fixed_variables.contains_key(&j) || j == i {
+                continue;
+            }
+
+            match one_side.get(&j) {
+                // fixed the same way on both sides
+                Some(&one_value) if one_value == zero_value => {
+                    new_fixed.insert(j, zero_value);
+                }
+                // x_j = x_i or x_j = 1 - x_i
+                Some(_) => {
+                    let constraint_type = match zero_value {
+                        0 => ConstraintType::Equal,
+                        _ => ConstraintType::ExactlyOne,
+                    };
+                    result
+                        .implications
+                        .push(Constraint::new(i, j, constraint_type));
+                }
+                // x_i = 0 implies x_j = zero_value
+                None => {
+                    let constraint = match zero_value {
+                        0 => Constraint::new(j, i, ConstraintType::LessThan),
+                        _ => Constraint::new(i, j, ConstraintType::AtLeastOne),
+                    };
+                    result.implications.push(constraint);
+                }
+            }
+        }
+
+        // x_i = 1 implies x_j = one_value, the variables fixed on both sides have already been handled
+        for (&j, &one_value) in &one_side {
+            if result.fixed_variables.contains_key(&j) || j == i || zero_side.contains_key(&j) {
+                continue;
+            }
+
+            let constraint = match one_value {
+                0 => Constraint::new(i, j, ConstraintType::NoMoreThanOne),
+                _ => Constraint::new(i, j, ConstraintType::LessThan),
+            };
+            result.implications.push(constraint);
+        }
+
+        if !new_fixed.is_empty() {
+            new_fixed.extend(result.fixed_variables.iter());
+            result.fixed_variables = propagate_fixed_variables(qubo, &new_fixed, implications)?;
+        }
+    }
+
+    // the equalities and complements come from the components of the implications, along with anything they force
+    let mut graph = implications.clone();
+    for &constraint in &result.implications {
+        graph.add_constraint(constraint);
+    }
+    let analysis = graph.two_sat_analysis(&result.fixed_variables)?;
+
+    result.fixed_variables = analysis.fixed_variables;
+    result.merges = analysis.merges;
+
+    Some(result)
+}
+
+/// Alternates between propagating the fixed variables through the implication graph and persistence, until neither fixes
+/// any more variables
+///
+/// Returns None if the fixed variables contradict the implication graph, in which case there is no optimal solution with
+/// these variables fixed.
+pub fn propagate_fixed_variables(
+    qubo: &Qubo,
+    fixed_variables: &HashMap<usize, usize>,
+    implications: &ImplicationGraph,
+) -> Option<HashMap<usize, usize>> {
+    let mut fixed_variables = fixed_variables.clone();
+
+    loop {
+        let propagated = implications.propagate(&fixed_variables)?;
+        let persistent = compute_iterative_persistence(qubo, &propagated, qubo.num_x());
+
+        if persistent.len() == fixed_variables.len() {
+            return Some(persistent);
+        }
+
+        fixed_variables = persistent;
+    }
 }
 
 /// This function is used to get the effect of the fixed variables on the linear term, we want to
 /// avoid generating copies of the Qubo object
 ///
+/// Returns the linear term of the free variables and the constant term, which contains the offset of the QUBO and every
+/// term that only depends on the fixed variables. The linear terms of the fixed variables are set to zero, so that
+/// evaluating the original Hessian with these returned terms at a point with the fixed variables set to zero recovers
+/// the original objective.
+///
 /// This is generally as expensive as a function evaluation
-pub fn get_fixed_c(qubo: &Qubo, fixed_variables: &HashMap<usize, usize>) -> Array1<f64> {
+pub fn get_fixed_c(qubo: &Qubo, fixed_variables: &HashMap<usize, usize>) -> (Array1<f64>, f64) {
     let mut new_c = qubo.c.clone();
+    let mut constant = qubo.offset;
 
     // there is likely a better way to do this, but for now we are looping through the Hessian
     for (&value, (i, j)) in &qubo.q {
-        // diagonal elements will never be extracted
-        if i == j {
-            continue;
-        }
-
         // check we have fixed variables
         let x_i_fixed = fixed_variables.contains_key(&i);
         let x_j_fixed = fixed_variables.contains_key(&j);
 
-        // if both are fixed, then the term is a constant and it doesn't matter
+        // if both are fixed, then the term is a constant, this includes the diagonal
         if x_i_fixed && x_j_fixed {
-            continue;
-        }
-
-        if x_i_fixed {
             let x_i = fixed_variables[&i] as f64;
-            new_c[j] += value * x_i;
+            let x_j = fixed_variables[&j] as f64;
+            constant += 0.5 * value * x_i * x_j;
+        } else if x_i_fixed {
+            let x_i = fixed_variables[&i] as f64;
+            new_c[j] += 0.5 * value * x_i;
         } else if x_j_fixed {
             let x_j = fixed_variables[&j] as f64;
-            new_c[i] += (value * x_j).min(0.0);
+            new_c[i] += 0.5 * value * x_j;
         }
 
         // if neither is fixed, then we don't need to do anything
     }
 
-    new_c
+    // move the linear terms of the fixed variables into the constant
+    for (&i, &x_i) in fixed_variables {
+        constant += qubo.c[i] * x_i as f64;
+        new_c[i] = 0.0;
+    }
+
+    (new_c, constant)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::preprocess::preprocess_qubo;
+    use crate::constraint::{Constraint, ConstraintType};
+    use crate::constraint_reduction::ImplicationGraph;
+    use crate::preprocess::{
+        get_fixed_c, preprocess_qubo, probe_variables, propagate_fixed_variables, ProbingOptions,
+    };
     use crate::qubo::Qubo;
+    use crate::tests::{brute_force_min, make_solver_qubo, make_test_prng};
     use ndarray::Array1;
     use sprs::CsMat;
     use std::collections::HashMap;
@@ -74,4 +334,160 @@ mod tests {
         let fixed_variables = preprocess_qubo(&p, &fixed_variables);
         assert_eq!(fixed_variables.len(), 3);
     }
+
+    #[test]
+    fn test_get_fixed_c() {
+        let mut p = make_solver_qubo();
+        p.offset = 2.0;
+
+        let fixed_variables = HashMap::from([(0, 1), (3, 0), (7, 1)]);
+        let (c, constant) = get_fixed_c(&p, &fixed_variables);
+        let p_fixed = Qubo::new_with_offset(p.q.clone(), c, constant);
+
+        // the fixed variables are zeroed in the reduced problem, but set in the full problem
+        let mut x = Array1::<usize>::ones(p.num_x());
+        x[3] = 0;
+        let mut x_reduced = x.clone();
+        x_reduced[0] = 0;
+        x_reduced[7] = 0;
+
+        assert!((p.eval_usize(&x) - p_fixed.eval_usize(&x_reduced)).abs() < 1E-10);
+    }
+
+    #[test]
+    fn test_preprocess_keeps_optimum() {
+        let mut prng = make_test_prng();
+
+        for _ in 0..20 {
+            // the bounds of the preprocessor assume a symmetric Hessian, as in the B&B solver
+            let p = Qubo::make_random_qubo(10, &mut prng, 0.5).make_symmetric();
+            let fixed_variables = preprocess_qubo(&p, &HashMap::new());
+
+            // the best solution with the fixed variables is still optimal
+            let (p_fixed, _, _) = p.fix_variables(&fixed_variables);
+            let reduced_min = brute_force_min(&p_fixed);
+            assert!((reduced_min - brute_force_min(&p)).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn test_propagate_fixed_variables_contradiction() {
+        let p = make_solver_qubo();
+
+        // x_0 <= x_1 and x_0 + x_1 <= 1 can not both hold with x_0 = 1
+        let implications = ImplicationGraph::from_constraints(
+            p.num_x(),
+            vec![
+                Constraint::new(0, 1, ConstraintType::NoMoreThanOne),
+                Constraint::new(0, 1, ConstraintType::LessThan),
+            ],
+        );
+
+        assert!(propagate_fixed_variables(&p, &HashMap::from([(0, 1)]), &implications).is_none());
+
+        let fixed = propagate_fixed_variables(&p, &HashMap::from([(1, 0)]), &implications).unwrap();
+        assert_eq!(fixed[&0], 0);
+    }
+
+    #[test]
+    fn test_probing_holds_at_optimum() {
+        let mut prng = make_test_prng();
+
+        for _ in 0..20 {
+            let p = Qubo::make_random_qubo(10, &mut prng, 0.5).make_symmetric();
+            let implications = ImplicationGraph::generate(&p, &HashMap::new());
+            let result =
+                probe_variables(&p, &HashMap::new(), &implications, &ProbingOptions::new())
+                    .unwrap();
+
+            // every optimal solution abides by the fixings, implications and merges found by probing
+            let min = brute_force_min(&p);
+            for k in 0..1usize << p.num_x() {
+                let x = Array1::from_shape_fn(p.num_x(), |i| (k >> i) & 1);
+                if p.eval_usize(&x) > min + 1E-10 {
+                    continue;
+                }
+
+                let solution: HashMap<_, _> = x.iter().copied().enumerate().collect();
+                assert!(result.fixed_variables.iter().all(|(&i, &v)| x[i] == v));
+                assert!(result.implications.iter().all(|c| c.check(&solution)));
+                assert!(result
+                    .merges
+                    .iter()
+                    .all(|m| { (x[m.variable] == x[m.representative]) != m.complement }));
+            }
+        }
+    }
+
+    #[test]
+    fn test_probing_implications_propagate_at_optimum() {
+        let mut prng = make_test_prng();
+
+        for _ in 0..20 {
+            let p = Qubo::make_random_qubo(10, &mut prng, 0.5).make_symmetric();
+            let mut implications = ImplicationGraph::generate(&p, &HashMap::new());
+            let result =
+                probe_variables(&p, &HashMap::new(), &implications, &ProbingOptions::new())
+                    .unwrap();
+            for &constraint in &result.implications {
+                implications.add_constraint(constraint);
+            }
+
+            // fixing any part of an optimal solution has to propagate to more of that same optimal solution, as in the
+            // nodes of the B&B tree
+            let min = brute_force_min(&p);
+            for k in 0..1usize << p.num_x() {
+                let x = Array1::from_shape_fn(p.num_x(), |i| (k >> i) & 1);
+                if p.eval_usize(&x) > min + 1E-10 {
+                    continue;
+                }
+
+                for mask in 0..1usize << p.num_x() {
+                    let partial: HashMap<_, _> = (0..p.num_x())
+                        .filter(|i| (mask >> i) & 1 == 1)
+                        .map(|i| (i, x[i]))
+                        .collect();
+
+                    let fixed = implications.propagate(&partial).unwrap();
+                    assert!(fixed.iter().all(|(&i, &v)| x[i] == v));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_probing_with_incumbent() {
+        let mut prng = make_test_prng();
+        let p = Qubo::make_random_qubo(10, &mut prng, 0.5).make_symmetric();
+        let implications = ImplicationGraph::new(p.num_x());
+
+        // with the optimal objective as the incumbent, an optimal solution is kept
+        let mut options = ProbingOptions::new();
+        options.incumbent = Some(brute_force_min(&p));
+        let result = probe_variables(&p, &HashMap::new(), &implications, &options).unwrap();
+        let (p_fixed, _, _) = p.fix_variables(&result.fixed_variables);
+        assert!((brute_force_min(&p_fixed) - brute_force_min(&p)).abs() < 1E-10);
+
+        // no solution is better than an incumbent of negative infinity
+        options.incumbent = Some(f64::NEG_INFINITY);
+        assert!(probe_variables(&p, &HashMap::new(), &implications, &options).is_none());
+    }
+
+    #[test]
+    fn test_probing_limit() {
+        let p = make_solver_qubo();
+        let implications = ImplicationGraph::new(p.num_x());
+        let fixed_variables = HashMap::from([(0, 1)]);
+
+        // small problems are probed in full by default
+        let mut options = ProbingOptions::new();
+        assert_eq!(options.probe_limit(&p), p.num_x());
+
+        options.max_probes = Some(0);
+        let result = probe_variables(&p, &fixed_variables, &implications, &options).unwrap();
+
+        assert_eq!(result.probes, 0);
+        assert_eq!(result.fixed_variables, fixed_variables);
+        assert!(result.implications.is_empty());
+    }
 }
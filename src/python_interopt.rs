@@ -3,20 +3,43 @@ use crate::qubo::Qubo;
 use std::collections::HashMap;
 
 use ndarray::Array1;
+use pyo3::exceptions::{PyIOError, PyValueError};
 use pyo3::prelude::*;
 
-use crate::persistence::compute_iterative_persistence;
+use crate::persistence::{compute_iterative_persistence, compute_roof_duality};
 use crate::{kopt, local_search};
 use smolprng::{JsfLarge, PRNG};
 
 use crate::branchbound::BBSolver;
-use crate::branchbound_utils::get_current_time;
+use crate::heuristic::{
+    GainCriteriaSearch, Heuristic, IteratedLocalSearch, MixedSearch, MultiStart,
+    ParticleSwarmSearch, RandomSearch, SimpleLocalSearch, SimulatedAnnealing, TabuSearch,
+    VariableNeighborhoodSearch,
+};
+use crate::io_error::QuboIOError;
+use crate::local_search::AnnealingSchedule;
+use crate::portfolio::{run_portfolio, PortfolioOptions};
 use crate::preprocess::preprocess_qubo;
+use crate::qubo_binary::{convert_qubo_file, read_qubo_file};
 use crate::solver_options::SolverOptions;
 use crate::variable_reduction::{generate_rule_11, generate_rule_21};
 
 // type alias for the qubo data object from python
-type QuboData = (Vec<usize>, Vec<usize>, Vec<f64>, Vec<f64>, usize);
+type QuboData = (Vec<usize>, Vec<usize>, Vec<f64>, Vec<f64>, f64, usize);
+
+// type alias for the statistics of a heuristic of a portfolio run returned to python
+type MethodData = (String, f64, f64, usize);
+
+/// Converts file errors into python exceptions, OS level failures become an `IOError` (`OSError`) and malformed files
+/// become a `ValueError`
+impl From<QuboIOError> for PyErr {
+    fn from(err: QuboIOError) -> Self {
+        match err {
+            QuboIOError::Io { .. } => PyIOError::new_err(err.to_string()),
+            _ => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
 
 /// This reads in the QUBO from a file, and solves the QUBO using random search, returns the best solution found.
 ///
@@ -29,10 +52,10 @@ type QuboData = (Vec<usize>, Vec<usize>, Vec<f64>, Vec<f64>, usize);
 /// ```
 /// # Errors
 ///
-/// if the file does not exist, then it will abort
+/// if the file does not exist or is malformed, then it will raise an exception
 pub fn rand_from_file(filename: String, seed: usize, num_points: usize) -> PyResult<Vec<usize>> {
     // read in the QUBO from file
-    let p = Qubo::read_qubo(filename.as_str());
+    let p = Qubo::try_read_qubo(filename.as_str())?;
 
     // set up the prng
     let mut prng = PRNG {
@@ -64,7 +87,9 @@ pub fn rand_from_file(filename: String, seed: usize, num_points: usize) -> PyRes
 /// This function should never error, but if it does, it will abort.
 pub fn rand(problem: QuboData, seed: usize, num_points: usize) -> PyResult<Vec<usize>> {
     // read in the QUBO from file
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     // set up the prng
     let mut prng = PRNG {
@@ -90,7 +115,7 @@ pub fn rand(problem: QuboData, seed: usize, num_points: usize) -> PyResult<Vec<u
 ///
 /// # Errors
 ///
-/// if the file does not exist, then it will abort
+/// if the file does not exist or is malformed, then it will raise an exception
 #[pyfunction]
 pub fn pso_from_file(
     filename: String,
@@ -99,7 +124,7 @@ pub fn pso_from_file(
     max_steps: usize,
 ) -> PyResult<(Vec<usize>, f64)> {
     // read in the QUBO from file
-    let p = Qubo::read_qubo(filename.as_str());
+    let p = Qubo::try_read_qubo(filename.as_str())?;
 
     // set up the prng
     let mut prng = PRNG {
@@ -136,7 +161,9 @@ pub fn pso(
     max_steps: usize,
 ) -> PyResult<(Vec<usize>, f64)> {
     // read in the QUBO from file
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     // set up the prng
     let mut prng = PRNG {
@@ -162,7 +189,7 @@ pub fn pso(
 ///
 /// # Errors
 ///
-/// if the file does not exist, then it will abort
+/// if the file does not exist or is malformed, then it will raise an exception
 #[pyfunction]
 pub fn gls_from_file(
     filename: String,
@@ -170,7 +197,7 @@ pub fn gls_from_file(
     max_steps: usize,
 ) -> PyResult<(Vec<usize>, f64)> {
     // read in the QUBO from file
-    let p = Qubo::read_qubo(filename.as_str());
+    let p = Qubo::try_read_qubo(filename.as_str())?;
 
     // convert the input to the correct type
     let x_array = Array1::from(x_0);
@@ -203,7 +230,9 @@ pub fn gls_from_file(
 #[pyfunction]
 pub fn gls(problem: QuboData, x_0: Vec<usize>, max_steps: usize) -> PyResult<(Vec<usize>, f64)> {
     // read in the QUBO from file
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     // convert the input to the correct type
     let x_array = Array1::from(x_0);
@@ -231,7 +260,7 @@ pub fn gls(problem: QuboData, x_0: Vec<usize>, max_steps: usize) -> PyResult<(Ve
 /// ```
 /// # Errors
 ///
-/// if the file does not exist, then it will abort
+/// if the file does not exist or is malformed, then it will raise an exception
 #[pyfunction]
 pub fn mls_from_file(
     filename: String,
@@ -239,7 +268,7 @@ pub fn mls_from_file(
     max_steps: usize,
 ) -> PyResult<(Vec<usize>, f64)> {
     // read in the QUBO from file
-    let p = Qubo::read_qubo(filename.as_str());
+    let p = Qubo::try_read_qubo(filename.as_str())?;
 
     // convert the input to the correct type
     let x_array = Array1::from(x_0);
@@ -272,7 +301,9 @@ pub fn mls_from_file(
 #[pyfunction]
 pub fn mls(problem: QuboData, x_0: Vec<usize>, max_steps: usize) -> PyResult<(Vec<usize>, f64)> {
     // read in the QUBO from file
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     // convert the input to the correct type
     let x_array = Array1::from(x_0);
@@ -284,6 +315,377 @@ pub fn mls(problem: QuboData, x_0: Vec<usize>, max_steps: usize) -> PyResult<(Ve
     Ok((x_soln.to_vec(), p.eval_usize(&x_soln)))
 }
 
+/// This reads in the QUBO from a file, and solves the QUBO using tabu search, returns the best solution found.
+///
+/// The search stops after max_steps steps, or after max_time seconds if a time limit is given.
+///
+/// Example
+/// ``` python
+/// import hercules
+/// import random
+///
+/// problem = hercules.read_qubo("test.qubo")
+/// num_x = problem[-1] // the number of x variables is the last variable of the problem
+/// x_0 = [random.randint(0,1) for _ in range(num_x)]
+///
+/// # read in the QUBO from a file, and run tabu search with a tenure of 10 for 1000 steps or 5 seconds
+/// x_soln, obj = hercules.tabu_from_file("file.qubo", x_0, 10, 1000, 5.0)
+/// ```
+/// # Errors
+///
+/// if the file does not exist or is malformed, then it will raise an exception
+#[pyfunction]
+pub fn tabu_from_file(
+    filename: String,
+    x_0: Vec<usize>,
+    tenure: usize,
+    max_steps: usize,
+    max_time: Option<f64>,
+) -> PyResult<(Vec<usize>, f64)> {
+    // read in the QUBO from file
+    let p = Qubo::try_read_qubo(filename.as_str())?;
+
+    // convert the input to the correct type
+    let x_array = Array1::from(x_0);
+
+    // run the tabu search
+    let max_time = max_time.unwrap_or(f64::INFINITY);
+    let x_soln = local_search::tabu_search(&p, &x_array, tenure, max_steps, max_time);
+
+    // return the solution and its objective
+    Ok((x_soln.to_vec(), p.eval_usize(&x_soln)))
+}
+
+/// This solves the QUBO using tabu search, returns the best solution found.
+///
+/// The search stops after max_steps steps, or after max_time seconds if a time limit is given.
+///
+/// Example
+/// ``` python
+/// import hercules
+/// import random
+///
+/// # read in the qubo problem
+/// problem = hercules.read_qubo("test.qubo")
+/// num_x = problem[-1] // the number of x variables is the last variable of the problem
+/// x_0 = [random.randint(0,1) for _ in range(num_x)]
+///
+/// # solve via tabu search with a tenure of 10 for 1000 steps
+/// x_soln, obj = hercules.tabu(problem, x_0, 10, 1000)
+/// ```
+/// # Errors
+///
+/// This function should never error, but if it does, it will abort.
+#[pyfunction]
+pub fn tabu(
+    problem: QuboData,
+    x_0: Vec<usize>,
+    tenure: usize,
+    max_steps: usize,
+    max_time: Option<f64>,
+) -> PyResult<(Vec<usize>, f64)> {
+    // read in the QUBO from file
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
+
+    // convert the input to the correct type
+    let x_array = Array1::from(x_0);
+
+    // run the tabu search
+    let max_time = max_time.unwrap_or(f64::INFINITY);
+    let x_soln = local_search::tabu_search(&p, &x_array, tenure, max_steps, max_time);
+
+    // return the solution and its objective
+    Ok((x_soln.to_vec(), p.eval_usize(&x_soln)))
+}
+
+/// This solves the QUBO using a tabu search from each of the initial points in parallel, returns all of the solutions.
+///
+/// Example
+/// ``` python
+/// import hercules
+/// import random
+///
+/// # read in the initial problem
+/// problem = hercules.read_qubo("test.qubo")
+/// num_x = problem[-1] // the number of x variables is the last variable of the problem
+/// num_starts = 10
+///
+/// # generate the initial starts of each search
+/// xs = [[random.randint(0,1) for _ in range(num_x)] for i in range(num_starts)]
+///
+/// # solve via multi start tabu search with a tenure of 10 for 1000 steps or 5 seconds
+/// x_solns, objs = hercules.mtabu(problem, xs, 10, 1000, 5.0)
+/// ```
+/// # Errors
+///
+/// This function should never error, but if it does, it will abort.
+#[pyfunction]
+pub fn mtabu(
+    problem: QuboData,
+    xs: Vec<Vec<usize>>,
+    tenure: usize,
+    max_steps: usize,
+    max_time: Option<f64>,
+) -> PyResult<(Vec<Vec<usize>>, Vec<f64>)> {
+    // read in the QUBO from file
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
+
+    // convert the input to the correct type
+    let xs = xs.into_iter().map(Array1::<usize>::from).collect();
+
+    // run the multi-start tabu search
+    let max_time = max_time.unwrap_or(f64::INFINITY);
+    let x_solns = local_search::multi_tabu_search(&p, &xs, tenure, max_steps, max_time);
+
+    // calculate the objective of each solution
+    let objs = x_solns.iter().map(|x| p.eval_usize(x)).collect();
+
+    // convert the output to the correct type
+    let x_solns_vec = x_solns.iter().map(ndarray::ArrayBase::to_vec).collect();
+
+    // return the solutions and their objectives
+    Ok((x_solns_vec, objs))
+}
+
+/// This solves the QUBO using iterated local search, returns the best solution found.
+///
+/// The search stops after max_iterations iterations, after max_time seconds if a time limit is given, or once the
+/// objective reaches target if a target is given.
+///
+/// Example
+/// ``` python
+/// import hercules
+/// import random
+///
+/// # read in the qubo problem
+/// problem = hercules.read_qubo("test.qubo")
+/// num_x = problem[-1] // the number of x variables is the last variable of the problem
+/// x_0 = [random.randint(0,1) for _ in range(num_x)]
+///
+/// # solve via iterated local search with seed 0 for 1000 iterations or 5 seconds
+/// x_soln, obj = hercules.ils(problem, x_0, 0, 1000, 5.0)
+/// ```
+/// # Errors
+///
+/// This function should never error, but if it does, it will abort.
+#[pyfunction]
+pub fn ils(
+    problem: QuboData,
+    x_0: Vec<usize>,
+    seed: usize,
+    max_iterations: usize,
+    max_time: Option<f64>,
+    target: Option<f64>,
+) -> PyResult<(Vec<usize>, f64)> {
+    // read in the QUBO from file
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
+
+    // convert the input to the correct type
+    let x_array = Array1::from(x_0);
+
+    // set up the prng
+    let mut prng = PRNG {
+        generator: JsfLarge::from(seed as u64),
+    };
+
+    // run the iterated local search
+    let max_time = max_time.unwrap_or(f64::INFINITY);
+    let x_soln = local_search::iterated_local_search(
+        &p,
+        &x_array,
+        max_iterations,
+        max_time,
+        target,
+        &mut prng,
+    );
+
+    // return the solution and its objective
+    Ok((x_soln.to_vec(), p.eval_usize(&x_soln)))
+}
+
+/// This solves the QUBO using variable neighborhood search with shakes of up to k_max flips, returns the best solution
+/// found.
+///
+/// The search stops after max_iterations iterations, after max_time seconds if a time limit is given, or once the
+/// objective reaches target if a target is given.
+///
+/// Example
+/// ``` python
+/// import hercules
+/// import random
+///
+/// # read in the qubo problem
+/// problem = hercules.read_qubo("test.qubo")
+/// num_x = problem[-1] // the number of x variables is the last variable of the problem
+/// x_0 = [random.randint(0,1) for _ in range(num_x)]
+///
+/// # solve via variable neighborhood search with seed 0, shakes of up to 10 flips, for 1000 iterations or 5 seconds
+/// x_soln, obj = hercules.vns(problem, x_0, 0, 10, 1000, 5.0)
+/// ```
+/// # Errors
+///
+/// This function should never error, but if it does, it will abort.
+#[pyfunction]
+pub fn vns(
+    problem: QuboData,
+    x_0: Vec<usize>,
+    seed: usize,
+    k_max: usize,
+    max_iterations: usize,
+    max_time: Option<f64>,
+    target: Option<f64>,
+) -> PyResult<(Vec<usize>, f64)> {
+    // read in the QUBO from file
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
+
+    // convert the input to the correct type
+    let x_array = Array1::from(x_0);
+
+    // set up the prng
+    let mut prng = PRNG {
+        generator: JsfLarge::from(seed as u64),
+    };
+
+    // run the variable neighborhood search
+    let max_time = max_time.unwrap_or(f64::INFINITY);
+    let x_soln = local_search::variable_neighborhood_search(
+        &p,
+        &x_array,
+        k_max,
+        max_iterations,
+        max_time,
+        target,
+        &mut prng,
+    );
+
+    // return the solution and its objective
+    Ok((x_soln.to_vec(), p.eval_usize(&x_soln)))
+}
+
+/// Creates the heuristic of the portfolio with the given name, with the default parameters of that heuristic
+fn make_heuristic(name: &str, seed: u64, num_x: usize) -> PyResult<Box<dyn Heuristic>> {
+    let heuristic: Box<dyn Heuristic> = match name {
+        "ls" => Box::new(SimpleLocalSearch),
+        "gls" => Box::new(GainCriteriaSearch),
+        "mls" => Box::new(MixedSearch),
+        "msls" => Box::new(MultiStart {
+            heuristic: SimpleLocalSearch,
+            num_starts: 10,
+            seed,
+        }),
+        "pso" => Box::new(ParticleSwarmSearch {
+            num_particles: 10,
+            seed,
+        }),
+        "tabu" => Box::new(TabuSearch {
+            tenure: local_search::default_tenure(num_x),
+        }),
+        "sa" => Box::new(SimulatedAnnealing {
+            schedule: AnnealingSchedule::Geometric {
+                beta_start: 0.1,
+                beta_end: 10.0,
+            },
+            num_sweeps: 1000,
+            num_restarts: 100,
+            seed,
+        }),
+        "ils" => Box::new(IteratedLocalSearch { seed }),
+        "vns" => Box::new(VariableNeighborhoodSearch { k_max: 10, seed }),
+        "random" => Box::new(RandomSearch { seed }),
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "unknown heuristic {name}, expected one of ls, gls, mls, msls, pso, tabu, sa, ils, vns or random"
+            )))
+        }
+    };
+
+    Ok(heuristic)
+}
+
+/// This runs a portfolio of heuristics concurrently from x_0 for max_time seconds, returns the best solution found.
+///
+/// Along with the best solution, this returns the name, best objective, time to best and number of evaluations of each
+/// heuristic.
+///
+/// The heuristics are named ls, gls, mls, msls, pso, tabu, sa, ils, vns and random. The heuristics restart from the best
+/// solution found by any of them every sync_interval seconds, and all of them stop once a solution reaching the target is
+/// found if a target is given.
+///
+/// Example
+/// ``` python
+/// import hercules
+///
+/// # read in the qubo problem
+/// problem = hercules.read_qubo("test.qubo")
+/// num_x = problem[-1] // the number of x variables is the last variable of the problem
+/// x_0 = [0] * num_x
+///
+/// # race particle swarm, gain search, mixed search and multi-start local search for 10 seconds
+/// x_soln, obj, stats = hercules.portfolio_search(problem, x_0, ["pso", "gls", "mls", "msls"], 10.0)
+///
+/// for name, method_obj, time_to_best, evaluations in stats:
+///     print(name, method_obj, time_to_best, evaluations)
+/// ```
+/// # Errors
+///
+/// if any of the heuristic names is unknown, then it will raise an exception
+#[pyfunction]
+#[allow(clippy::too_many_arguments)] // the trailing options are optional keyword arguments in python
+pub fn portfolio_search(
+    problem: QuboData,
+    x_0: Vec<usize>,
+    methods: Vec<String>,
+    max_time: f64,
+    target: Option<f64>,
+    sync_interval: Option<f64>,
+    seed: Option<usize>,
+    threads: Option<usize>,
+) -> PyResult<(Vec<usize>, f64, Vec<MethodData>)> {
+    // read in the QUBO from file
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
+
+    // convert the input to the correct type
+    let x_array = Array1::from(x_0);
+
+    // set up each of the heuristics, with a different seed for each
+    let seed = seed.unwrap_or(0) as u64;
+    let heuristics = methods
+        .iter()
+        .enumerate()
+        .map(|(k, name)| make_heuristic(name, seed + k as u64, p.num_x()))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let mut options = PortfolioOptions::new();
+    options.max_time = max_time;
+    options.target_objective = target;
+    options.sync_interval = sync_interval.unwrap_or(options.sync_interval);
+    options.threads = threads.unwrap_or(0);
+
+    // run the portfolio
+    let result = run_portfolio(&p, &x_array, &heuristics, &options);
+
+    // convert the statistics to the correct type
+    let stats = result
+        .methods
+        .into_iter()
+        .map(|m| (m.name, m.objective, m.time_to_best, m.evaluations))
+        .collect();
+
+    // return the solution, its objective and the statistics of each heuristic
+    let objective = p.eval_usize(&result.x);
+    Ok((result.x.to_vec(), objective, stats))
+}
+
 /// This reads in the QUBO from a file, and solves the QUBO using multi-start local search, returns the best solution found.
 ///
 /// Example
@@ -304,14 +706,14 @@ pub fn mls(problem: QuboData, x_0: Vec<usize>, max_steps: usize) -> PyResult<(Ve
 /// ```
 /// # Errors
 ///
-/// if the file does not exist, then it will abort
+/// if the file does not exist or is malformed, then it will raise an exception
 #[pyfunction]
 pub fn msls_from_file(
     filename: String,
     xs: Vec<Vec<usize>>,
 ) -> PyResult<(Vec<Vec<usize>>, Vec<f64>)> {
     // read in the QUBO from file
-    let p = Qubo::read_qubo(filename.as_str());
+    let p = Qubo::try_read_qubo(filename.as_str())?;
 
     // convert the input to the correct type
     let xs = xs
@@ -359,7 +761,9 @@ pub fn msls_from_file(
 #[pyfunction]
 pub fn msls(problem: QuboData, xs: Vec<Vec<usize>>) -> PyResult<(Vec<Vec<usize>>, Vec<f64>)> {
     // read in the QUBO from file
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     // convert the input to the correct type
     let xs = xs
@@ -383,7 +787,8 @@ pub fn msls(problem: QuboData, xs: Vec<Vec<usize>>) -> PyResult<(Vec<Vec<usize>>
     Ok((x_solns_vec, objs))
 }
 
-/// This reads in the QUBO from a .qubo file
+/// This reads in the QUBO from a .qubo file, from a QPLIB instance if the file has the .qplib extension, or from the
+/// binary format if the file has the .qubob or .qubob.gz extension
 ///
 /// Example
 /// ``` python
@@ -391,31 +796,23 @@ pub fn msls(problem: QuboData, xs: Vec<Vec<usize>>) -> PyResult<(Vec<Vec<usize>>
 ///
 /// # read in the QUBO from a file
 /// p = hercules.read_qubo("file.qubo")
+///
+/// # read in a binary unconstrained QPLIB instance
+/// p = hercules.read_qubo("QPLIB_3506.qplib")
 /// ```
 /// # Errors
 ///
-/// if the file does not exist, then it will abort
+/// if the file does not exist or is malformed, then it will raise an exception
 #[pyfunction]
 pub fn read_qubo(filename: String) -> PyResult<QuboData> {
-    // read in the QUBO from file
-    let p = Qubo::read_qubo(filename.as_str());
-
-    let mut i_indexs = Vec::new();
-    let mut j_indexs = Vec::new();
-    let mut q_values = Vec::new();
-    let c_values = p.c.to_vec();
-    let num_x = p.num_x();
-
-    for (k, v) in &p.q {
-        i_indexs.push(v.0);
-        j_indexs.push(v.1);
-        q_values.push(*k);
-    }
+    // read in the QUBO from file, picking the reader from the file extension and reading unknown extensions as text
+    let p = read_qubo_file(filename.as_str())?;
 
-    Ok((i_indexs, j_indexs, q_values, c_values, num_x))
+    Ok(p.to_vec())
 }
 
-/// This reads in the QUBO from a .qubo file
+/// This reads in the QUBO from a .qubo file, from a QPLIB instance if the file has the .qplib extension, or from the
+/// binary format if the file has the .qubob or .qubob.gz extension
 ///
 /// Example
 /// ``` python
@@ -423,23 +820,101 @@ pub fn read_qubo(filename: String) -> PyResult<QuboData> {
 ///
 /// # read in the QUBO from a file
 /// p = hercules.read_qubo("file.qubo")
+///
+/// # read in a binary unconstrained QPLIB instance
+/// p = hercules.read_qubo("QPLIB_3506.qplib")
 /// ```
 /// # Errors
 ///
-/// if the location does not exist, then it will abort
+/// if the location can not be written to, then it will raise an exception
 #[pyfunction]
 pub fn write_qubo(problem: QuboData, filename: String) -> PyResult<()> {
     // read in the QUBO from file
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     // write the QUBO to file
-    Qubo::write_qubo(&p, filename.as_str());
+    p.try_write_qubo(filename.as_str())?;
     Ok(())
 }
 
+/// This function converts a QUBO file between the text and binary formats, the formats are picked from the file
+/// extensions (.qubo, .qplib, .qubob or .qubob.gz)
+///
+/// Example
+/// ``` python
+/// import hercules
+///
+/// # convert a large text QUBO into the compressed binary format, and back
+/// hercules.convert_qubo("test_large.qubo", "test_large.qubob.gz")
+/// hercules.convert_qubo("test_large.qubob.gz", "test_large_copy.qubo")
+/// ```
+/// # Errors
+///
+/// if the input can not be read or the output can not be written, then it will raise an exception
+#[pyfunction]
+pub fn convert_qubo(input: String, output: String) -> PyResult<()> {
+    convert_qubo_file(input.as_str(), output.as_str())?;
+    Ok(())
+}
+
+/// This function reads every instance of a Beasley OR-Library `bqp*.txt` file, and returns them in vec form as
+/// minimization problems
+///
+/// Example
+/// ``` python
+/// import hercules
+///
+/// # read in all of the QUBOs in the file
+/// problems = hercules.read_orlib("bqp50.txt")
+///
+/// # the original maximization objective is the negative of the objective
+/// x, obj = hercules.pso(problems[0], 0, 10, 100)
+/// ```
+/// # Errors
+///
+/// if the file does not exist or is malformed, then it will raise an exception
+#[pyfunction]
+pub fn read_orlib(filename: String) -> PyResult<Vec<QuboData>> {
+    // read in the QUBOs from file
+    let ps = Qubo::try_read_orlib(filename.as_str())?;
+
+    Ok(ps.iter().map(Qubo::to_vec).collect())
+}
+
+/// This function reads a Max-Cut graph in the Gset edge list format, and returns the QUBO that minimizes the negative
+/// cut weight in vec form
+///
+/// Example
+/// ``` python
+/// import hercules
+///
+/// # read in the Max-Cut QUBO from a file
+/// problem = hercules.read_gset("G1")
+///
+/// # the cut weight is the negative of the objective
+/// x, obj = hercules.pso(problem, 0, 10, 100)
+/// cut = -obj
+/// ```
+/// # Errors
+///
+/// if the file does not exist or is malformed, then it will raise an exception
+#[pyfunction]
+pub fn read_gset(filename: String) -> PyResult<QuboData> {
+    // read in the graph from file and build the QUBO
+    let p = Qubo::try_read_gset(filename.as_str())?;
+
+    Ok(p.to_vec())
+}
+
 /// This function computes the persistence of the QUBO, e.g. an initial set of variables that can be fixed
 /// to reduce the size of the problem.
 ///
+/// The first-order persistence is followed by roof duality, which gives the strong persistencies, the values the
+/// variables take in every optimal solution. If weak is true, then the weak persistencies of roof duality are
+/// returned instead, which fix more variables but only keep at least one optimal solution.
+///
 /// Example
 /// ``` python
 /// import hercules
@@ -449,6 +924,9 @@ pub fn write_qubo(problem: QuboData, filename: String) -> PyResult<()> {
 ///
 /// # compute the persistence
 /// fixed = hercules.get_persistence(problem, {})
+///
+/// # compute the weak persistence
+/// fixed = hercules.get_persistence(problem, {}, True)
 /// ```
 ///
 /// # Errors
@@ -458,13 +936,21 @@ pub fn write_qubo(problem: QuboData, filename: String) -> PyResult<()> {
 pub fn get_persistence(
     problem: QuboData,
     fixed: HashMap<usize, usize>,
+    weak: Option<bool>,
 ) -> PyResult<HashMap<usize, usize>> {
     // read in the QUBO from file
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
     let p_symm = p.make_symmetric();
     let new_fixed = compute_iterative_persistence(&p_symm, &fixed, p_symm.num_x());
 
-    Ok(new_fixed)
+    let roof = compute_roof_duality(&p_symm, &new_fixed);
+
+    match weak.unwrap_or(false) {
+        true => Ok(roof.weak),
+        false => Ok(roof.strong),
+    }
 }
 
 /// Solves the QUBO using branch and bound, returns the best solution found.
@@ -498,21 +984,33 @@ pub fn solve_branch_bound(
     verbose: Option<usize>,
 ) -> PyResult<(Vec<usize>, f64, f64, usize, usize)> {
     // read in the QUBO from file
-    let p_input = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p_input = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     let symm_p = p_input.make_symmetric();
 
     // run preprocessing on the symmetric QUBO
     let fixed_variables = preprocess_qubo(&symm_p, &HashMap::new());
 
-    let eigs = symm_p.hess_eigenvalues();
+    // remove the fixed variables, so that the solver only branches on the free variables
+    let (reduced_p, _, index_map) = symm_p.fix_variables(&fixed_variables);
+
+    // if everything is fixed by preprocessing, then there is nothing left to solve
+    if index_map.is_empty() {
+        let x = Qubo::expand_solution(&Array1::zeros(0), &fixed_variables, &index_map);
+        let obj = p_input.eval_usize(&x);
+        return Ok((x.to_vec(), obj, 0.0, 0, 0));
+    }
+
+    let eigs = reduced_p.hess_eigenvalues();
 
     // get the lowest eigenvalue
     let min_eig = eigs.iter().fold(f64::INFINITY, |a, &b| a.min(b));
 
     let p = match min_eig > 0.0 {
-        true => symm_p,
-        false => symm_p.make_diag_transform(min_eig.abs() + 1.0),
+        true => reduced_p,
+        false => reduced_p.make_diag_transform(min_eig.abs() + 1.0),
     };
 
     let mut options = SolverOptions::new();
@@ -529,25 +1027,35 @@ pub fn solve_branch_bound(
 
     options.max_time = timeout;
 
-    options.fixed_variables = fixed_variables;
-
     let mut solver = BBSolver::new(p, options);
 
-    // if we have a warm start, use it
-    if let Some(x) = warm_start {
-        solver.warm_start(Array1::<usize>::from(x));
+    // if we have a warm start, use it on the free variables
+    if let Some(x_0) = &warm_start {
+        solver.warm_start(index_map.iter().map(|&i| x_0[i]).collect());
     }
 
-    let (x, obj) = solver.solve();
+    solver.solve();
+
+    let result = solver.result();
 
-    let time_elapse = get_current_time() - solver.time_start;
+    let mut x = Qubo::expand_solution(&result.solution, &fixed_variables, &index_map);
+
+    // the warm start might not agree with the fixed variables, so keep it if it is still better
+    if let Some(x_0) = warm_start {
+        let x_0 = Array1::<usize>::from(x_0);
+        if p_input.eval_usize(&x_0) < p_input.eval_usize(&x) {
+            x = x_0;
+        }
+    }
+
+    let obj = p_input.eval_usize(&x);
 
     Ok((
         x.to_vec(),
         obj,
-        time_elapse,
-        solver.nodes_visited,
-        solver.nodes_processed,
+        result.time,
+        result.nodes_visited,
+        result.nodes_processed,
     ))
 }
 
@@ -570,7 +1078,9 @@ pub fn solve_branch_bound(
 #[pyfunction]
 pub fn convex_symmetric_form(problem: QuboData) -> PyResult<QuboData> {
     // read in the QUBO from file
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
     let symm_p = p.make_symmetric();
     let eigs = symm_p.hess_eigenvalues();
 
@@ -606,7 +1116,9 @@ pub fn convex_symmetric_form(problem: QuboData) -> PyResult<QuboData> {
 #[pyfunction]
 pub fn generate_rule_1_1(problem: QuboData) -> PyResult<Vec<(usize, usize)>> {
     // read in the QUBO from vec form
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     let persist = compute_iterative_persistence(&p, &HashMap::new(), p.num_x());
 
@@ -641,7 +1153,9 @@ pub fn generate_rule_1_1(problem: QuboData) -> PyResult<Vec<(usize, usize)>> {
 #[pyfunction]
 pub fn generate_rule_2_1(problem: QuboData) -> PyResult<Vec<(usize, usize)>> {
     // read in the QUBO from vec form
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
 
     let persist = compute_iterative_persistence(&p, &HashMap::new(), p.num_x());
 
@@ -660,6 +1174,10 @@ pub fn generate_rule_2_1(problem: QuboData) -> PyResult<Vec<(usize, usize)>> {
 /// This function solves the QUBO using the k-opt algorithm
 /// and returns the best solution found.
 ///
+/// Subsets of k variables (8 if not given) are grown by connectivity and solved exactly, until 100 subsets in a row do
+/// not improve or max_time seconds (100 if not given) have passed. Subsets that are too large to enumerate are solved
+/// with B&B on the given number of threads.
+///
 /// Example
 /// ``` python
 /// import hercules
@@ -667,8 +1185,8 @@ pub fn generate_rule_2_1(problem: QuboData) -> PyResult<Vec<(usize, usize)>> {
 /// # read in the QUBO from a file
 /// problem = hercules.read_qubo("file.qubo")
 ///
-/// # solve the QUBO using k-opt
-/// x, obj = hercules.k_opt(problem, {}, initial_guess = None)
+/// # solve the QUBO using k-opt over subsets of 10 variables
+/// x = hercules.k_opt(problem, {}, None, 10, 10.0, 0, 1)
 /// ```
 ///
 /// # Errors
@@ -678,12 +1196,26 @@ pub fn k_opt(
     problem: QuboData,
     fixed: HashMap<usize, usize>,
     initial_guess: Option<Vec<usize>>,
+    k: Option<usize>,
+    max_time: Option<f64>,
+    seed: Option<usize>,
+    threads: Option<usize>,
 ) -> PyResult<Vec<usize>> {
     // read in the QUBO from vec form
-    let p = Qubo::from_vec(problem.0, problem.1, problem.2, problem.3, problem.4);
+    let p = Qubo::from_vec_with_offset(
+        problem.0, problem.1, problem.2, problem.3, problem.4, problem.5,
+    );
     let persistent = fixed;
 
     let warm_start = initial_guess.map(Array1::<usize>::from);
 
-    Ok(kopt::solve_kopt(&p, &persistent, warm_start).to_vec())
+    // anything not given keeps the default k-opt options
+    let mut options = kopt::KOptOptions::new();
+    options.k = k.unwrap_or(options.k);
+    options.max_time = max_time.unwrap_or(options.max_time);
+    options.seed = seed.unwrap_or(0) as u64;
+    options.solver_options.seed = seed.unwrap_or(0);
+    options.solver_options.threads = threads.unwrap_or(1);
+
+    Ok(kopt::solve_kopt(&p, &persistent, warm_start, &options).to_vec())
 }
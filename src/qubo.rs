@@ -5,22 +5,61 @@
 use ndarray::Array1;
 use ndarray_linalg::{Eigh, UPLO};
 
+use crate::constraint_reduction::VariableMerge;
+use crate::io_error::{
+    end_of_file, io_error, parse_coefficient, parse_header, parse_index, parse_one_based_index,
+    read_data_lines, QuboIOError,
+};
+use crate::maxcut::MaxCutGraph;
+use crate::preprocess::get_fixed_c;
 use sprs::{CsMat, TriMat};
+use std::collections::HashMap;
 use std::io::BufRead;
 use std::io::Write;
 
 use smolprng::Algorithm;
 use smolprng::PRNG;
 
-/// The QUBO struct, which contains the QUBO matrix and the linear coefficients. With the following form:
+/// The QUBO struct, which contains the QUBO matrix, the linear coefficients and a constant offset. With the following form:
 ///
-/// $$ \min_{x\in \{0,1\}^n} 0.5 x^T Q x + c^Tx $$
+/// $$ \min_{x\in \{0,1\}^n} 0.5 x^T Q x + c^Tx + \text{offset} $$
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedQubo"))]
+#[allow(clippy::unsafe_derive_deserialize)] // the only unsafe code is mapping a file, which relies on no invariant of Qubo
 pub struct Qubo {
     /// The Hessian of the QUBO problem
     pub q: CsMat<f64>,
     /// The linear term of the QUBO problem
     pub c: Array1<f64>,
+    /// The constant term of the QUBO problem, this does not change the minimizer but keeps the objective in the original units
+    pub offset: f64,
+}
+
+/// The fields of a deserialized QUBO, before the shapes of the Hessian and the linear term are checked against each other
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedQubo {
+    q: CsMat<f64>,
+    c: Array1<f64>,
+    offset: f64,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedQubo> for Qubo {
+    type Error = String;
+
+    fn try_from(p: UncheckedQubo) -> Result<Self, Self::Error> {
+        let (rows, cols) = p.q.shape();
+        if rows != cols || p.c.len() != cols {
+            return Err(format!(
+                "the Hessian is {rows}x{cols} but there are {} linear coefficients",
+                p.c.len()
+            ));
+        }
+
+        Ok(Self::new_with_offset(p.q, p.c, p.offset))
+    }
 }
 
 impl Qubo {
@@ -39,6 +78,7 @@ impl Qubo {
         Self {
             q,
             c: Array1::<f64>::zeros(num_vars),
+            offset: 0.0,
         }
     }
 
@@ -55,7 +95,23 @@ impl Qubo {
     /// let p = Qubo::new_with_c(q, c);
     /// ```
     pub const fn new_with_c(q: CsMat<f64>, c: Array1<f64>) -> Self {
-        Self { q, c }
+        Self { q, c, offset: 0.0 }
+    }
+
+    /// Generate a new QUBO struct from a sparse matrix, a dense vector of linear coefficients and a constant offset
+    ///
+    /// Example to create a QUBO with a constant term:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    /// use sprs::CsMat;
+    /// use ndarray::Array1;
+    ///
+    /// let q = CsMat::<f64>::eye(10);
+    /// let c = Array1::<f64>::zeros(10);
+    /// let p = Qubo::new_with_offset(q, c, 2.5);
+    /// ```
+    pub const fn new_with_offset(q: CsMat<f64>, c: Array1<f64>, offset: f64) -> Self {
+        Self { q, c, offset }
     }
 
     /// Generate a QUBO struct from the list format
@@ -73,6 +129,31 @@ impl Qubo {
     /// let p = Qubo::from_vec(x, y, q, c, 3);
     /// ```
     pub fn from_vec(i: Vec<usize>, j: Vec<usize>, q: Vec<f64>, c: Vec<f64>, num_x: usize) -> Self {
+        Self::from_vec_with_offset(i, j, q, c, 0.0, num_x)
+    }
+
+    /// Generate a QUBO struct from the list format with a constant offset, this is the inverse of `to_vec`
+    ///
+    /// Example to create a QUBO from a list of tuples:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    ///
+    /// let x = vec![0,1,2];
+    /// let y = vec![0,1,2];
+    /// let q = vec![1.0,1.0,1.0];
+    /// let c = vec![0.0,0.0,0.0];
+    /// let p = Qubo::from_vec_with_offset(x, y, q, c, 1.5, 3);
+    ///
+    /// let (x, y, q, c, offset, num_x) = p.to_vec();
+    /// ```
+    pub fn from_vec_with_offset(
+        i: Vec<usize>,
+        j: Vec<usize>,
+        q: Vec<f64>,
+        c: Vec<f64>,
+        offset: f64,
+        num_x: usize,
+    ) -> Self {
         // set up the sparse matrix and dense vector
         let mut q_mat = TriMat::<f64>::new((num_x, num_x));
         let mut c_vec = Array1::<f64>::zeros(num_x);
@@ -87,10 +168,11 @@ impl Qubo {
             c_vec[k] = *v;
         }
 
-        Self::new_with_c(q_mat.to_csr(), c_vec)
+        Self::new_with_offset(q_mat.to_csr(), c_vec, offset)
     }
 
-    pub fn to_vec(&self) -> (Vec<usize>, Vec<usize>, Vec<f64>, Vec<f64>, usize) {
+    /// Converts the QUBO into the list format, (i, j, q_ij, c, offset, num_x)
+    pub fn to_vec(&self) -> (Vec<usize>, Vec<usize>, Vec<f64>, Vec<f64>, f64, usize) {
         let mut i = Vec::new();
         let mut j = Vec::new();
         let mut q = Vec::new();
@@ -106,7 +188,7 @@ impl Qubo {
             c.push(value);
         }
 
-        (i, j, q, c, self.num_x())
+        (i, j, q, c, self.offset, self.num_x())
     }
 
     /// Generate a random QUBO struct with a given number of variables, sparsity, and PRNG. This function is deterministic.
@@ -163,7 +245,7 @@ impl Qubo {
     /// ```
     pub fn eval(&self, x: &Array1<f64>) -> f64 {
         let temp = &self.q * x;
-        0.5 * x.dot(&temp) + self.c.dot(x)
+        0.5 * x.dot(&temp) + self.c.dot(x) + self.offset
     }
 
     pub fn eval_usize(&self, x: &Array1<usize>) -> f64 {
@@ -336,25 +418,388 @@ impl Qubo {
     ///
     /// Will panics if it is not possible to write to the file.
     pub fn write_qubo(&self, filename: &str) {
+        self.try_write_qubo(filename)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Writes the QUBO to a file in the ORL problem format, returning an error instead of panicking
+    ///
+    /// Example of writing a QUBO to a file:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    /// use smolprng::{PRNG, JsfLarge};
+    ///
+    /// let mut prng = PRNG {
+    ///   generator: JsfLarge::default(),
+    /// };
+    /// let p = Qubo::make_random_qubo(50, &mut prng, 0.01);
+    /// p.try_write_qubo("test.qubo").expect("could not write the QUBO");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `QuboIOError::Io` if the file can not be created or written to.
+    pub fn try_write_qubo(&self, filename: &str) -> Result<(), QuboIOError> {
         // open the file, create file writer
-        let file = std::fs::File::create(filename).unwrap();
+        let file = std::fs::File::create(filename).map_err(|err| io_error(filename, err))?;
         let mut writer = std::io::BufWriter::new(file);
 
-        // write the number of variables
-        writeln!(writer, "{}", self.num_x()).unwrap();
+        // write the number of variables, and the offset if there is one
+        if self.offset == 0.0 {
+            writeln!(writer, "{}", self.num_x()).map_err(|err| io_error(filename, err))?;
+        } else {
+            writeln!(writer, "{} {}", self.num_x(), self.offset)
+                .map_err(|err| io_error(filename, err))?;
+        }
 
         // for every nonzero Q_ij, write the indices and the value
         for (value, (i, j)) in &self.q {
-            writeln!(writer, "{i} {j} {value}").unwrap();
+            writeln!(writer, "{i} {j} {value}").map_err(|err| io_error(filename, err))?;
         }
 
         // for every nonzero c_i, write the index and the value
         for i in 0..self.num_x() {
             let value = self.c[i];
             if value != 0.0 {
-                writeln!(writer, "{i} {value}").unwrap();
+                writeln!(writer, "{i} {value}").map_err(|err| io_error(filename, err))?;
+            }
+        }
+
+        // make sure that everything has actually reached the file, as dropping the writer swallows errors
+        writer.flush().map_err(|err| io_error(filename, err))
+    }
+
+    /// Writes the QUBO as a binary quadratic program in the CPLEX LP format, to cross-check results with other MIQP
+    /// solvers
+    ///
+    /// The fixed variables, e.g. the `fixed_variables` of a `SolverOptions`, are written as fixed bounds and every other
+    /// variable is declared binary. The Hessian is written as `[ ... ] / 2`, which keeps the 0.5 scaling of the QUBO.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the file can not be written to.
+    pub fn write_lp(&self, filename: &str, fixed_variables: &HashMap<usize, usize>) {
+        self.try_write_lp(filename, fixed_variables)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Writes the QUBO in the CPLEX LP format, returning an error instead of panicking
+    ///
+    /// Example of writing a QUBO with a fixed variable:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    /// use sprs::CsMat;
+    /// use std::collections::HashMap;
+    ///
+    /// let p = Qubo::new(CsMat::<f64>::eye(3));
+    /// let fixed_variables = HashMap::from([(1, 1)]);
+    /// p.try_write_lp("test.lp", &fixed_variables).expect("could not write the LP file");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `QuboIOError::Io` if the file can not be created or written to.
+    pub fn try_write_lp(
+        &self,
+        filename: &str,
+        fixed_variables: &HashMap<usize, usize>,
+    ) -> Result<(), QuboIOError> {
+        let p_sym = self.make_symmetric();
+        let mut lines = vec![
+            "\\ QUBO written by hercules".to_string(),
+            "Minimize".to_string(),
+            " obj:".to_string(),
+        ];
+
+        // every variable is written in the linear part, so that all of them are declared
+        for i in 0..self.num_x() {
+            lines.push(format!(" {} x{i}", lp_coefficient(self.c[i])));
+        }
+
+        if self.offset != 0.0 {
+            lines.push(format!(" {}", lp_coefficient(self.offset)));
+        }
+
+        // the upper triangle of the symmetric Hessian, the off-diagonal terms are counted twice in x^TQx
+        if p_sym.q.nnz() > 0 {
+            lines.push(" + [".to_string());
+            for (&value, (i, j)) in &p_sym.q {
+                match i.cmp(&j) {
+                    std::cmp::Ordering::Equal => {
+                        lines.push(format!(" {} x{i} ^ 2", lp_coefficient(value)));
+                    }
+                    std::cmp::Ordering::Less => {
+                        lines.push(format!(" {} x{i} * x{j}", lp_coefficient(2.0 * value)));
+                    }
+                    std::cmp::Ordering::Greater => {}
+                }
+            }
+            lines.push(" ] / 2".to_string());
+        }
+
+        // there are no constraints, only bounds
+        lines.push("Subject To".to_string());
+        lines.push("Bounds".to_string());
+        for i in (0..self.num_x()).filter(|i| fixed_variables.contains_key(i)) {
+            lines.push(format!(" x{i} = {}", fixed_variables[&i]));
+        }
+
+        lines.push("Binary".to_string());
+        for i in (0..self.num_x()).filter(|i| !fixed_variables.contains_key(i)) {
+            lines.push(format!(" x{i}"));
+        }
+        lines.push("End".to_string());
+
+        // end the file with a newline
+        lines.push(String::new());
+        std::fs::write(filename, lines.join("\n")).map_err(|err| io_error(filename, err))
+    }
+
+    /// Writes the QUBO as a binary quadratic program in the free MPS format, with the Hessian in a QUADOBJ section
+    ///
+    /// The fixed variables, e.g. the `fixed_variables` of a `SolverOptions`, are written as `FX` bounds and every other
+    /// variable as a `BV` bound. The QUADOBJ section holds the upper triangle of the symmetric Hessian, which keeps the
+    /// 0.5 scaling of the QUBO, and the offset is written as the negated right hand side of the objective row.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the file can not be written to.
+    pub fn write_mps(&self, filename: &str, fixed_variables: &HashMap<usize, usize>) {
+        self.try_write_mps(filename, fixed_variables)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Writes the QUBO in the free MPS format, returning an error instead of panicking
+    ///
+    /// Example of writing a QUBO with a fixed variable:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    /// use sprs::CsMat;
+    /// use std::collections::HashMap;
+    ///
+    /// let p = Qubo::new(CsMat::<f64>::eye(3));
+    /// let fixed_variables = HashMap::from([(1, 1)]);
+    /// p.try_write_mps("test.mps", &fixed_variables).expect("could not write the MPS file");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `QuboIOError::Io` if the file can not be created or written to.
+    pub fn try_write_mps(
+        &self,
+        filename: &str,
+        fixed_variables: &HashMap<usize, usize>,
+    ) -> Result<(), QuboIOError> {
+        let p_sym = self.make_symmetric();
+        let mut lines = vec![
+            "NAME hercules".to_string(),
+            "ROWS".to_string(),
+            " N obj".to_string(),
+            "COLUMNS".to_string(),
+        ];
+
+        for i in 0..self.num_x() {
+            lines.push(format!(" x{i} obj {}", self.c[i]));
+        }
+
+        lines.push("RHS".to_string());
+        if self.offset != 0.0 {
+            lines.push(format!(" rhs obj {}", -self.offset));
+        }
+
+        lines.push("BOUNDS".to_string());
+        for i in 0..self.num_x() {
+            match fixed_variables.get(&i) {
+                Some(value) => lines.push(format!(" FX bnd x{i} {value}")),
+                None => lines.push(format!(" BV bnd x{i}")),
+            }
+        }
+
+        lines.push("QUADOBJ".to_string());
+        for (&value, (i, j)) in &p_sym.q {
+            if i <= j {
+                lines.push(format!(" x{i} x{j} {value}"));
             }
         }
+        lines.push("ENDATA".to_string());
+
+        // end the file with a newline
+        lines.push(String::new());
+        std::fs::write(filename, lines.join("\n")).map_err(|err| io_error(filename, err))
+    }
+
+    /// Reads a binary quadratic program in the free MPS format, as written by `write_mps`, and returns the QUBO and
+    /// the variables fixed by `FX` bounds
+    ///
+    /// Columns are numbered in the order they first appear. The objective may be given by a QUADOBJ section (one
+    /// triangle of a symmetric Hessian) or a QMATRIX section (the full Hessian).
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be read, if any line is malformed, or if the file has constraints or
+    /// ranges, which can not be represented in a QUBO.
+    #[allow(clippy::too_many_lines)]
+    pub fn try_read_mps(filename: &str) -> Result<(Self, HashMap<usize, usize>), QuboIOError> {
+        let file = std::fs::File::open(filename).map_err(|err| io_error(filename, err))?;
+        let reader = std::io::BufReader::new(file);
+
+        let mut section = String::new();
+        let mut objective_row = String::new();
+        let mut columns = HashMap::new();
+        let mut linear = Vec::new();
+        let mut quadratic = Vec::new();
+        let mut fixed_variables = HashMap::new();
+        let mut offset = 0.0;
+
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|err| io_error(filename, err))?;
+            let line_number = line_index + 1;
+            let row_data: Vec<_> = line.split_whitespace().collect();
+
+            // skip blank lines and comments
+            if row_data.is_empty() || row_data[0].starts_with('*') {
+                continue;
+            }
+
+            // section headers start in the first column
+            if !line.starts_with(char::is_whitespace) {
+                section = row_data[0].to_string();
+                match section.as_str() {
+                    "NAME" | "ROWS" | "COLUMNS" | "RHS" | "BOUNDS" | "QUADOBJ" | "QMATRIX" => {}
+                    "ENDATA" => break,
+                    _ => {
+                        return Err(QuboIOError::Unsupported {
+                            path: filename.to_string(),
+                            line: line_number,
+                            token: section,
+                            reason: "only unconstrained binary quadratic programs can be read",
+                        });
+                    }
+                }
+                continue;
+            }
+
+            let unsupported = |token: &str, reason| QuboIOError::Unsupported {
+                path: filename.to_string(),
+                line: line_number,
+                token: token.to_string(),
+                reason,
+            };
+            let wrong_column_count = |expected| QuboIOError::WrongColumnCount {
+                path: filename.to_string(),
+                line: line_number,
+                found: row_data.len(),
+                expected,
+            };
+
+            match section.as_str() {
+                "ROWS" => {
+                    if row_data.len() != 2 {
+                        return Err(wrong_column_count("2 (type name)"));
+                    }
+                    if row_data[0] != "N" || !objective_row.is_empty() {
+                        return Err(unsupported(
+                            row_data[1],
+                            "constraints can not be part of a QUBO",
+                        ));
+                    }
+                    objective_row = row_data[1].to_string();
+                }
+                "COLUMNS" => {
+                    // integrality markers carry no information, every variable is binary
+                    if row_data.get(1) == Some(&"'MARKER'") {
+                        continue;
+                    }
+                    if row_data.len() != 3 && row_data.len() != 5 {
+                        return Err(wrong_column_count("3 or 5 (column row value [row value])"));
+                    }
+
+                    let num_columns = columns.len();
+                    let i = *columns
+                        .entry(row_data[0].to_string())
+                        .or_insert(num_columns);
+
+                    for pair in row_data[1..].chunks(2) {
+                        if pair[0] != objective_row {
+                            return Err(unsupported(
+                                pair[0],
+                                "constraints can not be part of a QUBO",
+                            ));
+                        }
+                        linear.push((i, parse_coefficient(filename, line_number, pair[1])?));
+                    }
+                }
+                "RHS" => {
+                    if row_data.len() != 3 {
+                        return Err(wrong_column_count("3 (name row value)"));
+                    }
+                    if row_data[1] != objective_row {
+                        return Err(unsupported(
+                            row_data[1],
+                            "constraints can not be part of a QUBO",
+                        ));
+                    }
+                    offset = -parse_coefficient(filename, line_number, row_data[2])?;
+                }
+                "BOUNDS" => {
+                    if row_data.len() < 3 {
+                        return Err(wrong_column_count("3 or 4 (type name column [value])"));
+                    }
+                    let i = column_index(filename, line_number, &columns, row_data[2])?;
+
+                    match (row_data[0], row_data.get(3)) {
+                        ("BV", _) | ("UP", Some(&"1")) | ("LO", Some(&"0")) => {}
+                        ("FX", Some(&value)) => match value.parse::<f64>() {
+                            Ok(v) if v == 0.0 || v == 1.0 => {
+                                fixed_variables.insert(i, v as usize);
+                            }
+                            _ => {
+                                return Err(unsupported(
+                                    value,
+                                    "variables can only be fixed to 0 or 1",
+                                ))
+                            }
+                        },
+                        (bound, _) => {
+                            return Err(unsupported(bound, "every variable must be binary"))
+                        }
+                    }
+                }
+                "QUADOBJ" | "QMATRIX" => {
+                    if row_data.len() != 3 {
+                        return Err(wrong_column_count("3 (column column value)"));
+                    }
+                    let i = column_index(filename, line_number, &columns, row_data[0])?;
+                    let j = column_index(filename, line_number, &columns, row_data[1])?;
+                    let value = parse_coefficient(filename, line_number, row_data[2])?;
+
+                    quadratic.push((i, j, value));
+
+                    // QUADOBJ only lists one triangle of the symmetric Hessian
+                    if section == "QUADOBJ" && i != j {
+                        quadratic.push((j, i, value));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let num_x = columns.len();
+        let mut q = TriMat::<f64>::new((num_x, num_x));
+        let mut c = Array1::<f64>::zeros(num_x);
+
+        for (i, value) in linear {
+            c[i] += value;
+        }
+
+        for (i, j, value) in quadratic {
+            q.add_triplet(i, j, value);
+        }
+
+        Ok((
+            Self::new_with_offset(q.to_csr(), c, offset),
+            fixed_variables,
+        ))
     }
 
     /// Reads a QUBO from a file in the ORL problem format
@@ -371,44 +816,340 @@ impl Qubo {
     /// Will panic if there is not a file at the given filename in the .qubo format.
     #[must_use]
     pub fn read_qubo(filename: &str) -> Self {
+        Self::try_read_qubo(filename).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Reads a QUBO from a file in the ORL problem format, returning an error instead of panicking
+    ///
+    /// The first line is the number of variables, optionally followed by the constant offset, every following line is
+    /// either `i j q_ij` or `i c_i`. Blank lines are skipped.
+    ///
+    /// Example of reading a QUBO from a file:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    ///
+    /// match Qubo::try_read_qubo("test.qubo") {
+    ///     Ok(p) => println!("read a QUBO with {} variables", p.num_x()),
+    ///     Err(err) => println!("could not read the QUBO: {err}"),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be read, or if any line is malformed. The error reports the file,
+    /// the line number and the offending token.
+    pub fn try_read_qubo(filename: &str) -> Result<Self, QuboIOError> {
         // open the file
-        let file = std::fs::File::open(filename).unwrap();
-        let mut reader = std::io::BufReader::new(file);
+        let file = std::fs::File::open(filename).map_err(|err| io_error(filename, err))?;
+        let reader = std::io::BufReader::new(file);
+        let mut lines = reader.lines();
 
-        // get the number of variables
-        let mut line = String::new();
-        reader.read_line(&mut line).unwrap();
-        let num_x = line.trim().parse::<usize>().unwrap();
+        // get the number of variables and the offset
+        let header = match lines.next() {
+            Some(line) => line.map_err(|err| io_error(filename, err))?,
+            None => String::new(),
+        };
+        let (num_x, offset) = parse_header(filename, &header)?;
 
-        line = String::new();
         // set up the sparse matrix and dense vector
         let mut q = TriMat::<f64>::new((num_x, num_x));
         let mut c = Array1::<f64>::zeros(num_x);
 
-        // read the file
-        while reader.read_line(&mut line).unwrap() > 0 {
+        // read the file, the header is line 1
+        for (line_index, line) in lines.enumerate() {
+            let line = line.map_err(|err| io_error(filename, err))?;
+            let line_number = line_index + 2;
             let row_data: Vec<_> = line.split_whitespace().collect();
 
-            // we add to the column vector if there are only two elements
-            if row_data.len() == 2 {
-                let i = row_data[0].parse::<usize>().unwrap();
-                let value = row_data[1].parse::<f64>().unwrap();
-                c[i] = value;
+            match row_data.len() {
+                // skip blank lines
+                0 => {}
+                // we add to the column vector if there are only two elements
+                2 => {
+                    let i = parse_index(filename, line_number, row_data[0], num_x)?;
+                    let value = parse_coefficient(filename, line_number, row_data[1])?;
+                    c[i] = value;
+                }
+                // otherwise, we add to the sparse matrix
+                3 => {
+                    let i = parse_index(filename, line_number, row_data[0], num_x)?;
+                    let j = parse_index(filename, line_number, row_data[1], num_x)?;
+                    let value = parse_coefficient(filename, line_number, row_data[2])?;
+                    q.add_triplet(i, j, value);
+                }
+                found => {
+                    return Err(QuboIOError::WrongColumnCount {
+                        path: filename.to_string(),
+                        line: line_number,
+                        found,
+                        expected: "2 (i c_i) or 3 (i j q_ij)",
+                    });
+                }
             }
+        }
+
+        Ok(Self::new_with_offset(q.to_csr(), c, offset))
+    }
+
+    /// Reads every instance of a Beasley OR-Library `bqp*.txt` file
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is not a file at the given filename in the OR-Library format.
+    #[must_use]
+    pub fn read_orlib(filename: &str) -> Vec<Self> {
+        Self::try_read_orlib(filename).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Reads every instance of a Beasley OR-Library `bqp*.txt` file, returning an error instead of panicking
+    ///
+    /// The first line is the number of instances, and each instance starts with a line `n nnz`, the number of variables
+    /// and of non-zero entries, followed by `nnz` lines `i j q_ij` with 1-indexed variables. The instances maximize
+    /// $\sum_{i,j} q_{ij} x_i x_j$ over a symmetric matrix of which only one triangle is listed, so they are negated and
+    /// stored as minimization problems; an off-diagonal entry is used for both q_ij and q_ji and a diagonal entry becomes
+    /// a linear term. Blank lines are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be read, if any line is malformed, or if the file ends before every
+    /// declared instance and entry is read.
+    pub fn try_read_orlib(filename: &str) -> Result<Vec<Self>, QuboIOError> {
+        // read the file, skipping the blank lines while keeping track of the line numbers
+        let data_lines = read_data_lines(filename, &[])?;
+        let end_of_file = |expected| end_of_file(filename, &data_lines, expected);
+        let mut lines = data_lines.iter();
+
+        // get the number of instances
+        let (line_number, header) = lines
+            .next()
+            .ok_or_else(|| end_of_file("the number of instances"))?;
+        let num_instances = header
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| QuboIOError::BadHeader {
+                path: filename.to_string(),
+                line: *line_number,
+                token: header.trim().to_string(),
+                expected: "the number of instances",
+            })?;
+
+        let mut instances = Vec::with_capacity(num_instances);
+
+        for _ in 0..num_instances {
+            // get the number of variables and non-zero entries of this instance
+            let (line_number, header) = lines
+                .next()
+                .ok_or_else(|| end_of_file("the header of an instance"))?;
+            let header_data: Vec<_> = header.split_whitespace().map(str::parse::<usize>).collect();
+            let [Ok(num_x), Ok(num_entries)] = header_data[..] else {
+                return Err(QuboIOError::BadHeader {
+                    path: filename.to_string(),
+                    line: *line_number,
+                    token: header.trim().to_string(),
+                    expected: "the number of variables and the number of non-zero entries",
+                });
+            };
+
+            let mut q = TriMat::<f64>::new((num_x, num_x));
+            let mut c = Array1::<f64>::zeros(num_x);
+
+            for _ in 0..num_entries {
+                let (line_number, line) = lines
+                    .next()
+                    .ok_or_else(|| end_of_file("an entry i j q_ij"))?;
+                let row_data: Vec<_> = line.split_whitespace().collect();
+
+                if row_data.len() != 3 {
+                    return Err(QuboIOError::WrongColumnCount {
+                        path: filename.to_string(),
+                        line: *line_number,
+                        found: row_data.len(),
+                        expected: "3 (i j q_ij)",
+                    });
+                }
+
+                let i = parse_one_based_index(filename, *line_number, row_data[0], num_x)?;
+                let j = parse_one_based_index(filename, *line_number, row_data[1], num_x)?;
+                let value = parse_coefficient(filename, *line_number, row_data[2])?;
+
+                // flip the sign to go from maximization to minimization
+                if i == j {
+                    c[i] -= value;
+                } else {
+                    q.add_triplet(i, j, -2.0 * value);
+                    q.add_triplet(j, i, -2.0 * value);
+                }
+            }
+
+            instances.push(Self::new_with_c(q.to_csr(), c));
+        }
+
+        Ok(instances)
+    }
+
+    /// Reads a binary unconstrained instance from a file in the QPLIB format
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is not a file at the given filename in the QPLIB format, or if the instance is not a QUBO.
+    #[must_use]
+    pub fn read_qplib(filename: &str) -> Self {
+        Self::try_read_qplib(filename).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Reads a binary unconstrained instance from a file in the QPLIB format, returning an error instead of panicking
+    ///
+    /// Only the QUBO class of QPLIB is accepted, the problem type must have binary variables (`B` as the second letter)
+    /// and no constraints other than the variable bounds (`N` or `B` as the third letter). QPLIB stores the objective
+    /// as $0.5 x^TQx + b^Tx + q_0$ with the lower triangle of the symmetric Q listed with 1-indexed variables, which
+    /// maps directly onto the QUBO and its offset. Maximization instances are negated, so the objective of the QUBO is
+    /// the negative of the QPLIB objective. Anything after a `!` or `#` is a comment, and everything after the
+    /// objective constant, such as starting points and names, is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be read, if any line is malformed, if the file ends early, or an
+    /// `QuboIOError::Unsupported` if the instance has constraints or non-binary variables.
+    pub fn try_read_qplib(filename: &str) -> Result<Self, QuboIOError> {
+        let data_lines = read_data_lines(filename, &['!', '#'])?;
+        let mut lines = data_lines
+            .iter()
+            .map(|(line_number, line)| (*line_number, line.split_whitespace().collect::<Vec<_>>()));
+        let mut next_line = |expected| {
+            lines
+                .next()
+                .ok_or_else(|| end_of_file(filename, &data_lines, expected))
+        };
+        let bad_header = |line, token: &[&str], expected| QuboIOError::BadHeader {
+            path: filename.to_string(),
+            line,
+            token: token.join(" "),
+            expected,
+        };
+
+        // the name of the instance is not needed
+        next_line("the name of the instance")?;
+
+        // the problem type is three letters, the objective, the variables and the constraints
+        let (line_number, problem_type) = next_line("the problem type")?;
+        let type_letters: Vec<_> = problem_type[0].chars().collect();
+        if type_letters.len() != 3 {
+            return Err(bad_header(
+                line_number,
+                &problem_type,
+                "a three letter problem type",
+            ));
+        }
+        let unsupported = |reason| QuboIOError::Unsupported {
+            path: filename.to_string(),
+            line: line_number,
+            token: problem_type[0].to_string(),
+            reason,
+        };
+        if type_letters[1] != 'B' {
+            return Err(unsupported(
+                "only instances with binary variables are QUBOs",
+            ));
+        }
+        if type_letters[2] != 'N' && type_letters[2] != 'B' {
+            return Err(unsupported("constraints can not be part of a QUBO"));
+        }
+
+        // maximization problems are negated to give a minimization problem
+        let (line_number, sense) = next_line("the objective sense")?;
+        let sign = match sense[0].to_lowercase().as_str() {
+            "minimize" => 1.0,
+            "maximize" => -1.0,
+            _ => return Err(bad_header(line_number, &sense, "minimize or maximize")),
+        };
+
+        let (line_number, header) = next_line("the number of variables")?;
+        let num_x = header[0]
+            .parse::<usize>()
+            .map_err(|_| bad_header(line_number, &header, "the number of variables"))?;
+
+        let mut q = TriMat::<f64>::new((num_x, num_x));
 
-            // otherwise, we add to the sparse matrix
-            if row_data.len() == 3 {
-                let i = row_data[0].parse::<usize>().unwrap();
-                let j = row_data[1].parse::<usize>().unwrap();
-                let value = row_data[2].parse::<f64>().unwrap();
+        // a linear objective has no quadratic terms at all
+        if type_letters[0] != 'L' {
+            let (line_number, header) = next_line("the number of quadratic terms")?;
+            let num_terms = header[0]
+                .parse::<usize>()
+                .map_err(|_| bad_header(line_number, &header, "the number of quadratic terms"))?;
+
+            for _ in 0..num_terms {
+                let (line_number, row_data) = next_line("a quadratic term i j q_ij")?;
+                if row_data.len() != 3 {
+                    return Err(QuboIOError::WrongColumnCount {
+                        path: filename.to_string(),
+                        line: line_number,
+                        found: row_data.len(),
+                        expected: "3 (i j q_ij)",
+                    });
+                }
+
+                let i = parse_one_based_index(filename, line_number, row_data[0], num_x)?;
+                let j = parse_one_based_index(filename, line_number, row_data[1], num_x)?;
+                let value = sign * parse_coefficient(filename, line_number, row_data[2])?;
+
+                // only the lower triangle of the symmetric matrix is listed
                 q.add_triplet(i, j, value);
+                if i != j {
+                    q.add_triplet(j, i, value);
+                }
+            }
+        }
+
+        // the linear terms are given as a default value, and the entries that differ from it
+        let (line_number, row_data) = next_line("the default linear coefficient")?;
+        let default_c = sign * parse_coefficient(filename, line_number, row_data[0])?;
+        let mut c = Array1::<f64>::from_elem(num_x, default_c);
+
+        let (line_number, header) = next_line("the number of linear terms")?;
+        let num_terms = header[0]
+            .parse::<usize>()
+            .map_err(|_| bad_header(line_number, &header, "the number of linear terms"))?;
+
+        for _ in 0..num_terms {
+            let (line_number, row_data) = next_line("a linear term i b_i")?;
+            if row_data.len() != 2 {
+                return Err(QuboIOError::WrongColumnCount {
+                    path: filename.to_string(),
+                    line: line_number,
+                    found: row_data.len(),
+                    expected: "2 (i b_i)",
+                });
             }
 
-            // reset the line
-            line = String::new();
+            let i = parse_one_based_index(filename, line_number, row_data[0], num_x)?;
+            c[i] = sign * parse_coefficient(filename, line_number, row_data[1])?;
         }
 
-        Self::new_with_c(q.to_csr(), c)
+        let (line_number, row_data) = next_line("the objective constant")?;
+        let offset = sign * parse_coefficient(filename, line_number, row_data[0])?;
+
+        Ok(Self::new_with_offset(q.to_csr(), c, offset))
+    }
+
+    /// Reads a Max-Cut graph in the Gset edge list format, and returns the QUBO that minimizes the negative cut weight
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is not a file at the given filename in the Gset format.
+    #[must_use]
+    pub fn read_gset(filename: &str) -> Self {
+        Self::try_read_gset(filename).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Reads a Max-Cut graph in the Gset edge list format, returning an error instead of panicking
+    ///
+    /// See `MaxCutGraph::try_read_gset` for the format, and `MaxCutGraph::to_qubo` for the QUBO that is built.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be read, or if any line is malformed.
+    pub fn try_read_gset(filename: &str) -> Result<Self, QuboIOError> {
+        Ok(MaxCutGraph::try_read_gset(filename)?.to_qubo())
     }
 
     /// Generates a Symmetric QUBO from the current QUBO
@@ -444,7 +1185,7 @@ impl Qubo {
             }
         }
 
-        Self::new_with_c(tri_q.to_csr(), c)
+        Self::new_with_offset(tri_q.to_csr(), c, self.offset)
     }
 
     /// Convexifies the QUBO problem by modifying the Hessian and linear coefficients,rendering a convex problem.
@@ -461,7 +1202,7 @@ impl Qubo {
         }
         let s_eye = s_eye_tri.to_csr();
 
-        Self::new_with_c(&self.q + &s_eye, self.c.clone() - 0.5 * s)
+        Self::new_with_offset(&self.q + &s_eye, self.c.clone() - 0.5 * s, self.offset)
     }
 
     /// Calculates the eigenvalues of the QUBO Hessian matrix this is a somewhat expensive operation.
@@ -525,6 +1266,266 @@ impl Qubo {
 
         true
     }
+
+    /// Fixes variables to their values and removes them from the QUBO, returning the reduced QUBO, the constant picked
+    /// up from the fixed variables and an index map from the reduced variables to the original variables.
+    ///
+    /// The constant is also stored as the offset of the reduced QUBO, so that the objective of the reduced QUBO matches
+    /// the objective of the original QUBO at the expanded solution.
+    ///
+    /// Example of fixing variables and expanding a solution of the reduced problem:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    /// use ndarray::Array1;
+    /// use sprs::CsMat;
+    /// use std::collections::HashMap;
+    ///
+    /// let p = Qubo::new_with_c(CsMat::<f64>::eye(3), Array1::from_vec(vec![-1.0, 1.0, -1.0]));
+    /// let fixed_variables = HashMap::from([(1, 0)]);
+    ///
+    /// let (p_reduced, constant, index_map) = p.fix_variables(&fixed_variables);
+    /// assert_eq!(p_reduced.num_x(), 2);
+    /// assert_eq!(index_map, vec![0, 2]);
+    ///
+    /// let x_reduced = Array1::from_vec(vec![1, 1]);
+    /// let x = Qubo::expand_solution(&x_reduced, &fixed_variables, &index_map);
+    /// assert_eq!(p.eval_usize(&x), p_reduced.eval_usize(&x_reduced));
+    /// ```
+    pub fn fix_variables(
+        &self,
+        fixed_variables: &HashMap<usize, usize>,
+    ) -> (Self, f64, Vec<usize>) {
+        // the free variables, in the order they will appear in the reduced problem
+        let index_map: Vec<_> = (0..self.num_x())
+            .filter(|i| !fixed_variables.contains_key(i))
+            .collect();
+
+        // the inverse of the index map, for the free variables
+        let mut reduced_index = vec![None; self.num_x()];
+        for (k, &i) in index_map.iter().enumerate() {
+            reduced_index[i] = Some(k);
+        }
+
+        // fold the fixed variables into the linear and constant terms
+        let (full_c, constant) = get_fixed_c(self, fixed_variables);
+        let c = index_map
+            .iter()
+            .map(|&i| full_c[i])
+            .collect::<Array1<f64>>();
+
+        // only the free-free block of the Hessian survives
+        let mut q = TriMat::new((index_map.len(), index_map.len()));
+        for (&value, (i, j)) in &self.q {
+            if let (Some(k), Some(l)) = (reduced_index[i], reduced_index[j]) {
+                q.add_triplet(k, l, value);
+            }
+        }
+
+        (
+            Self::new_with_offset(q.to_csr(), c, constant),
+            constant,
+            index_map,
+        )
+    }
+
+    /// Rebuilds a full length solution from a solution of a reduced QUBO generated by `fix_variables`
+    pub fn expand_solution(
+        x_reduced: &Array1<usize>,
+        fixed_variables: &HashMap<usize, usize>,
+        index_map: &[usize],
+    ) -> Array1<usize> {
+        let mut x = Array1::zeros(x_reduced.len() + fixed_variables.len());
+
+        for (&i, &value) in fixed_variables {
+            x[i] = value;
+        }
+
+        for (k, &i) in index_map.iter().enumerate() {
+            x[i] = x_reduced[k];
+        }
+
+        x
+    }
+
+    /// Substitutes each merged variable with its representative, or the complement of its representative, returning the
+    /// smaller QUBO and the map that restores the merged variables.
+    ///
+    /// The rows and columns of Q and the linear term of each merged variable are folded into its representative, and
+    /// the constant picked up from the complements is stored as the offset, so that the objective of the reduced QUBO
+    /// matches the objective of the original QUBO at the expanded solution. Merges can be chained, such as x_j = x_i and
+    /// x_k = 1 - x_j. Returns None if the merges contradict each other, such as x_j = x_i and x_j = 1 - x_i.
+    ///
+    /// Example of merging x_1 = x_0 and x_2 = 1 - x_0:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    /// use hercules::constraint_reduction::VariableMerge;
+    /// use ndarray::Array1;
+    /// use sprs::CsMat;
+    ///
+    /// let p = Qubo::new_with_c(CsMat::<f64>::eye(4), Array1::from_vec(vec![-1.0, 1.0, -1.0, 2.0]));
+    /// let merges = [
+    ///     VariableMerge { representative: 0, variable: 1, complement: false },
+    ///     VariableMerge { representative: 0, variable: 2, complement: true },
+    /// ];
+    ///
+    /// let (p_reduced, aggregation) = p.aggregate_variables(&merges).unwrap();
+    /// assert_eq!(p_reduced.num_x(), 2);
+    ///
+    /// let x_reduced = Array1::from_vec(vec![1, 0]);
+    /// let x = aggregation.expand_solution(&x_reduced);
+    /// assert_eq!(x, Array1::from_vec(vec![1, 1, 0, 0]));
+    /// assert_eq!(p.eval_usize(&x), p_reduced.eval_usize(&x_reduced));
+    /// ```
+    pub fn aggregate_variables(&self, merges: &[VariableMerge]) -> Option<(Self, AggregationMap)> {
+        let num_x = self.num_x();
+
+        // union find over the variables, where each variable is its parent xor its parity
+        let mut parent: Vec<_> = (0..num_x).collect();
+        let mut parity = vec![false; num_x];
+
+        let find = |parent: &mut Vec<usize>, parity: &mut Vec<bool>, i: usize| {
+            // find the root and the parity of i relative to it
+            let mut root = i;
+            let mut root_parity = false;
+            while parent[root] != root {
+                root_parity ^= parity[root];
+                root = parent[root];
+            }
+
+            // point everything on the path directly to the root
+            let mut k = i;
+            let mut k_parity = root_parity;
+            while parent[k] != root && k != root {
+                let next = parent[k];
+                let next_parity = k_parity ^ parity[k];
+                parent[k] = root;
+                parity[k] = k_parity;
+                k = next;
+                k_parity = next_parity;
+            }
+
+            (root, root_parity)
+        };
+
+        for merge in merges {
+            let (root_i, parity_i) = find(&mut parent, &mut parity, merge.representative);
+            let (root_j, parity_j) = find(&mut parent, &mut parity, merge.variable);
+
+            if root_i == root_j {
+                // the variables are already merged, so the merge needs to agree
+                if parity_i ^ parity_j != merge.complement {
+                    return None;
+                }
+                continue;
+            }
+
+            parent[root_j] = root_i;
+            parity[root_j] = parity_i ^ parity_j ^ merge.complement;
+        }
+
+        // the roots are the variables of the reduced problem, in their original order
+        let index_map: Vec<_> = (0..num_x).filter(|&i| parent[i] == i).collect();
+        let mut reduced_index = vec![0; num_x];
+        for (k, &i) in index_map.iter().enumerate() {
+            reduced_index[i] = k;
+        }
+
+        let substitution: Vec<_> = (0..num_x)
+            .map(|i| {
+                let (root, complement) = find(&mut parent, &mut parity, i);
+                (reduced_index[root], complement)
+            })
+            .collect();
+
+        // x_i = a_i y_r + b_i, with a_i = -1 and b_i = 1 for the complements
+        let sign = |i: usize| match substitution[i].1 {
+            true => -1.0,
+            false => 1.0,
+        };
+        let shift = |i: usize| match substitution[i].1 {
+            true => 1.0,
+            false => 0.0,
+        };
+
+        let mut q = TriMat::new((index_map.len(), index_map.len()));
+        let mut c = Array1::zeros(index_map.len());
+        let mut constant = self.offset;
+
+        for (&value, (i, j)) in &self.q {
+            let (r_i, r_j) = (substitution[i].0, substitution[j].0);
+
+            q.add_triplet(r_i, r_j, sign(i) * sign(j) * value);
+            c[r_i] += 0.5 * sign(i) * value * shift(j);
+            c[r_j] += 0.5 * sign(j) * value * shift(i);
+            constant += 0.5 * value * shift(i) * shift(j);
+        }
+
+        for (i, &c_i) in self.c.iter().enumerate() {
+            c[substitution[i].0] += sign(i) * c_i;
+            constant += c_i * shift(i);
+        }
+
+        Some((
+            Self::new_with_offset(q.to_csr(), c, constant),
+            AggregationMap {
+                index_map,
+                substitution,
+            },
+        ))
+    }
+}
+
+/// The postsolve map of `Qubo::aggregate_variables`, which restores the merged variables
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AggregationMap {
+    /// The original variable of each variable of the reduced QUBO
+    pub index_map: Vec<usize>,
+    /// For each original variable, the variable of the reduced QUBO it is equal to, and if it is the complement of it
+    pub substitution: Vec<(usize, bool)>,
+}
+
+impl AggregationMap {
+    /// Rebuilds a full length solution from a solution of the reduced QUBO
+    pub fn expand_solution(&self, x_reduced: &Array1<usize>) -> Array1<usize> {
+        self.substitution
+            .iter()
+            .map(|&(k, complement)| match complement {
+                true => 1 - x_reduced[k],
+                false => x_reduced[k],
+            })
+            .collect()
+    }
+
+    /// Restricts a full length solution to the variables of the reduced QUBO, such as to warm start the reduced problem
+    pub fn reduce_solution(&self, x: &Array1<usize>) -> Array1<usize> {
+        self.index_map.iter().map(|&i| x[i]).collect()
+    }
+}
+
+/// Formats a coefficient with an explicit sign, as used in the LP format
+fn lp_coefficient(value: f64) -> String {
+    match value < 0.0 {
+        true => format!("- {}", -value),
+        false => format!("+ {value}"),
+    }
+}
+
+/// Looks up the index of a named MPS column
+fn column_index(
+    path: &str,
+    line: usize,
+    columns: &HashMap<String, usize>,
+    token: &str,
+) -> Result<usize, QuboIOError> {
+    columns
+        .get(token)
+        .copied()
+        .ok_or_else(|| QuboIOError::InvalidIndex {
+            path: path.to_string(),
+            line,
+            token: token.to_string(),
+        })
 }
 
 #[cfg(test)]
@@ -532,7 +1533,8 @@ mod tests {
 
     use super::*;
     use crate::initial_points::generate_random_binary_points;
-    use crate::tests::{make_solver_qubo, make_test_prng};
+    use crate::io_error::QuboIOError;
+    use crate::tests::{make_solver_qubo, make_test_prng, write_temp_file};
     use ndarray::Array1;
     use sprs::CsMat;
 
@@ -560,14 +1562,14 @@ mod tests {
     #[test]
     fn test_qubo_from_vec() {
         // Create a QUBO from a list of tuples:
-        let x = vec![0, 1, 2];
-        let y = vec![0, 1, 2];
+        let rows = vec![0, 1, 2];
+        let cols = vec![0, 1, 2];
         let q = vec![1.0, 1.0, 1.0];
         let c = vec![0.0, 0.0, 0.0];
         let num_x = 3;
 
         // actually create the QUBO
-        let p = Qubo::from_vec(x, y, q, c, num_x);
+        let p = Qubo::from_vec(rows, cols, q, c, num_x);
 
         // check that the QUBO was created correctly
         assert_eq!(p.num_x(), 3);
@@ -587,6 +1589,29 @@ mod tests {
         assert_eq!(obj, 1.0);
     }
 
+    #[test]
+    fn test_qubo_eval_with_offset() {
+        let q = CsMat::<f64>::eye(3);
+        let c = Array1::<f64>::zeros(3);
+        let p = Qubo::new_with_offset(q, c, -2.5);
+
+        assert_eq!(p.eval(&Array1::from_vec(vec![1.0, 0.0, 1.0])), -1.5);
+        assert_eq!(p.eval_usize(&Array1::zeros(3)), -2.5);
+    }
+
+    #[test]
+    fn test_qubo_vec_round_trip_offset() {
+        let mut p = make_solver_qubo();
+        p.offset = 4.25;
+
+        let (rows, cols, q, c, offset, num_x) = p.to_vec();
+        let p_round_trip = Qubo::from_vec_with_offset(rows, cols, q, c, offset, num_x);
+
+        assert_eq!(p_round_trip.offset, 4.25);
+        assert_eq!(p_round_trip.q, p.q);
+        assert_eq!(p_round_trip.c, p.c);
+    }
+
     #[test]
     fn test_qubo_eval_grad() {
         let q = CsMat::<f64>::eye(3);
@@ -601,14 +1626,14 @@ mod tests {
     #[test]
     fn test_qubo_eval_grad_non_symmetric() {
         // Create a QUBO from a list of tuples:
-        let x = vec![0];
-        let y = vec![2];
+        let rows = vec![0];
+        let cols = vec![2];
         let q = vec![1.0];
         let c = vec![0.0, 0.0, 0.0];
         let num_x = 3;
 
         // actually create the QUBO
-        let p = Qubo::from_vec(x, y, q, c, num_x);
+        let p = Qubo::from_vec(rows, cols, q, c, num_x);
         let x_0 = Array1::from_vec(vec![1.0, 1.0, 1.0]);
         let grad = p.eval_grad(&x_0);
 
@@ -629,12 +1654,12 @@ mod tests {
     #[test]
     fn test_make_symmetric_from_non_symmetric() {
         // Create a QUBO from a list of tuples:
-        let x = vec![0, 0];
-        let y = vec![0, 2];
+        let rows = vec![0, 0];
+        let cols = vec![0, 2];
         let q = vec![1.0, 1.0];
         let c = vec![0.0, 0.0, 0.0];
         let num_x = 3;
-        let p = Qubo::from_vec(x, y, q, c, num_x);
+        let p = Qubo::from_vec(rows, cols, q, c, num_x);
 
         // make a symmetric QUBO from this QUBO
         let p_sym = p.make_symmetric();
@@ -648,12 +1673,12 @@ mod tests {
     #[test]
     fn test_make_symmetric_from_non_symmetric_off_diagonal() {
         // Create a QUBO from a list of tuples:
-        let x = vec![0, 0, 2];
-        let y = vec![0, 2, 0];
+        let rows = vec![0, 0, 2];
+        let cols = vec![0, 2, 0];
         let q = vec![1.0, 1.5, 0.5];
         let c = vec![0.0, 0.0, 0.0];
         let num_x = 3;
-        let p = Qubo::from_vec(x, y, q, c, num_x);
+        let p = Qubo::from_vec(rows, cols, q, c, num_x);
 
         // make a symmetric QUBO from this QUBO
         let p_sym = p.make_symmetric();
@@ -698,6 +1723,253 @@ mod tests {
         assert_eq!(p.q.nnz(), q.q.nnz())
     }
 
+    /// reads a file that is expected to be malformed, and returns the error
+    fn read_error(path: &str) -> QuboIOError {
+        match Qubo::try_read_qubo(path) {
+            Ok(_) => panic!("expected {path} to fail to read"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn try_read_qubo_valid() {
+        let path = write_temp_file("hercules_valid.qubo", "3\n0 0 1.0\n0 2 -2.5\n\n1 0.5\n");
+        let p = Qubo::try_read_qubo(&path).unwrap();
+
+        assert_eq!(p.num_x(), 3);
+        assert_eq!(p.q.get(0, 2), Some(&-2.5));
+        assert_eq!(p.c, Array1::from_vec(vec![0.0, 0.5, 0.0]));
+    }
+
+    #[test]
+    fn try_read_qubo_offset() {
+        let path = write_temp_file(
+            "hercules_offset.qubo",
+            "2 -1.5
+0 1 1.0
+",
+        );
+        let p = Qubo::try_read_qubo(&path).unwrap();
+
+        assert_eq!(p.num_x(), 2);
+        assert_eq!(p.offset, -1.5);
+
+        // the offset survives being written back out
+        p.try_write_qubo(&path).unwrap();
+        let p_round_trip = Qubo::try_read_qubo(&path).unwrap();
+        assert_eq!(p_round_trip.offset, -1.5);
+    }
+
+    #[test]
+    fn try_read_qubo_missing_file() {
+        let err = read_error("this_file_does_not_exist.qubo");
+        assert!(matches!(err, QuboIOError::Io { .. }));
+        assert_eq!(err.line(), None);
+    }
+
+    #[test]
+    fn try_read_qubo_bad_header() {
+        let path = write_temp_file("hercules_bad_header.qubo", "three\n0 0 1.0\n");
+        let err = read_error(&path);
+
+        assert!(matches!(err, QuboIOError::BadHeader { ref token, .. } if token == "three"));
+        assert_eq!(err.line(), Some(1));
+        assert_eq!(err.path(), path);
+    }
+
+    #[test]
+    fn try_read_qubo_index_out_of_range() {
+        let path = write_temp_file("hercules_out_of_range.qubo", "3\n0 0 1.0\n0 3 1.0\n");
+        let err = read_error(&path);
+
+        assert!(
+            matches!(err, QuboIOError::IndexOutOfRange { ref token, num_x: 3, .. } if token == "3")
+        );
+        assert_eq!(err.line(), Some(3));
+    }
+
+    #[test]
+    fn try_read_qubo_invalid_float() {
+        let path = write_temp_file("hercules_invalid_float.qubo", "3\n0 0 1.0\n1 1.0.0\n");
+        let err = read_error(&path);
+
+        assert!(matches!(err, QuboIOError::InvalidFloat { ref token, .. } if token == "1.0.0"));
+        assert_eq!(err.line(), Some(3));
+    }
+
+    #[test]
+    fn try_read_qubo_wrong_column_count() {
+        let path = write_temp_file("hercules_wrong_columns.qubo", "3\n0 0 1.0 4.0\n");
+        let err = read_error(&path);
+
+        assert!(matches!(
+            err,
+            QuboIOError::WrongColumnCount { found: 4, .. }
+        ));
+        assert_eq!(err.line(), Some(2));
+    }
+
+    #[test]
+    fn try_read_qubo_non_finite() {
+        let path = write_temp_file("hercules_non_finite.qubo", "3\n0 0 NaN\n");
+        let err = read_error(&path);
+        assert!(matches!(err, QuboIOError::NonFiniteCoefficient { .. }));
+
+        let path = write_temp_file("hercules_infinite.qubo", "3\n2 inf\n");
+        let err = read_error(&path);
+        assert!(
+            matches!(err, QuboIOError::NonFiniteCoefficient { ref token, .. } if token == "inf")
+        );
+    }
+
+    #[test]
+    fn try_read_orlib_valid() {
+        let path = write_temp_file(
+            "hercules_valid_bqp.txt",
+            "2\n3 4\n1 1 2\n1 2 -3\n2 3 4\n3 3 -1\n\n2 1\n1 2 5\n",
+        );
+        let ps = Qubo::try_read_orlib(&path).unwrap();
+
+        assert_eq!(ps.len(), 2);
+        assert_eq!(ps[0].num_x(), 3);
+        assert_eq!(ps[1].num_x(), 2);
+
+        // the first instance maximizes 2 x_1 - 6 x_1 x_2 + 8 x_2 x_3 - x_3, check every point
+        for k in 0..8usize {
+            let x = Array1::from_shape_fn(3, |i| (k >> i) & 1);
+            let x_f = x.mapv(|x_i| x_i as f64);
+            let f = 2.0 * x_f[0] - 6.0 * x_f[0] * x_f[1] + 8.0 * x_f[1] * x_f[2] - x_f[2];
+            assert!((ps[0].eval_usize(&x) + f).abs() < 1E-10);
+        }
+
+        // the second instance maximizes 10 x_1 x_2
+        assert!((ps[1].eval_usize(&Array1::ones(2)) + 10.0).abs() < 1E-10);
+    }
+
+    #[test]
+    fn try_read_orlib_truncated() {
+        let path = write_temp_file("hercules_truncated_bqp.txt", "1\n3 2\n1 2 1\n");
+        let err = Qubo::try_read_orlib(&path).map(|_| ()).unwrap_err();
+
+        assert!(matches!(
+            err,
+            QuboIOError::UnexpectedEndOfFile { line: 4, .. }
+        ));
+    }
+
+    #[test]
+    fn try_read_orlib_zero_index() {
+        let path = write_temp_file("hercules_zero_index_bqp.txt", "1\n3 1\n0 2 1\n");
+        let err = Qubo::try_read_orlib(&path).map(|_| ()).unwrap_err();
+
+        assert!(matches!(err, QuboIOError::IndexOutOfRange { line: 3, .. }));
+    }
+
+    #[test]
+    fn mps_round_trip() {
+        let mut p = make_solver_qubo();
+        p.offset = -3.25;
+        let fixed_variables = HashMap::from([(2, 1), (11, 0)]);
+
+        let path = std::env::temp_dir().join("hercules_round_trip.mps");
+        let path = path.to_str().unwrap();
+        p.try_write_mps(path, &fixed_variables).unwrap();
+        let (p_read, fixed_read) = Qubo::try_read_mps(path).unwrap();
+
+        assert_eq!(p_read.num_x(), p.num_x());
+        assert_eq!(fixed_read, fixed_variables);
+
+        let mut prng = make_test_prng();
+        let xs = generate_random_binary_points(p.num_x(), 50, &mut prng);
+
+        for x in &xs {
+            assert!((p.eval_usize(x) - p_read.eval_usize(x)).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn try_read_mps_constraint() {
+        let path = write_temp_file(
+            "hercules_constraint.mps",
+            "NAME test\nROWS\n N obj\n L c1\nCOLUMNS\n x0 obj 1\nENDATA\n",
+        );
+        let err = Qubo::try_read_mps(&path).map(|_| ()).unwrap_err();
+
+        assert!(matches!(err, QuboIOError::Unsupported { line: 4, .. }));
+    }
+
+    #[test]
+    fn try_write_lp_format() {
+        let p = Qubo::from_vec_with_offset(
+            vec![0, 0, 1],
+            vec![0, 1, 0],
+            vec![2.0, 1.0, 3.0],
+            vec![-1.0, 0.5],
+            1.5,
+            2,
+        );
+        let fixed_variables = HashMap::from([(1, 0)]);
+
+        let path = std::env::temp_dir().join("hercules_format.lp");
+        let path = path.to_str().unwrap();
+        p.try_write_lp(path, &fixed_variables).unwrap();
+        let lp = std::fs::read_to_string(path).unwrap();
+
+        // the off-diagonal terms are averaged and then counted twice, inside of the halved bracket
+        assert!(lp.contains(" - 1 x0\n + 0.5 x1\n + 1.5\n"));
+        assert!(lp.contains(" + [\n + 2 x0 ^ 2\n + 4 x0 * x1\n ] / 2\n"));
+        assert!(lp.contains("Bounds\n x1 = 0\nBinary\n x0\nEnd\n"));
+    }
+
+    #[test]
+    fn try_read_qplib_valid() {
+        let contents =
+            "test ! name\nQBN\nminimize\n3\n3 ! quadratic terms\n1 1 2.0\n2 1 -1.0\n3 2 4.0\n\
+                        0.5 ! default linear\n1\n3 -1.0\n1.5 ! constant\n1.0E+30 ! infinity\n";
+        let path = write_temp_file("hercules_valid.qplib", contents);
+        let p = Qubo::try_read_qplib(&path).unwrap();
+
+        // x_1 - x_1 x_2 + 4 x_2 x_3 + 0.5 x_1 + 0.5 x_2 - x_3 + 1.5, check every point
+        for k in 0..8usize {
+            let x = Array1::from_shape_fn(3, |i| (k >> i) & 1);
+            let x_f = x.mapv(|x_i| x_i as f64);
+            let f = 1.5 * x_f[0] - x_f[0] * x_f[1] + 4.0 * x_f[1] * x_f[2] + 0.5 * x_f[1] - x_f[2]
+                + 1.5;
+            assert!((p.eval_usize(&x) - f).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn try_read_qplib_maximize() {
+        let contents = "test\nQBN\nmaximize\n2\n1\n2 1 3.0\n0\n1\n1 1.0\n2.0\n";
+        let path = write_temp_file("hercules_maximize.qplib", contents);
+        let p = Qubo::try_read_qplib(&path).unwrap();
+
+        // maximizes 3 x_1 x_2 + x_1 + 2, so the QUBO is the negative
+        assert!((p.eval_usize(&Array1::ones(2)) + 6.0).abs() < 1E-10);
+        assert!((p.eval_usize(&Array1::zeros(2)) + 2.0).abs() < 1E-10);
+    }
+
+    #[test]
+    fn try_read_qplib_unsupported() {
+        let path = write_temp_file("hercules_continuous.qplib", "test\nQCN\nminimize\n2\n");
+        let err = Qubo::try_read_qplib(&path).map(|_| ()).unwrap_err();
+        assert!(matches!(err, QuboIOError::Unsupported { line: 2, .. }));
+
+        let path = write_temp_file("hercules_constrained.qplib", "test\nQBL\nminimize\n2\n");
+        let err = Qubo::try_read_qplib(&path).map(|_| ()).unwrap_err();
+        assert!(matches!(err, QuboIOError::Unsupported { line: 2, .. }));
+    }
+
+    #[test]
+    fn try_write_qubo_bad_location() {
+        let p = make_solver_qubo();
+        let err = p
+            .try_write_qubo("this_directory_does_not_exist/test.qubo")
+            .unwrap_err();
+        assert!(matches!(err, QuboIOError::Io { .. }));
+    }
+
     #[test]
     fn test_is_symmetric_on_symmetric() {
         let q = CsMat::<f64>::eye(3);
@@ -709,12 +1981,12 @@ mod tests {
     #[test]
     fn test_is_symmetric_on_not_symmetric() {
         // Create a QUBO from a list of tuples:
-        let x = vec![0, 0];
-        let y = vec![0, 2];
+        let rows = vec![0, 0];
+        let cols = vec![0, 2];
         let q = vec![1.0, 1.0];
         let c = vec![0.0, 0.0, 0.0];
         let num_x = 3;
-        let p = Qubo::from_vec(x, y, q, c, num_x);
+        let p = Qubo::from_vec(rows, cols, q, c, num_x);
 
         // make a symmetric QUBO from this QUBO
         let p_sym = p.make_symmetric();
@@ -737,7 +2009,8 @@ mod tests {
 
     #[test]
     fn test_convex_symetric_form() {
-        let p = make_solver_qubo();
+        let mut p = make_solver_qubo();
+        p.offset = 1.5;
         let p_convex = p.convex_symmetric_form();
 
         let eigs = p_convex.hess_eigenvalues();
@@ -762,4 +2035,151 @@ mod tests {
             assert!((obj - obj_convex).abs() < 1e-5);
         }
     }
+
+    #[test]
+    fn test_fix_variables() {
+        let mut p = make_solver_qubo();
+        p.offset = -1.0;
+
+        let fixed_variables = HashMap::from([(0, 1), (5, 0), (17, 1), (49, 1)]);
+        let (p_reduced, constant, index_map) = p.fix_variables(&fixed_variables);
+
+        assert_eq!(p_reduced.num_x(), p.num_x() - fixed_variables.len());
+        assert_eq!(index_map.len(), p_reduced.num_x());
+        assert_eq!(p_reduced.offset, constant);
+
+        // the reduced objective must match the full objective at the expanded point
+        let mut prng = make_test_prng();
+        let xs = generate_random_binary_points(p_reduced.num_x(), 50, &mut prng);
+
+        for x_reduced in &xs {
+            let x = Qubo::expand_solution(x_reduced, &fixed_variables, &index_map);
+
+            for (&i, &value) in &fixed_variables {
+                assert_eq!(x[i], value);
+            }
+            assert!((p.eval_usize(&x) - p_reduced.eval_usize(x_reduced)).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn test_aggregate_variables() {
+        let mut prng = make_test_prng();
+        let mut p = Qubo::make_random_qubo(8, &mut prng, 0.6);
+        p.offset = 0.5;
+
+        // chained merges, x_3 = x_0, x_5 = 1 - x_1 and x_6 = 1 - x_3 = 1 - x_0
+        let merges = [
+            VariableMerge {
+                representative: 0,
+                variable: 3,
+                complement: false,
+            },
+            VariableMerge {
+                representative: 1,
+                variable: 5,
+                complement: true,
+            },
+            VariableMerge {
+                representative: 3,
+                variable: 6,
+                complement: true,
+            },
+        ];
+
+        let (p_reduced, aggregation) = p.aggregate_variables(&merges).unwrap();
+        assert_eq!(p_reduced.num_x(), 5);
+        assert_eq!(aggregation.index_map, vec![0, 1, 2, 4, 7]);
+
+        // the reduced objective must match the full objective at every expanded point
+        for mask in 0..1usize << p_reduced.num_x() {
+            let x_reduced = Array1::from_shape_fn(p_reduced.num_x(), |k| (mask >> k) & 1);
+            let x = aggregation.expand_solution(&x_reduced);
+
+            assert_eq!(x[3], x[0]);
+            assert_eq!(x[5], 1 - x[1]);
+            assert_eq!(x[6], 1 - x[0]);
+            assert_eq!(aggregation.reduce_solution(&x), x_reduced);
+            assert!((p.eval_usize(&x) - p_reduced.eval_usize(&x_reduced)).abs() < 1E-10);
+        }
+    }
+
+    #[test]
+    fn test_aggregate_contradicting_variables() {
+        let p = make_solver_qubo();
+
+        // x_1 = x_0, x_2 = x_1 and x_2 = 1 - x_0 can not all hold
+        let merges = [
+            VariableMerge {
+                representative: 0,
+                variable: 1,
+                complement: false,
+            },
+            VariableMerge {
+                representative: 1,
+                variable: 2,
+                complement: false,
+            },
+            VariableMerge {
+                representative: 0,
+                variable: 2,
+                complement: true,
+            },
+        ];
+
+        assert!(p.aggregate_variables(&merges).is_none());
+        assert!(p.aggregate_variables(&merges[..2]).is_some());
+
+        // without any merges, the QUBO is unchanged
+        let (p_reduced, aggregation) = p.aggregate_variables(&[]).unwrap();
+        let x = Array1::from_shape_fn(p.num_x(), |i| i % 2);
+        assert_eq!(p_reduced.num_x(), p.num_x());
+        assert_eq!(aggregation.expand_solution(&x), x);
+        assert!((p.eval_usize(&x) - p_reduced.eval_usize(&x)).abs() < 1E-10);
+    }
+
+    #[test]
+    fn test_fix_all_variables() {
+        let p = make_solver_qubo();
+        let x = Array1::from_shape_fn(p.num_x(), |i| i % 2);
+        let fixed_variables: HashMap<_, _> = x.iter().copied().enumerate().collect();
+
+        let (p_reduced, constant, index_map) = p.fix_variables(&fixed_variables);
+
+        assert_eq!(p_reduced.num_x(), 0);
+        assert!(index_map.is_empty());
+        assert!((constant - p.eval_usize(&x)).abs() < 1E-10);
+        assert_eq!(
+            Qubo::expand_solution(&Array1::zeros(0), &fixed_variables, &index_map),
+            x
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_qubo_serde_round_trip() {
+        let mut p = make_solver_qubo();
+        p.offset = 2.5;
+
+        let json = serde_json::to_string(&p).unwrap();
+        let p_json: Qubo = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(p_json.q, p.q);
+        assert_eq!(p_json.c, p.c);
+        assert_eq!(p_json.offset, p.offset);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_qubo_serde_rejects_mismatched_shapes() {
+        let p = make_solver_qubo();
+
+        let mut json = serde_json::to_value(&p).unwrap();
+        json["c"] = serde_json::to_value(Array1::<f64>::zeros(p.num_x() + 1)).unwrap();
+
+        let err = serde_json::from_value::<Qubo>(json)
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.to_string().contains("linear coefficients"));
+    }
 }
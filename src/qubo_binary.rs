@@ -0,0 +1,567 @@
+//! This module contains the compact binary format for large QUBOs
+//!
+//! Parsing the text formats one triplet at a time dominates the load time of problems with millions of nonzeros, so
+//! the binary format stores the raw CSR arrays of the Hessian and the dense linear term instead. A file is a fixed
+//! size header followed by the payload, all values are little endian:
+//!
+//! | bytes | contents |
+//! |-------|----------|
+//! | 8 | magic number, `HRCLQUBO` |
+//! | 4 | format version, u32 |
+//! | 4 | flags, u32, bit 0 is set if the payload is gzip compressed |
+//! | 8 | number of variables, u64 |
+//! | 8 | number of nonzeros in the Hessian, u64 |
+//! | 8 | offset, f64 |
+//! | 4 | CRC32 checksum of the uncompressed payload, u32 |
+//! | 4 | reserved, zero |
+//!
+//! The payload is the CSR row pointers (num_x + 1 u64), the column indices (nnz u64), the values (nnz f64) and the
+//! linear term (num_x f64).
+
+use crate::io_error::{io_error, QuboIOError};
+use crate::qubo::Qubo;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ndarray::Array1;
+use sprs::CsMat;
+use std::ffi::OsStr;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// The magic number at the start of every binary QUBO file
+const MAGIC: &[u8; 8] = b"HRCLQUBO";
+
+/// The current version of the binary format
+const VERSION: u32 = 1;
+
+/// The flag marking a gzip compressed payload
+const FLAG_GZIP: u32 = 1;
+
+/// The size of the header in bytes
+const HEADER_SIZE: usize = 48;
+
+/// The header of a binary QUBO file
+struct BinaryHeader {
+    compressed: bool,
+    num_x: usize,
+    nnz: usize,
+    offset: f64,
+    checksum: u32,
+    /// The size of the uncompressed payload in bytes
+    payload_size: usize,
+}
+
+/// The size of the payload in bytes, or None if it would not fit in memory
+fn payload_size(num_x: usize, nnz: usize) -> Option<usize> {
+    num_x
+        .checked_add(nnz)?
+        .checked_mul(2)?
+        .checked_add(1)?
+        .checked_mul(8)
+}
+
+impl BinaryHeader {
+    fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let flags = if self.compressed { FLAG_GZIP } else { 0 };
+
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[0..8].copy_from_slice(MAGIC);
+        bytes[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[12..16].copy_from_slice(&flags.to_le_bytes());
+        bytes[16..24].copy_from_slice(&(self.num_x as u64).to_le_bytes());
+        bytes[24..32].copy_from_slice(&(self.nnz as u64).to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.offset.to_le_bytes());
+        bytes[40..44].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(path: &str, bytes: &[u8]) -> Result<Self, QuboIOError> {
+        let invalid = |reason: String| QuboIOError::InvalidBinary {
+            path: path.to_string(),
+            reason,
+        };
+
+        if bytes.len() < HEADER_SIZE {
+            return Err(invalid(format!(
+                "the file is shorter than the {HEADER_SIZE} byte header"
+            )));
+        }
+
+        if &bytes[0..8] != MAGIC {
+            return Err(invalid("the magic number does not match".to_string()));
+        }
+
+        let version = read_u32(&bytes[8..12]);
+        if version != VERSION {
+            return Err(invalid(format!(
+                "version {version} is not supported, expected version {VERSION}"
+            )));
+        }
+
+        let flags = read_u32(&bytes[12..16]);
+        let num_x = usize::try_from(read_u64(&bytes[16..24]))
+            .map_err(|_| invalid("the number of variables does not fit in memory".to_string()))?;
+        let nnz = usize::try_from(read_u64(&bytes[24..32]))
+            .map_err(|_| invalid("the number of nonzeros does not fit in memory".to_string()))?;
+
+        let payload_size = payload_size(num_x, nnz)
+            .ok_or_else(|| invalid("the payload does not fit in memory".to_string()))?;
+
+        Ok(Self {
+            compressed: flags & FLAG_GZIP != 0,
+            num_x,
+            nnz,
+            offset: read_f64(&bytes[32..40]),
+            checksum: read_u32(&bytes[40..44]),
+            payload_size,
+        })
+    }
+}
+
+/// A writer that only computes the checksum of everything written to it
+struct ChecksumWriter(crc32fast::Hasher);
+
+impl Write for ChecksumWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Qubo {
+    /// Writes the QUBO to a file in the binary format, optionally gzip compressing the payload
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the file can not be written to.
+    pub fn write_binary(&self, filename: &str, compress: bool) {
+        self.try_write_binary(filename, compress)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Writes the QUBO to a file in the binary format, returning an error instead of panicking
+    ///
+    /// Example of writing and reading back a compressed QUBO:
+    /// ```rust
+    /// use hercules::qubo::Qubo;
+    /// use smolprng::{PRNG, JsfLarge};
+    ///
+    /// let mut prng = PRNG {
+    ///   generator: JsfLarge::default(),
+    /// };
+    /// let p = Qubo::make_random_qubo(50, &mut prng, 0.1);
+    /// p.try_write_binary("test.qubob.gz", true).expect("could not write the QUBO");
+    ///
+    /// let p_read = Qubo::try_read_binary("test.qubob.gz").expect("could not read the QUBO");
+    /// assert_eq!(p.q, p_read.q);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `QuboIOError::Io` if the file can not be created or written to.
+    pub fn try_write_binary(&self, filename: &str, compress: bool) -> Result<(), QuboIOError> {
+        // the payload is the raw CSR arrays, so a CSC Hessian has to be converted first
+        let q_csr;
+        let q = if self.q.is_csr() {
+            &self.q
+        } else {
+            q_csr = self.q.to_csr();
+            &q_csr
+        };
+
+        // the checksum goes in the header, so it is computed in a first pass over the payload
+        let mut checksum_writer = ChecksumWriter(crc32fast::Hasher::new());
+        write_payload(&mut checksum_writer, q, &self.c).map_err(|err| io_error(filename, err))?;
+
+        let header = BinaryHeader {
+            compressed: compress,
+            num_x: self.num_x(),
+            nnz: q.nnz(),
+            offset: self.offset,
+            checksum: checksum_writer.0.finalize(),
+            // the arrays are already in memory, so their total size can not overflow
+            payload_size: 8 * (2 * (self.num_x() + q.nnz()) + 1),
+        };
+
+        let file = std::fs::File::create(filename).map_err(|err| io_error(filename, err))?;
+        let mut writer = std::io::BufWriter::new(file);
+        writer
+            .write_all(&header.to_bytes())
+            .map_err(|err| io_error(filename, err))?;
+
+        if compress {
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+            write_payload(&mut encoder, q, &self.c).map_err(|err| io_error(filename, err))?;
+            writer = encoder.finish().map_err(|err| io_error(filename, err))?;
+        } else {
+            write_payload(&mut writer, q, &self.c).map_err(|err| io_error(filename, err))?;
+        }
+
+        writer.flush().map_err(|err| io_error(filename, err))
+    }
+
+    /// Reads a QUBO from a file in the binary format
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is not a valid binary QUBO file at the given filename.
+    #[must_use]
+    pub fn read_binary(filename: &str) -> Self {
+        Self::try_read_binary(filename).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Reads a QUBO from a file in the binary format, returning an error instead of panicking
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be read, if the header is invalid, if the file is truncated, or if
+    /// the checksum does not match the contents.
+    pub fn try_read_binary(filename: &str) -> Result<Self, QuboIOError> {
+        let file = std::fs::File::open(filename).map_err(|err| io_error(filename, err))?;
+        let mut reader = std::io::BufReader::new(file);
+
+        let mut header_bytes = [0u8; HEADER_SIZE];
+        reader
+            .read_exact(&mut header_bytes)
+            .map_err(|_| truncated(filename))?;
+        let header = BinaryHeader::from_bytes(filename, &header_bytes)?;
+
+        // read exactly the payload, anything shorter is a truncated file
+        let mut payload = Vec::new();
+        let read = if header.compressed {
+            GzDecoder::new(reader)
+                .take(header.payload_size as u64)
+                .read_to_end(&mut payload)
+        } else {
+            reader
+                .take(header.payload_size as u64)
+                .read_to_end(&mut payload)
+        };
+        read.map_err(|err| io_error(filename, err))?;
+
+        decode_payload(filename, &header, &payload)
+    }
+
+    /// Reads a QUBO from a file in the binary format by memory mapping it, which avoids copying an uncompressed file
+    /// through a read buffer
+    ///
+    /// A compressed file is decompressed straight from the mapped memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `QuboIOError` if the file can not be mapped, if the header is invalid, if the file is truncated, or if
+    /// the checksum does not match the contents.
+    pub fn try_read_binary_mmap(filename: &str) -> Result<Self, QuboIOError> {
+        let file = std::fs::File::open(filename).map_err(|err| io_error(filename, err))?;
+
+        // SAFETY: the map is only read from, and is dropped before this function returns. The file being modified by
+        // another process while it is mapped is not something we can guard against, same as for any other reader.
+        let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(|err| io_error(filename, err))?;
+
+        let header = BinaryHeader::from_bytes(filename, &mmap)?;
+        let body = &mmap[HEADER_SIZE..];
+
+        if header.compressed {
+            let mut payload = Vec::new();
+            GzDecoder::new(body)
+                .take(header.payload_size as u64)
+                .read_to_end(&mut payload)
+                .map_err(|err| io_error(filename, err))?;
+            decode_payload(filename, &header, &payload)
+        } else {
+            decode_payload(filename, &header, body)
+        }
+    }
+}
+
+/// Converts a QUBO file between formats, the format of each file is picked from its extension
+///
+/// The supported extensions are `.qubo` for the text format, `.qplib` (input only), `.qubob` for the binary format and
+/// `.qubob.gz` for the compressed binary format. An input file with any other extension is read as text.
+///
+/// Example of converting a text QUBO to the binary format and back:
+/// ```rust
+/// use hercules::qubo_binary::convert_qubo_file;
+///
+/// convert_qubo_file("test.qubo", "test_convert.qubob").expect("could not convert");
+/// convert_qubo_file("test_convert.qubob", "test_convert.qubo").expect("could not convert");
+/// ```
+///
+/// # Errors
+///
+/// Returns a `QuboIOError` if the input can not be read, if the output can not be written, or
+/// `QuboIOError::UnsupportedExtension` if the output extension is not supported.
+pub fn convert_qubo_file(input: &str, output: &str) -> Result<(), QuboIOError> {
+    let p = read_qubo_file(input)?;
+
+    match file_format(output) {
+        Some(QuboFileFormat::CompressedBinary) => p.try_write_binary(output, true),
+        Some(QuboFileFormat::Binary) => p.try_write_binary(output, false),
+        Some(QuboFileFormat::Text) => p.try_write_qubo(output),
+        Some(QuboFileFormat::Qplib) | None => Err(QuboIOError::UnsupportedExtension {
+            path: output.to_string(),
+        }),
+    }
+}
+
+/// Reads a QUBO file in any of the supported formats, the format is picked from the extension as in
+/// `convert_qubo_file`, and files with any other extension are read as text
+///
+/// # Errors
+///
+/// Returns a `QuboIOError` if the file can not be read.
+pub fn read_qubo_file(path: &str) -> Result<Qubo, QuboIOError> {
+    match file_format(path) {
+        Some(QuboFileFormat::Binary | QuboFileFormat::CompressedBinary) => {
+            Qubo::try_read_binary_mmap(path)
+        }
+        Some(QuboFileFormat::Qplib) => Qubo::try_read_qplib(path),
+        Some(QuboFileFormat::Text) | None => Qubo::try_read_qubo(path),
+    }
+}
+
+/// The file formats that `convert_qubo_file` and `read_qubo_file` pick between
+enum QuboFileFormat {
+    Text,
+    Qplib,
+    Binary,
+    CompressedBinary,
+}
+
+/// Picks the file format from the extension of the path, ignoring case
+fn file_format(path: &str) -> Option<QuboFileFormat> {
+    let extension = |path: &Path| {
+        path.extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_lowercase)
+    };
+    let path = Path::new(path);
+
+    match extension(path)?.as_str() {
+        "qubo" => Some(QuboFileFormat::Text),
+        "qplib" => Some(QuboFileFormat::Qplib),
+        "qubob" => Some(QuboFileFormat::Binary),
+        "gz" if extension(Path::new(path.file_stem()?)).as_deref() == Some("qubob") => {
+            Some(QuboFileFormat::CompressedBinary)
+        }
+        _ => None,
+    }
+}
+
+/// Writes the payload of the binary format, the CSR arrays of the Hessian and then the linear term
+fn write_payload<W: Write>(writer: &mut W, q: &CsMat<f64>, c: &Array1<f64>) -> std::io::Result<()> {
+    for &index in q.proper_indptr().iter() {
+        writer.write_all(&(index as u64).to_le_bytes())?;
+    }
+
+    for &index in q.indices() {
+        writer.write_all(&(index as u64).to_le_bytes())?;
+    }
+
+    for &value in q.data() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    for &value in c {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Checks the payload against the header and rebuilds the QUBO from it
+fn decode_payload(path: &str, header: &BinaryHeader, payload: &[u8]) -> Result<Qubo, QuboIOError> {
+    if payload.len() < header.payload_size {
+        return Err(truncated(path));
+    }
+    let payload = &payload[..header.payload_size];
+
+    let found = crc32fast::hash(payload);
+    if found != header.checksum {
+        return Err(QuboIOError::ChecksumMismatch {
+            path: path.to_string(),
+            expected: header.checksum,
+            found,
+        });
+    }
+
+    // split the payload into the four arrays
+    let (indptr, rest) = payload.split_at(8 * (header.num_x + 1));
+    let (indices, rest) = rest.split_at(8 * header.nnz);
+    let (data, c) = rest.split_at(8 * header.nnz);
+
+    let indptr: Vec<usize> = indptr
+        .chunks_exact(8)
+        .map(|b| read_u64(b) as usize)
+        .collect();
+    let indices: Vec<usize> = indices
+        .chunks_exact(8)
+        .map(|b| read_u64(b) as usize)
+        .collect();
+    let data: Vec<f64> = data.chunks_exact(8).map(read_f64).collect();
+    let c: Array1<f64> = c.chunks_exact(8).map(read_f64).collect();
+
+    let q = CsMat::try_new((header.num_x, header.num_x), indptr, indices, data).map_err(
+        |(_, _, _, err)| QuboIOError::InvalidBinary {
+            path: path.to_string(),
+            reason: format!("the Hessian is not a valid CSR matrix, {err}"),
+        },
+    )?;
+
+    Ok(Qubo::new_with_offset(q, c, header.offset))
+}
+
+/// The error for a file that ends before the header or payload is complete
+fn truncated(path: &str) -> QuboIOError {
+    QuboIOError::InvalidBinary {
+        path: path.to_string(),
+        reason: "the file is truncated".to_string(),
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn read_f64(bytes: &[u8]) -> f64 {
+    f64::from_le_bytes(bytes.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io_error::QuboIOError;
+    use crate::qubo::Qubo;
+    use crate::qubo_binary::{convert_qubo_file, read_qubo_file};
+    use crate::tests::{make_solver_qubo, write_temp_file};
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        path.to_str().unwrap().to_string()
+    }
+
+    fn assert_same_qubo(p: &Qubo, q: &Qubo) {
+        assert_eq!(p.q, q.q);
+        assert_eq!(p.c, q.c);
+        assert!((p.offset - q.offset).abs() < 1E-12);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut p = make_solver_qubo();
+        p.offset = 2.5;
+
+        for compress in [false, true] {
+            let path = temp_path(&format!("hercules_round_trip_{compress}.qubob"));
+            p.try_write_binary(&path, compress).unwrap();
+
+            assert_same_qubo(&p, &Qubo::try_read_binary(&path).unwrap());
+            assert_same_qubo(&p, &Qubo::try_read_binary_mmap(&path).unwrap());
+        }
+    }
+
+    #[test]
+    fn binary_csc_hessian() {
+        let p = make_solver_qubo();
+        let p_csc = Qubo::new_with_c(p.q.to_csc(), p.c.clone());
+
+        let path = temp_path("hercules_csc.qubob");
+        p_csc.try_write_binary(&path, false).unwrap();
+
+        assert_same_qubo(&p, &Qubo::try_read_binary(&path).unwrap());
+    }
+
+    #[test]
+    fn binary_checksum_mismatch() {
+        let p = make_solver_qubo();
+        let path = temp_path("hercules_corrupt.qubob");
+        p.try_write_binary(&path, false).unwrap();
+
+        // flip a bit in the last value of the linear term
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(&path, bytes).unwrap();
+
+        for result in [
+            Qubo::try_read_binary(&path),
+            Qubo::try_read_binary_mmap(&path),
+        ] {
+            let err = result.map(|_| ()).unwrap_err();
+            assert!(matches!(err, QuboIOError::ChecksumMismatch { .. }));
+        }
+    }
+
+    #[test]
+    fn binary_truncated_and_bad_magic() {
+        let path = write_temp_file("hercules_not_binary.qubob", "3\n0 0 1.0\n");
+        let err = Qubo::try_read_binary(&path).map(|_| ()).unwrap_err();
+        assert!(matches!(err, QuboIOError::InvalidBinary { .. }));
+
+        let p = make_solver_qubo();
+        let path = temp_path("hercules_truncated.qubob");
+        p.try_write_binary(&path, false).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+
+        let err = Qubo::try_read_binary_mmap(&path).map(|_| ()).unwrap_err();
+        assert!(matches!(err, QuboIOError::InvalidBinary { .. }));
+    }
+
+    #[test]
+    fn convert_text_round_trip() {
+        let mut p = make_solver_qubo();
+        p.offset = -1.0;
+
+        let text = temp_path("hercules_convert.qubo");
+        let binary = temp_path("hercules_convert.qubob.gz");
+        let text_again = temp_path("hercules_convert_again.qubo");
+
+        p.try_write_qubo(&text).unwrap();
+        convert_qubo_file(&text, &binary).unwrap();
+        convert_qubo_file(&binary, &text_again).unwrap();
+
+        assert_same_qubo(
+            &Qubo::try_read_qubo(&text).unwrap(),
+            &Qubo::read_binary(&binary),
+        );
+        assert_same_qubo(
+            &Qubo::try_read_qubo(&text).unwrap(),
+            &Qubo::try_read_qubo(&text_again).unwrap(),
+        );
+    }
+
+    #[test]
+    fn read_by_extension() {
+        let p = make_solver_qubo();
+        let path = temp_path("hercules_by_extension.qubob.gz");
+        p.try_write_binary(&path, true).unwrap();
+        assert_same_qubo(&p, &read_qubo_file(&path).unwrap());
+
+        // only .qubob.gz is a compressed binary file, any other extension is read as text
+        for name in [
+            "hercules_by_extension.qubo.gz",
+            "hercules_by_extension.txt",
+            "hercules_by_extension",
+        ] {
+            let path = write_temp_file(name, "3\n0 0 1.0\n");
+            let q = read_qubo_file(&path).unwrap();
+            assert_eq!(q.num_x(), 3);
+            assert!((q.q.get(0, 0).unwrap() - 1.0).abs() < 1E-12);
+        }
+
+        // but only the known formats can be written
+        let output = temp_path("hercules_by_extension.txt");
+        let err = convert_qubo_file(&path, &output).unwrap_err();
+        assert!(matches!(err, QuboIOError::UnsupportedExtension { ref path } if *path == output));
+        assert_eq!(err.line(), None);
+    }
+}
@@ -1,8 +1,12 @@
 use crate::branch_stratagy::BranchStrategySelection;
 use crate::branch_subproblem::SubProblemSelection;
+use crate::preprocess::ProbingOptions;
 use std::collections::HashMap;
 
-/// Options for the B&B solver for run time
+/// Options for the B&B solver for run time, missing fields take their default value when deserialized
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct SolverOptions {
     pub fixed_variables: HashMap<usize, usize>,
     pub branch_strategy: BranchStrategySelection,
@@ -11,6 +15,8 @@ pub struct SolverOptions {
     pub seed: usize,
     pub verbose: usize,
     pub threads: usize,
+    /// The limits of probing in the presolve, where the incumbent is set by the solver
+    pub probing: ProbingOptions,
 }
 
 impl SolverOptions {
@@ -23,6 +29,7 @@ impl SolverOptions {
             seed: 0,
             verbose: 1,
             threads: 1,
+            probing: ProbingOptions::new(),
         }
     }
 
@@ -51,3 +58,9 @@ impl SolverOptions {
         }
     }
 }
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -7,7 +7,7 @@ use std::collections::HashMap;
 /// Helper function to get d_ih, where d_ih = p.q[i,h] + p.q[h,i]
 pub fn get_dih(p: &Qubo, i: usize, h: usize) -> f64 {
     let q_ih = *(p.q.get(i, h).unwrap_or(&0.0));
-    let q_hi = *(p.q.get(i, h).unwrap_or(&0.0));
+    let q_hi = *(p.q.get(h, i).unwrap_or(&0.0));
     q_ih + q_hi
 }
 
@@ -17,17 +17,24 @@ pub fn get_dih(p: &Qubo, i: usize, h: usize) -> f64 {
 /// Assume d_ih > 0, if c_i +d_ih + D-_ >= 0 then x_i >= x_h
 ///
 /// The rule we are implimenting is the following (this is due to us doing min while they are doing max)
-/// Assume d_ih < 0, if c_i +d_ih + D+_ > 0 then x_i <= x_h
+/// Assume d_ih < 0, if D-_ - d_ih / 2 > 0 then x_i <= x_h
+///
+/// Here D-_ is the lower bound of the gradient of x_i from `grad_bounds`, which includes c_i. If x_h = 0, then the
+/// negative coupling is not there and flipping x_i to 1 always increases the objective, so x_i = 0.
 pub fn generate_rule_11(p: &Qubo, fixed: &HashMap<usize, usize>, i: usize) -> Vec<Constraint> {
     let d_i = (0..p.num_x())
         .map(|h| get_dih(p, i, h))
         .collect::<Array1<f64>>();
-    let (D_plus, _) = grad_bounds(p, i, fixed, false);
+    let (D_minus, _) = grad_bounds(p, i, fixed, true);
 
     let mut generated_rules = vec![];
 
     for h in 0..p.num_x() {
-        if d_i[h] < 0.0 && d_i[h] + D_plus > 0.0 {
+        if h == i || fixed.contains_key(&h) {
+            continue;
+        }
+
+        if d_i[h] < 0.0 && D_minus - 0.5 * d_i[h] > 0.0 {
             let rule = Constraint::new(i, h, ConstraintType::LessThan);
             generated_rules.push(rule);
         }
@@ -37,20 +44,26 @@ pub fn generate_rule_11(p: &Qubo, fixed: &HashMap<usize, usize>, i: usize) -> Ve
 }
 
 /// Implement Rule 2.1 from the paper glover2018
+///
+/// The rule we are implimenting is the following, in the same min form as Rule 1.1
+/// Assume d_ih > 0, if D+_ - d_ih / 2 < 0 then x_i + x_h >= 1
+///
+/// Here D+_ is the upper bound of the gradient of x_i from `grad_bounds`. If x_h = 0, then the positive coupling is
+/// not there and flipping x_i to 1 always decreases the objective, so x_i = 1.
 pub fn generate_rule_21(p: &Qubo, fixed: &HashMap<usize, usize>, i: usize) -> Vec<Constraint> {
     let d_i = (0..p.num_x())
         .map(|h| get_dih(p, i, h))
         .collect::<Array1<f64>>();
-    let (_, D_minus) = grad_bounds(p, i, fixed, false);
+    let (_, D_plus) = grad_bounds(p, i, fixed, true);
 
     let mut generated_rules = vec![];
 
     for h in 0..p.num_x() {
-        if h == i {
+        if h == i || fixed.contains_key(&h) {
             continue;
         }
 
-        if d_i[h] > 0.0 && d_i[h] + D_minus > 0.0 {
+        if d_i[h] > 0.0 && D_plus - 0.5 * d_i[h] < 0.0 {
             let rule = Constraint::new(i, h, ConstraintType::AtLeastOne);
             generated_rules.push(rule);
         }